@@ -0,0 +1,2 @@
+pub mod alias_resolver;
+mod alias_sets;
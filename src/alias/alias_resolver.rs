@@ -0,0 +1,281 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::IpAddr;
+
+use log::{info, warn};
+
+use crate::alias::alias_sets::read_alias_sets;
+use crate::common::structs::parse_data::{CsvEdgeObservation, CsvNodeMapping};
+use crate::preprocess::node_registry::ip_from_numeric;
+use crate::preprocess::parser::ip_to_numeric;
+use crate::{DatasetConfig, IpType, OutputPaths};
+
+pub struct AliasResolver {
+    config: DatasetConfig,
+    output_paths: OutputPaths,
+}
+
+impl AliasResolver {
+    pub fn new(config: &DatasetConfig, output_paths: &OutputPaths) -> AliasResolver {
+        AliasResolver {
+            config: config.clone(),
+            output_paths: output_paths.clone(),
+        }
+    }
+
+    /**
+     * Collapses the interfaces of every router of the configured alias sets into one node, which
+     * takes the smallest node ID of its interfaces. The edges are rewritten accordingly, edges
+     * between interfaces of the same router are dropped. mapping.csv stays on interface level,
+     * so the IP listed for a router node is the one of the interface that lends its ID.
+     * Interfaces that appear in several alias sets are counted and logged (-> see RouterAssignment).
+     *
+     * The original edges.csv is kept as edges_interface.csv and serves as input for later runs,
+     * until the merge step generates a new edges.csv.
+     *
+     *  Requires: edges.csv, mapping.csv, alias sets at dataset.alias_path
+     * Generates: edges.csv (router level), edges_interface.csv,
+     *            ip_to_router.csv (ip,node_id,router_id,router_node_id)
+     */
+    pub fn resolve_aliases(&self) {
+        let alias_path = match &self.config.alias_path {
+            Some(path) => path,
+            None => {
+                info!("No alias_path configured - skipping alias resolution.");
+                return;
+            }
+        };
+
+        info!("Step: Collapsing interfaces into routers using alias sets at {}.", alias_path.to_str().unwrap());
+
+        let mapping = self.load_mapping();
+        let node_ids: HashMap<u128, i64> = mapping.iter().map(|&(node_id, ip)| (ip, node_id)).collect();
+
+        let mut assignment = RouterAssignment::new();
+        read_alias_sets(alias_path, |router_id, interfaces| {
+            let members: Vec<i64> = interfaces.iter()
+                .filter(|ip| self.matches_address_type(ip))
+                .filter_map(|&ip| node_ids.get(&ip_to_numeric(ip)).copied())
+                .collect();
+            assignment.add(router_id, &members);
+        });
+        if !assignment.conflicting_interfaces.is_empty() {
+            warn!(
+                "{} interfaces appear in more than one alias set, keeping the router with the smallest node ID.",
+                assignment.conflicting_interfaces.len()
+            );
+        }
+        let (routers, router_count) = assignment.finish();
+        info!("Found {} routers covering {} of {} known interfaces.", router_count, routers.len(), mapping.len());
+
+        self.write_ip_to_router(&mapping, &routers);
+        drop(mapping);
+        drop(node_ids);
+
+        let router_of: HashMap<i64, i64> = routers.into_iter()
+            .map(|(node_id, (router_node_id, _))| (node_id, router_node_id))
+            .filter(|(node_id, router_node_id)| node_id != router_node_id)
+            .collect();
+        self.rewrite_edges(&router_of);
+    }
+
+    fn matches_address_type(&self, ip: &IpAddr) -> bool {
+        matches!((ip, &self.config.address_type), (IpAddr::V4(_), IpType::V4) | (IpAddr::V6(_), IpType::V6))
+    }
+
+    /**
+     * Returns (node ID, IP) of all known nodes, sorted by node ID.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn load_mapping(&self) -> Vec<(i64, u128)> {
+        let mapping_path = &self.output_paths.mapping;
+        let mut reader = csv::Reader::from_path(mapping_path).expect(&format!(
+            "Could not read node mapping at {}",
+            mapping_path.to_str().unwrap()
+        ));
+
+        let mut mapping: Vec<(i64, u128)> = reader.deserialize()
+            .map(|row: Result<CsvNodeMapping, _>| row.expect("Invalid row in node mapping"))
+            .map(|row| (row.node_id, ip_to_numeric(row.ip)))
+            .collect();
+        mapping.sort_unstable();
+
+        mapping
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn write_ip_to_router(&self, mapping: &[(i64, u128)], routers: &HashMap<i64, (i64, String)>) {
+        let path = &self.output_paths.ip_to_router;
+        let mut writer = csv::Writer::from_path(path).expect(&format!(
+            "Could not create file for storing the IP to router mapping at {}",
+            path.to_str().unwrap()
+        ));
+
+        writer.serialize(("ip", "node_id", "router_id", "router_node_id")).unwrap();
+        for &(node_id, ip) in mapping {
+            let ip = ip_from_numeric(ip, &self.config.address_type);
+            match routers.get(&node_id) {
+                Some((router_node_id, router_id)) => {
+                    writer.serialize((ip, node_id, router_id, router_node_id)).unwrap();
+                }
+                None => writer.serialize((ip, node_id, "", node_id)).unwrap(),
+            }
+        }
+        writer.flush().unwrap();
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn rewrite_edges(&self, router_of: &HashMap<i64, i64>) {
+        let edges_path = &self.output_paths.edges;
+        let interface_edges_path = &self.output_paths.edges_interface;
+        if interface_edges_path.exists() {
+            info!("Reading interface-level edges of a previous alias resolution from {}",
+                interface_edges_path.to_str().unwrap());
+        } else {
+            fs::rename(edges_path, interface_edges_path).unwrap();
+        }
+
+        let mut reader = csv::Reader::from_path(interface_edges_path).unwrap();
+        let mut writer = csv::Writer::from_path(edges_path).expect(&format!(
+            "Could not create file for storing edges at {}",
+            edges_path.to_str().unwrap()
+        ));
+
+        let router_node_id = |node_id: i64| router_of.get(&node_id).copied().unwrap_or(node_id);
+
+        let mut self_loop_count: u64 = 0;
+        writer.serialize(("from", "to", "rtt", "rtt_delta")).unwrap();
+        for edge in reader.deserialize() {
+            let edge: CsvEdgeObservation = edge.unwrap();
+            let from = router_node_id(edge.from);
+            let to = router_node_id(edge.to);
+
+            if from == to {
+                self_loop_count += 1;
+                continue;
+            }
+            writer.serialize((from, to, edge.rtt, edge.rtt_delta)).unwrap();
+        }
+        writer.flush().unwrap();
+
+        info!("Rewrote edges to router level, dropped {} edges within routers.", self_loop_count);
+    }
+}
+
+/**
+ * Assigns the interfaces to the routers of the alias sets. An interface that appears in several
+ * alias sets is kept in the set with the smallest (smallest member, router ID), so the assignment
+ * does not depend on the order of the sets.
+ */
+struct RouterAssignment {
+    // node ID of every interface -> (smallest member of its alias set, router ID)
+    routers: HashMap<i64, (i64, String)>,
+    conflicting_interfaces: HashSet<i64>,
+}
+
+impl RouterAssignment {
+    fn new() -> RouterAssignment {
+        RouterAssignment {
+            routers: HashMap::new(),
+            conflicting_interfaces: HashSet::new(),
+        }
+    }
+
+    fn add(&mut self, router_id: &str, members: &[i64]) {
+        let smallest_member = match members.iter().min() {
+            Some(&node_id) => node_id,
+            None => return,
+        };
+
+        for &member in members {
+            let router = (smallest_member, router_id.to_string());
+            match self.routers.entry(member) {
+                Entry::Vacant(entry) => {
+                    entry.insert(router);
+                }
+                Entry::Occupied(mut entry) if *entry.get() != router => {
+                    self.conflicting_interfaces.insert(member);
+                    if router < *entry.get() {
+                        entry.insert(router);
+                    }
+                }
+                Entry::Occupied(_) => {}
+            }
+        }
+    }
+
+    /**
+     * Returns the (router node ID, router ID) of every interface and the number of routers. The
+     * router node ID is the smallest node ID of the interfaces kept in the alias set, which differs
+     * from the smallest member if that one was assigned to another set.
+     */
+    fn finish(self) -> (HashMap<i64, (i64, String)>, usize) {
+        let mut router_node_ids: HashMap<(i64, String), i64> = HashMap::new();
+        for (&node_id, router) in &self.routers {
+            router_node_ids.entry(router.clone())
+                .and_modify(|router_node_id| *router_node_id = node_id.min(*router_node_id))
+                .or_insert(node_id);
+        }
+
+        let router_count = router_node_ids.len();
+        let routers = self.routers.into_iter()
+            .map(|(node_id, router)| {
+                let router_node_id = router_node_ids[&router];
+                (node_id, (router_node_id, router.1))
+            })
+            .collect();
+
+        (routers, router_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assign(alias_sets: &[(&str, &[i64])]) -> Vec<(i64, i64, String)> {
+        let mut assignment = RouterAssignment::new();
+        for (router_id, members) in alias_sets {
+            assignment.add(router_id, members);
+        }
+
+        let (routers, _) = assignment.finish();
+        let mut routers: Vec<(i64, i64, String)> = routers.into_iter()
+            .map(|(node_id, (router_node_id, router_id))| (node_id, router_node_id, router_id))
+            .collect();
+        routers.sort();
+        routers
+    }
+
+    #[test]
+    fn conflicting_interfaces_keep_the_router_with_the_smallest_node_id() {
+        let alias_sets: [(&str, &[i64]); 3] = [("N1", &[3, 7]), ("N2", &[1, 3]), ("N3", &[8, 9, 8])];
+        let expected = vec![
+            (1, 1, "N2".to_string()),
+            (3, 1, "N2".to_string()),
+            // N1 lost node 3, so the remaining interface lends its own ID
+            (7, 7, "N1".to_string()),
+            (8, 8, "N3".to_string()),
+            (9, 8, "N3".to_string()),
+        ];
+
+        assert_eq!(assign(&alias_sets), expected);
+
+        let mut reversed = alias_sets;
+        reversed.reverse();
+        assert_eq!(assign(&reversed), expected);
+    }
+
+    #[test]
+    fn conflicts_are_counted_per_interface() {
+        let mut assignment = RouterAssignment::new();
+        assignment.add("N1", &[3, 7]);
+        assignment.add("N2", &[3, 8]);
+        assignment.add("N3", &[3, 9]);
+        assignment.add("N4", &[10, 10]);
+
+        assert_eq!(assignment.conflicting_interfaces.len(), 1);
+        assert_eq!(assignment.finish().1, 4);
+    }
+}
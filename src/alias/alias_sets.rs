@@ -0,0 +1,114 @@
+/*!
+ * Readers for the supported alias set formats. The format is chosen by the file extension:
+ *     - .nodes: CAIDA ITDK nodes file (node N1:  ip1 ip2 ...)
+ *     - .sets:  MIDAR alias sets (# set 1 2, followed by one IP per line)
+ *     - .csv:   router_id,ip with one interface per row
+ */
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::exit;
+
+use log::{debug, error};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct CsvAlias {
+    router_id: String,
+    ip: IpAddr,
+}
+
+/**
+ * Calls the consumer once for every alias set with the ID of the router and its interface IPs.
+ */
+pub fn read_alias_sets<F>(path: &Path, consumer: F)
+    where F: FnMut(&str, &[IpAddr]) {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+    match extension {
+        "nodes" => read_itdk_nodes(path, consumer),
+        "sets" => read_midar_sets(path, consumer),
+        "csv" => read_csv_aliases(path, consumer),
+        _ => {
+            error!("Unknown alias set format of {}, expected .nodes, .sets or .csv", path.to_str().unwrap());
+            exit(1);
+        }
+    }
+}
+
+fn open_lines(path: &Path) -> impl Iterator<Item = String> {
+    let file = File::open(path).unwrap_or_else(|_| {
+        error!("Could not open alias sets at {}", path.to_str().unwrap());
+        exit(1);
+    });
+    BufReader::new(file).lines().map(|line| line.unwrap())
+}
+
+fn parse_ips<'a>(tokens: impl Iterator<Item = &'a str>) -> Vec<IpAddr> {
+    tokens.filter_map(|token| match token.parse() {
+        Ok(ip) => Some(ip),
+        Err(_) => {
+            debug!("Skipping invalid IP in alias sets: {}", token);
+            None
+        }
+    }).collect()
+}
+
+fn read_itdk_nodes<F>(path: &Path, mut consumer: F)
+    where F: FnMut(&str, &[IpAddr]) {
+    for line in open_lines(path) {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let parsed = line.strip_prefix("node ").and_then(|rest| rest.split_once(':'));
+        match parsed {
+            Some((router_id, interfaces)) => {
+                consumer(router_id.trim(), &parse_ips(interfaces.split_whitespace()));
+            }
+            None if line.trim().is_empty() => {}
+            None => debug!("Skipping invalid line in ITDK nodes: {}", line),
+        }
+    }
+}
+
+fn read_midar_sets<F>(path: &Path, mut consumer: F)
+    where F: FnMut(&str, &[IpAddr]) {
+    let mut current_set: Option<String> = None;
+    let mut interfaces: Vec<IpAddr> = Vec::new();
+
+    for line in open_lines(path) {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix("# set ") {
+            if let Some(set_id) = current_set.take() {
+                consumer(&set_id, &interfaces);
+            }
+            interfaces.clear();
+            current_set = header.split_whitespace().next().map(str::to_string);
+        } else if !line.is_empty() && !line.starts_with('#') && current_set.is_some() {
+            interfaces.extend(parse_ips(std::iter::once(line)));
+        }
+    }
+
+    if let Some(set_id) = current_set {
+        consumer(&set_id, &interfaces);
+    }
+}
+
+#[allow(clippy::expect_fun_call)]
+fn read_csv_aliases<F>(path: &Path, mut consumer: F)
+    where F: FnMut(&str, &[IpAddr]) {
+    let mut reader = csv::Reader::from_path(path).unwrap();
+
+    let mut routers: BTreeMap<String, Vec<IpAddr>> = BTreeMap::new();
+    for row in reader.deserialize() {
+        let row: CsvAlias = row.expect(&format!("Invalid row in alias sets {}", path.to_str().unwrap()));
+        routers.entry(row.router_id).or_default().push(row.ip);
+    }
+
+    for (router_id, interfaces) in routers {
+        consumer(&router_id, &interfaces);
+    }
+}
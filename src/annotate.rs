@@ -0,0 +1,5 @@
+pub mod as_annotator;
+pub mod geo_annotator;
+pub mod prefix_trie;
+pub mod node_columns;
+mod geo_database;
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::exit;
+
+use log::{debug, error, info};
+
+use crate::annotate::node_columns::{annotated_files, set_node_column};
+use crate::annotate::prefix_trie::PrefixTrie;
+use crate::common::structs::parse_data::CsvNodeMapping;
+use crate::{DatasetConfig, IpType, OutputPaths};
+use crate::preprocess::parser::ip_to_numeric;
+
+pub const ASN_COLUMN: &str = "asn";
+
+pub struct AsAnnotator {
+    config: DatasetConfig,
+    output_paths: OutputPaths,
+}
+
+impl AsAnnotator {
+    pub fn new(config: &DatasetConfig, output_paths: &OutputPaths) -> AsAnnotator {
+        AsAnnotator {
+            config: config.clone(),
+            output_paths: output_paths.clone(),
+        }
+    }
+
+    /**
+     * Looks up the origin AS of every known node by longest-prefix match in the configured
+     * prefix-to-AS file and adds it as asn column to mapping.csv and all statistic outputs
+     * (directed and undirected). Unknown nodes and IPs without a matching prefix get no ASN.
+     *
+     *  Requires: mapping.csv, prefix-to-AS file at dataset.prefix_to_as_path
+     * Generates: asn column in mapping.csv, degree.csv, betweenness.csv, ...
+     */
+    pub fn annotate(&self) {
+        let prefix_to_as_path = match &self.config.prefix_to_as_path {
+            Some(path) => path,
+            None => {
+                info!("No prefix_to_as_path configured - skipping AS annotation.");
+                return;
+            }
+        };
+
+        let trie = load_prefix_to_as(prefix_to_as_path, &self.config.address_type);
+        let asns = self.lookup_nodes(&trie);
+
+        for path in annotated_files(&self.output_paths) {
+            set_node_column(&path, ASN_COLUMN, &asns);
+        }
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn lookup_nodes(&self, trie: &PrefixTrie<u32>) -> HashMap<i64, u32> {
+        let mapping_path = &self.output_paths.mapping;
+        let mut reader = csv::Reader::from_path(mapping_path).expect(&format!(
+            "Could not read node mapping at {}",
+            mapping_path.to_str().unwrap()
+        ));
+
+        let mut node_count = 0;
+        let asns: HashMap<i64, u32> = reader.deserialize()
+            .map(|row: Result<CsvNodeMapping, _>| row.expect("Invalid row in node mapping"))
+            .inspect(|_| node_count += 1)
+            .filter_map(|row| {
+                trie.longest_match(ip_to_numeric(row.ip)).map(|&asn| (row.node_id, asn))
+            })
+            .collect();
+
+        info!("Found an AS for {} of {} known nodes.", asns.len(), node_count);
+        asns
+    }
+}
+
+/**
+ * Reads a prefix-to-AS file in the format of CAIDA's pfx2as (prefix, length and ASN separated by
+ * whitespace), also accepting prefixes in CIDR notation (prefix/length ASN). For prefixes with
+ * multiple origins (1_2) or AS sets (1,2), the first AS is used. Comments (#), malformed lines and
+ * prefixes of the other IP version are skipped.
+ */
+pub fn load_prefix_to_as(path: &Path, address_type: &IpType) -> PrefixTrie<u32> {
+    info!("Loading prefix-to-AS file from {}...", path.to_str().unwrap());
+
+    let file = File::open(path).unwrap_or_else(|_| {
+        error!("Could not open prefix-to-AS file at {}", path.to_str().unwrap());
+        exit(1);
+    });
+
+    let address_bits = if address_type == &IpType::V4 { 32 } else { 128 };
+    let mut trie = PrefixTrie::new(address_bits);
+    let mut prefix_count = 0;
+    let mut skipped_count = 0;
+    for line in BufReader::new(file).lines() {
+        let line = line.unwrap();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match parse_prefix_line(&line) {
+            Some((ip, prefix_length, asn)) if ip_matches_type(&ip, address_type) && prefix_length <= address_bits => {
+                trie.insert(ip_to_numeric(ip), prefix_length, asn);
+                prefix_count += 1;
+            }
+            _ => {
+                debug!("Skipping prefix-to-AS line: {}", line);
+                skipped_count += 1;
+            }
+        }
+    }
+
+    info!("Loaded {} prefixes, skipped {} lines.", prefix_count, skipped_count);
+    trie
+}
+
+fn parse_prefix_line(line: &str) -> Option<(IpAddr, u8, u32)> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    let (prefix, prefix_length, asn) = match tokens.as_slice() {
+        [cidr, asn, ..] if cidr.contains('/') => {
+            let (prefix, prefix_length) = cidr.split_once('/')?;
+            (prefix, prefix_length, *asn)
+        }
+        [prefix, prefix_length, asn, ..] => (*prefix, *prefix_length, *asn),
+        _ => return None,
+    };
+
+    let first_asn = asn.split(['_', ',']).next()?;
+    Some((prefix.parse().ok()?, prefix_length.parse().ok()?, first_asn.parse().ok()?))
+}
+
+fn ip_matches_type(ip: &IpAddr, address_type: &IpType) -> bool {
+    matches!((ip, address_type), (IpAddr::V4(_), IpType::V4) | (IpAddr::V6(_), IpType::V6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cidr_and_caida_prefix_lines() {
+        assert_eq!(parse_prefix_line("1.0.0.0/24 13335"), Some(("1.0.0.0".parse().unwrap(), 24, 13335)));
+        assert_eq!(parse_prefix_line("1.0.4.0\t22\t38803"), Some(("1.0.4.0".parse().unwrap(), 22, 38803)));
+        assert_eq!(parse_prefix_line("2001:db8::\t32\t64496"), Some(("2001:db8::".parse().unwrap(), 32, 64496)));
+    }
+
+    #[test]
+    fn uses_the_first_of_multiple_origins() {
+        assert_eq!(parse_prefix_line("1.0.0.0 24 64496_64497").map(|(_, _, asn)| asn), Some(64496));
+        assert_eq!(parse_prefix_line("1.0.0.0 24 64498,64499").map(|(_, _, asn)| asn), Some(64498));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(parse_prefix_line("1.0.0.0/24"), None);
+        assert_eq!(parse_prefix_line("1.0.0 24 13335"), None);
+        assert_eq!(parse_prefix_line("1.0.0.0 x 13335"), None);
+        assert_eq!(parse_prefix_line("1.0.0.0/24 AS13335"), None);
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::fs::File;
+
+use csv::Writer;
+use log::{info, warn};
+
+use crate::annotate::geo_database::{GeoDatabase, GeoLocation};
+use crate::annotate::node_columns::{annotated_files, set_node_column};
+use crate::common::structs::parse_data::CsvNodeMapping;
+use crate::contract::graph_contractor::{write_contracted_edges, GraphContractor};
+use crate::graph::common::graph::Graph;
+use crate::{DatasetConfig, GeoParameters, OutputPaths};
+
+// subgraph of all countries whose code cannot be used as file name
+const UNKNOWN_COUNTRY_FILE: &str = "unknown.csv";
+
+pub struct GeoAnnotator {
+    config: DatasetConfig,
+    output_paths: OutputPaths,
+    params: GeoParameters,
+}
+
+impl GeoAnnotator {
+    pub fn new(config: &DatasetConfig, output_paths: &OutputPaths, params: &GeoParameters) -> GeoAnnotator {
+        GeoAnnotator {
+            config: config.clone(),
+            output_paths: output_paths.clone(),
+            params: params.clone(),
+        }
+    }
+
+    /**
+     * Looks up the location of every known node in the configured geolocation database and adds
+     * the columns country, city, latitude and longitude to mapping.csv and all statistic outputs.
+     * Optionally, the edges between countries and the subgraph of every country are written.
+     *
+     *  Requires: mapping.csv, geolocation database at dataset.geo_db_path,
+     *            edges_deduplicated.csv for the optional outputs
+     * Generates: geo columns in mapping.csv, degree.csv, betweenness.csv, ...
+     *            cross_border_edges.csv (from_country,to_country,edge_count), if enabled
+     *            country_subgraphs/<country>.csv (same columns as edges_deduplicated.csv), if enabled
+     */
+    pub fn annotate(&self) {
+        let geo_db_path = match &self.config.geo_db_path {
+            Some(path) => path,
+            None => {
+                info!("No geo_db_path configured - skipping geolocation annotation.");
+                return;
+            }
+        };
+
+        let database = GeoDatabase::open(geo_db_path, &self.config.address_type);
+        let locations = self.lookup_nodes(&database);
+
+        let countries: HashMap<i64, String> = Self::column(&locations, |location| location.country.clone());
+        let cities: HashMap<i64, String> = Self::column(&locations, |location| location.city.clone());
+        let latitudes: HashMap<i64, f64> = Self::column(&locations, |location| location.latitude);
+        let longitudes: HashMap<i64, f64> = Self::column(&locations, |location| location.longitude);
+        drop(locations);
+
+        for path in annotated_files(&self.output_paths) {
+            set_node_column(&path, "country", &countries);
+            set_node_column(&path, "city", &cities);
+            set_node_column(&path, "latitude", &latitudes);
+            set_node_column(&path, "longitude", &longitudes);
+        }
+
+        if self.params.cross_border_edges {
+            self.write_cross_border_edges(&countries);
+        }
+        if self.params.country_subgraphs {
+            self.write_country_subgraphs(&countries);
+        }
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn lookup_nodes(&self, database: &GeoDatabase) -> Vec<(i64, GeoLocation)> {
+        let mapping_path = &self.output_paths.mapping;
+        let mut reader = csv::Reader::from_path(mapping_path).expect(&format!(
+            "Could not read node mapping at {}",
+            mapping_path.to_str().unwrap()
+        ));
+
+        let mut node_count = 0;
+        let locations: Vec<(i64, GeoLocation)> = reader.deserialize()
+            .map(|row: Result<CsvNodeMapping, _>| row.expect("Invalid row in node mapping"))
+            .inspect(|_| node_count += 1)
+            .filter_map(|row| database.lookup(row.ip).map(|location| (row.node_id, location)))
+            .collect();
+
+        info!("Found a location for {} of {} known nodes.", locations.len(), node_count);
+        locations
+    }
+
+    fn column<V, F>(locations: &[(i64, GeoLocation)], value: F) -> HashMap<i64, V>
+        where F: Fn(&GeoLocation) -> Option<V> {
+        locations.iter()
+            .filter_map(|(node_id, location)| value(location).map(|value| (*node_id, value)))
+            .collect()
+    }
+
+    /**
+     * Contracts the graph to country level, like the AS-level graph. The edge count is the number
+     * of node-level edges between the two countries.
+     */
+    fn write_cross_border_edges(&self, countries: &HashMap<i64, String>) {
+        info!("Counting cross-border edges ({:?} nodes without location).", self.params.unknown_nodes);
+
+        let mut country_codes: Vec<&String> = countries.values().collect();
+        country_codes.sort_unstable();
+        country_codes.dedup();
+
+        let groups: HashMap<i64, i64> = countries.iter()
+            .map(|(&node_id, country)| (node_id, country_codes.binary_search(&country).unwrap() as i64))
+            .collect();
+
+        let graph = Graph::new(
+            &self.output_paths,
+            /* from_deduplicated = */ true,
+            /* undirected = */ false
+        );
+        let country_edges = GraphContractor::new(&groups, self.params.unknown_nodes.clone()).contract(&graph);
+
+        // The contractor numbers the groups, the output names the countries again
+        let named_edges: Vec<(&String, &String, u64)> = country_edges.into_iter()
+            .map(|(from, to, weight)| (country_codes[from as usize], country_codes[to as usize], weight))
+            .collect();
+        write_contracted_edges(
+            &self.output_paths.cross_border_edges,
+            ("from_country", "to_country", "edge_count"),
+            &named_edges
+        );
+    }
+
+    /**
+     * Writes the edges whose nodes are located in the same country into one file per country.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn write_country_subgraphs(&self, countries: &HashMap<i64, String>) {
+        let directory = &self.output_paths.country_subgraphs;
+        info!("Writing country subgraphs to {}...", directory.to_str().unwrap());
+        fs::create_dir_all(directory).expect("Could not create directory for country subgraphs");
+
+        let mut reader = csv::Reader::from_path(&self.output_paths.edges_deduplicated).unwrap();
+        let headers = reader.headers().unwrap().clone();
+
+        let mut writers: BTreeMap<String, Writer<File>> = BTreeMap::new();
+        for record in reader.records() {
+            let record = record.unwrap();
+            let from_country = record[0].parse().ok().and_then(|from: i64| countries.get(&from));
+            let to_country = record[1].parse().ok().and_then(|to: i64| countries.get(&to));
+
+            if let (Some(from_country), Some(to_country)) = (from_country, to_country) {
+                if from_country != to_country {
+                    continue;
+                }
+
+                let writer = writers.entry(country_file_name(from_country)).or_insert_with_key(|file_name| {
+                    if file_name == UNKNOWN_COUNTRY_FILE {
+                        warn!("Some country codes are not valid file names, writing their subgraphs to {}", file_name);
+                    }
+                    let path = directory.join(file_name);
+                    let mut writer = csv::Writer::from_path(&path).expect(&format!(
+                        "Could not create file for storing country subgraph at {}",
+                        path.to_str().unwrap()
+                    ));
+                    writer.write_record(&headers).unwrap();
+                    writer
+                });
+                writer.write_record(&record).unwrap();
+            }
+        }
+
+        for writer in writers.values_mut() {
+            writer.flush().unwrap();
+        }
+        info!("Wrote subgraphs of {} countries.", writers.len());
+    }
+}
+
+/**
+ * The file name of the subgraph of a country. The country codes stem from the geolocation database, so
+ * anything but letters, digits, '_' and '-' (e.g. a path or an empty code) goes to unknown.csv.
+ */
+fn country_file_name(country: &str) -> String {
+    let is_valid = !country.is_empty()
+        && country.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if is_valid {
+        format!("{}.csv", country)
+    } else {
+        UNKNOWN_COUNTRY_FILE.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn country_file_names_stay_within_the_directory() {
+        assert_eq!(country_file_name("DE"), "DE.csv");
+        assert_eq!(country_file_name("EU-1_x"), "EU-1_x.csv");
+        assert_eq!(country_file_name(""), UNKNOWN_COUNTRY_FILE);
+        assert_eq!(country_file_name(".."), UNKNOWN_COUNTRY_FILE);
+        assert_eq!(country_file_name("../../etc/passwd"), UNKNOWN_COUNTRY_FILE);
+        assert_eq!(country_file_name("Côte d'Ivoire"), UNKNOWN_COUNTRY_FILE);
+    }
+}
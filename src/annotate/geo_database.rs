@@ -0,0 +1,135 @@
+/*!
+ * Local geolocation databases. Two formats are supported, chosen by the file extension:
+ *     - .mmdb: MaxMind DB in the layout of GeoIP2/GeoLite2 City
+ *     - .csv:  One row per network with the columns network (CIDR), country, city, latitude and
+ *              longitude, of which only network is mandatory.
+ */
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::exit;
+
+use log::{debug, error, info};
+use maxminddb::geoip2;
+
+use crate::annotate::prefix_trie::PrefixTrie;
+use crate::IpType;
+use crate::preprocess::parser::ip_to_numeric;
+
+#[derive(Clone, Default)]
+pub struct GeoLocation {
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+pub enum GeoDatabase {
+    Csv {
+        trie: PrefixTrie<u32>,
+        locations: Vec<GeoLocation>,
+    },
+    Mmdb(maxminddb::Reader<Vec<u8>>),
+}
+
+impl GeoDatabase {
+    pub fn open(path: &Path, address_type: &IpType) -> GeoDatabase {
+        info!("Loading geolocation database from {}...", path.to_str().unwrap());
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("mmdb") => {
+                let reader = maxminddb::Reader::open_readfile(path).unwrap_or_else(|e| {
+                    error!("Could not open MaxMind DB at {}: {}", path.to_str().unwrap(), e);
+                    exit(1);
+                });
+                GeoDatabase::Mmdb(reader)
+            }
+            Some("csv") => Self::load_csv(path, address_type),
+            _ => {
+                error!("Unknown geolocation database format of {}, expected .mmdb or .csv", path.to_str().unwrap());
+                exit(1);
+            }
+        }
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn load_csv(path: &Path, address_type: &IpType) -> GeoDatabase {
+        let mut reader = csv::Reader::from_path(path).unwrap_or_else(|_| {
+            error!("Could not open geolocation database at {}", path.to_str().unwrap());
+            exit(1);
+        });
+
+        let headers = reader.headers().unwrap().clone();
+        let position = |name: &str| headers.iter().position(|header| header == name);
+        let network_position = position("network").unwrap_or_else(|| {
+            error!("Geolocation database {} has no network column", path.to_str().unwrap());
+            exit(1);
+        });
+        let (country_position, city_position) = (position("country"), position("city"));
+        let (latitude_position, longitude_position) = (position("latitude"), position("longitude"));
+
+        let address_bits = if address_type == &IpType::V4 { 32 } else { 128 };
+        let mut trie = PrefixTrie::new(address_bits);
+        let mut locations = Vec::new();
+        for record in reader.records() {
+            let record = record.expect(&format!("Invalid row in {}", path.to_str().unwrap()));
+            let network = match parse_network(&record[network_position], address_type) {
+                Some(network) => network,
+                None => {
+                    debug!("Skipping network of geolocation database: {}", &record[network_position]);
+                    continue;
+                }
+            };
+
+            let text = |position: Option<usize>| position
+                .map(|position| record[position].to_string())
+                .filter(|value| !value.is_empty());
+            let number = |position: Option<usize>| position.and_then(|position| record[position].parse().ok());
+            locations.push(GeoLocation {
+                country: text(country_position),
+                city: text(city_position),
+                latitude: number(latitude_position),
+                longitude: number(longitude_position),
+            });
+
+            let (prefix, prefix_length) = network;
+            trie.insert(prefix, prefix_length, (locations.len() - 1) as u32);
+        }
+
+        info!("Loaded {} networks.", locations.len());
+        GeoDatabase::Csv { trie, locations }
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoLocation> {
+        match self {
+            GeoDatabase::Csv { trie, locations } => {
+                trie.longest_match(ip_to_numeric(ip)).map(|&position| locations[position as usize].clone())
+            }
+            GeoDatabase::Mmdb(reader) => {
+                let city: geoip2::City = reader.lookup(ip).ok()?;
+                let location = city.location.as_ref();
+
+                Some(GeoLocation {
+                    country: city.country.and_then(|country| country.iso_code).map(str::to_string),
+                    city: city.city
+                        .and_then(|city| city.names)
+                        .and_then(|names| names.get("en").map(|name| name.to_string())),
+                    latitude: location.and_then(|location| location.latitude),
+                    longitude: location.and_then(|location| location.longitude),
+                })
+            }
+        }
+    }
+}
+
+fn parse_network(network: &str, address_type: &IpType) -> Option<(u128, u8)> {
+    let (prefix, prefix_length) = network.split_once('/')?;
+    let prefix: IpAddr = prefix.parse().ok()?;
+    let prefix_length: u8 = prefix_length.parse().ok()?;
+
+    match (prefix, address_type) {
+        (IpAddr::V4(_), IpType::V4) if prefix_length <= 32 => Some((ip_to_numeric(prefix), prefix_length)),
+        (IpAddr::V6(_), IpType::V6) if prefix_length <= 128 => Some((ip_to_numeric(prefix), prefix_length)),
+        _ => None,
+    }
+}
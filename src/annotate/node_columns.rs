@@ -0,0 +1,104 @@
+/*!
+ * Adds columns to CSV files that are keyed by node_id (mapping.csv and the statistic outputs).
+ */
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::OutputPaths;
+
+const NODE_ID_COLUMN: &str = "node_id";
+
+/**
+ * Returns all files keyed by node_id that annotations are added to: mapping.csv and the
+ * statistic outputs, directed and undirected, of the whole graph and of the giant component.
+ */
+pub fn annotated_files(output_paths: &OutputPaths) -> Vec<PathBuf> {
+    let mut files = vec![output_paths.mapping.clone()];
+    for paths in [output_paths.clone(), output_paths.for_undirected_graph()] {
+        files.extend(paths.node_metrics());
+        files.extend(paths.for_giant_component().node_metrics());
+    }
+    // the components are not labelled for the giant component
+    files.sort();
+    files.dedup();
+    files
+}
+
+/**
+ * Sets the given column of the CSV file at path to the values of the nodes. The column is appended
+ * if it does not exist yet, otherwise its values are replaced, so annotating twice is harmless.
+ * Nodes without a value get an empty cell. Files that do not exist are skipped.
+ */
+#[allow(clippy::expect_fun_call)]
+pub fn set_node_column<V: Display>(path: &Path, column: &str, values: &HashMap<i64, V>) {
+    if !path.exists() {
+        return;
+    }
+
+    let temporary_path = path.with_extension("csv.tmp");
+    let mut reader = csv::Reader::from_path(path).unwrap();
+    let mut writer = csv::Writer::from_path(&temporary_path).expect(&format!(
+        "Could not create temporary file at {}",
+        temporary_path.to_str().unwrap()
+    ));
+
+    let mut headers = reader.headers().unwrap().clone();
+    let node_id_position = headers.iter()
+        .position(|header| header == NODE_ID_COLUMN)
+        .expect(&format!("{} has no {} column", path.to_str().unwrap(), NODE_ID_COLUMN));
+    let column_position = match headers.iter().position(|header| header == column) {
+        Some(position) => position,
+        None => {
+            headers.push_field(column);
+            headers.len() - 1
+        }
+    };
+    writer.write_record(&headers).unwrap();
+
+    for record in reader.records() {
+        let record = record.expect(&format!("Invalid row in {}", path.to_str().unwrap()));
+        let value = record[node_id_position].parse::<i64>().ok()
+            .and_then(|node_id| values.get(&node_id))
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+
+        let mut fields: Vec<&str> = record.iter().collect();
+        if column_position < fields.len() {
+            fields[column_position] = &value;
+        } else {
+            fields.push(&value);
+        }
+        writer.write_record(&fields).unwrap();
+    }
+
+    writer.flush().unwrap();
+    drop(writer);
+    fs::rename(&temporary_path, path).unwrap();
+
+    info!("Added column {} to {}", column, path.to_str().unwrap());
+}
+
+/**
+ * Reads the given column of a CSV file keyed by node_id. Empty cells are left out.
+ * Returns None if the file or the column does not exist.
+ */
+#[allow(clippy::expect_fun_call)]
+pub fn read_node_column(path: &Path, column: &str) -> Option<HashMap<i64, String>> {
+    let mut reader = csv::Reader::from_path(path).ok()?;
+    let headers = reader.headers().unwrap().clone();
+    let node_id_position = headers.iter().position(|header| header == NODE_ID_COLUMN)?;
+    let column_position = headers.iter().position(|header| header == column)?;
+
+    let values = reader.records()
+        .map(|record| record.expect(&format!("Invalid row in {}", path.to_str().unwrap())))
+        .filter(|record| !record[column_position].is_empty())
+        .map(|record| (record[node_id_position].parse().unwrap(), record[column_position].to_string()))
+        .collect();
+
+    Some(values)
+}
@@ -0,0 +1,142 @@
+/*!
+ * Binary trie for longest-prefix matching on IP addresses in their numeric (u128) representation.
+ * IPv4 addresses use the lower 32 bits, so the trie has to know the address width.
+ */
+
+const NO_CHILD: u32 = u32::MAX;
+
+struct TrieNode<T> {
+    children: [u32; 2],
+    value: Option<T>,
+}
+
+impl<T> TrieNode<T> {
+    fn new() -> TrieNode<T> {
+        TrieNode {
+            children: [NO_CHILD, NO_CHILD],
+            value: None,
+        }
+    }
+}
+
+pub struct PrefixTrie<T> {
+    // All nodes are stored in one Vec and reference their children by position, the root is at 0
+    nodes: Vec<TrieNode<T>>,
+    address_bits: u8,
+}
+
+impl<T> PrefixTrie<T> {
+    pub fn new(address_bits: u8) -> PrefixTrie<T> {
+        assert!(address_bits <= 128, "Addresses cannot have more than 128 bits");
+
+        PrefixTrie {
+            nodes: vec![TrieNode::new()],
+            address_bits,
+        }
+    }
+
+    /**
+     * Stores the value for the given prefix. The bits of the prefix after prefix_length are ignored.
+     * If the prefix is already present, its value is replaced.
+     */
+    pub fn insert(&mut self, prefix: u128, prefix_length: u8, value: T) {
+        assert!(prefix_length <= self.address_bits, "Prefix length exceeds the address width");
+
+        let mut current = 0;
+        for position in 0..prefix_length {
+            let bit = self.bit_at(prefix, position);
+            let child = self.nodes[current].children[bit];
+            current = if child == NO_CHILD {
+                self.nodes.push(TrieNode::new());
+                let new_child = self.nodes.len() - 1;
+                self.nodes[current].children[bit] = new_child as u32;
+                new_child
+            } else {
+                child as usize
+            };
+        }
+
+        self.nodes[current].value = Some(value);
+    }
+
+    /**
+     * Returns the value of the longest stored prefix that contains the given address.
+     */
+    pub fn longest_match(&self, address: u128) -> Option<&T> {
+        let mut current = 0;
+        let mut best_match = self.nodes[current].value.as_ref();
+
+        for position in 0..self.address_bits {
+            let child = self.nodes[current].children[self.bit_at(address, position)];
+            if child == NO_CHILD {
+                break;
+            }
+
+            current = child as usize;
+            if let Some(value) = &self.nodes[current].value {
+                best_match = Some(value);
+            }
+        }
+
+        best_match
+    }
+
+    /**
+     * Returns the bit at the given position, counted from the most significant bit of the address.
+     */
+    fn bit_at(&self, address: u128, position: u8) -> usize {
+        ((address >> (self.address_bits - 1 - position)) & 1) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::*;
+    use crate::preprocess::parser::ip_to_numeric;
+
+    fn numeric(ip: &str) -> u128 {
+        ip_to_numeric(ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn longest_match_prefers_the_most_specific_ipv4_prefix() {
+        let mut trie = PrefixTrie::new(32);
+        trie.insert(numeric("10.0.0.0"), 8, 1);
+        trie.insert(numeric("10.1.0.0"), 16, 2);
+        trie.insert(numeric("10.1.2.0"), 24, 3);
+        trie.insert(numeric("10.1.2.128"), 25, 4);
+
+        assert_eq!(trie.longest_match(numeric("10.1.2.200")), Some(&4));
+        assert_eq!(trie.longest_match(numeric("10.1.2.3")), Some(&3));
+        assert_eq!(trie.longest_match(numeric("10.1.3.3")), Some(&2));
+        assert_eq!(trie.longest_match(numeric("10.2.0.1")), Some(&1));
+        assert_eq!(trie.longest_match(numeric("11.0.0.1")), None);
+    }
+
+    #[test]
+    fn longest_match_prefers_the_most_specific_ipv6_prefix() {
+        let mut trie = PrefixTrie::new(128);
+        trie.insert(numeric("2001:db8::"), 32, 1);
+        trie.insert(numeric("2001:db8:ab00::"), 40, 2);
+        trie.insert(numeric("2001:db8:abcd::"), 48, 3);
+
+        assert_eq!(trie.longest_match(numeric("2001:db8:abcd::1")), Some(&3));
+        assert_eq!(trie.longest_match(numeric("2001:db8:abce::1")), Some(&2));
+        assert_eq!(trie.longest_match(numeric("2001:db8:1::1")), Some(&1));
+        assert_eq!(trie.longest_match(numeric("2001:db9::1")), None);
+    }
+
+    #[test]
+    fn default_route_and_replaced_prefixes() {
+        let mut trie = PrefixTrie::new(32);
+        trie.insert(0, 0, 1);
+        // host bits after the prefix length are ignored
+        trie.insert(numeric("192.168.1.77"), 24, 2);
+        trie.insert(numeric("192.168.1.0"), 24, 3);
+
+        assert_eq!(trie.longest_match(numeric("192.168.1.1")), Some(&3));
+        assert_eq!(trie.longest_match(numeric("8.8.8.8")), Some(&1));
+    }
+}
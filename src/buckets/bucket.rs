@@ -6,10 +6,23 @@ use serde::{Deserialize, Serialize};
 use crate::common::structs::parse_data::InternalNode;
 use crate::preprocess::file_util;
 
+/**
+ * Hop data per target: (hop node ID, hop count, RTT in microseconds).
+ */
+pub type HopList = Vec<(u64, u8, Option<u32>)>;
+
+/**
+ * Identifies a path: (source node ID, target node ID, trace number).
+ * YARRP paths start at the vantage point (node 0) and have trace number 0, so all probes towards
+ * a target form one path, also across input files. WARTS traceroutes are numbered from 1 within
+ * their input file and are never joined with another traceroute.
+ */
+pub type PathKey = (u64, u64, u32);
+
 #[derive(Serialize)]
 #[derive(Deserialize)]
 pub struct GraphBucket {
-    edge_map: HashMap<u64, Vec<(u64, u8)>>,
+    edge_map: HashMap<PathKey, HopList>,
     file_path: PathBuf,
 }
 
@@ -21,10 +34,10 @@ impl GraphBucket {
         }
     }
 
-    fn load_or_create(file_path: &PathBuf) -> HashMap<u64, Vec<(u64, u8)>> {
+    #[allow(clippy::expect_fun_call)]
+    fn load_or_create(file_path: &PathBuf) -> HashMap<PathKey, HopList> {
         let f = File::open(file_path);
-        if f.is_ok() {
-            let file = f.unwrap();
+        if let Ok(file) = f {
             bincode::deserialize_from(file).expect(
                 &format!(
                     "File at {} does not contain or contains invalid graph bucket data",
@@ -37,13 +50,8 @@ impl GraphBucket {
     }
 
     pub fn add_node(&mut self, node: InternalNode) {
-        if !self.edge_map.contains_key(&node.target_id) {
-            let new_list = Vec::<(u64, u8)>::new();
-            self.edge_map.insert(node.target_id, new_list);
-        }
-
-        let list = self.edge_map.get_mut(&node.target_id).unwrap();
-        list.push((node.hop_id, node.hop_count));
+        let list = self.edge_map.entry((node.source_id, node.target_id, node.trace)).or_default();
+        list.push((node.hop_id, node.hop_count, node.rtt));
     }
 
     pub fn evict_to_disk(&mut self) {
@@ -53,7 +61,7 @@ impl GraphBucket {
         self.edge_map.shrink_to_fit(); // this is to make sure that the memory actually is freed
     }
 
-    pub fn edge_map(self) -> HashMap<u64, Vec<(u64, u8)>> {
+    pub fn edge_map(self) -> HashMap<PathKey, HopList> {
         self.edge_map
     }
 }
\ No newline at end of file
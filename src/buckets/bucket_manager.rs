@@ -7,27 +7,52 @@ use crate::common::structs::parse_data::{InternalNode, NodeV4, NodeV6};
 // Make sure to update this if you change the bucket id calculation logic.
 pub const BUCKET_COUNT: u64 = 256;
 
+/**
+ * The input format the buckets were created from, which names their intermediate files.
+ */
+#[derive(Clone, Copy)]
+pub enum BucketFormat {
+    Yarrp,
+    Warts,
+}
+
+impl BucketFormat {
+    pub fn bucket_file_name(&self, bucket_id: u64) -> String {
+        match self {
+            BucketFormat::Yarrp => format!("yarrp.{}.bin", bucket_id),
+            BucketFormat::Warts => format!("warts.{}.bin", bucket_id),
+        }
+    }
+}
+
 /**
  * Utility struct for managing the loaded buckets and centrally storing the IP mapping.
+ * IPs that are contained in the node ID registry are assigned their registered ID.
  */
 pub struct GraphBucketManager<'a> {
     buckets: HashMap<u8, GraphBucket>,
     global_ip_mapping: &'a mut HashMap<u128, u64>,
+    registry: &'a HashMap<u128, u64>,
     id_counter: u64,
     intermediate_path: PathBuf,
+    format: BucketFormat,
 }
 
 impl<'a> GraphBucketManager<'a> {
     pub fn new(
         intermediate_path: PathBuf,
         global_ip_mapping: &'a mut HashMap<u128, u64>,
-        counter: u64
+        registry: &'a HashMap<u128, u64>,
+        counter: u64,
+        format: BucketFormat,
     ) -> GraphBucketManager<'a> {
         GraphBucketManager {
             buckets: HashMap::new(),
             global_ip_mapping,
+            registry,
             id_counter: counter,
             intermediate_path,
+            format,
         }
     }
 
@@ -43,13 +68,14 @@ impl<'a> GraphBucketManager<'a> {
     }
 
     fn convert_to_internal_node_v4(&mut self, node: &NodeV4) -> InternalNode {
-        let target_ip: u32 = node.target_ip.into();
-        let hop_ip: u32 = node.hop_ip.into();
         self.convert_to_internal_node_v6(
             &NodeV6 {
-                target_ip: u128::from(target_ip),
-                hop_ip: u128::from(hop_ip),
-                hop_count: node.hop_count.into(),
+                source_ip: node.source_ip.map(u128::from),
+                target_ip: u128::from(node.target_ip),
+                trace: node.trace,
+                hop_ip: u128::from(node.hop_ip),
+                hop_count: node.hop_count,
+                rtt: node.rtt,
             }
         )
     }
@@ -66,36 +92,43 @@ impl<'a> GraphBucketManager<'a> {
     }
 
     /**
-     * Takes a node, assigns numeric incremental IDs to it and returns the node IDs.
+     * Takes a node, assigns numeric incremental IDs (or the registered ones) to it and returns the node IDs.
+     * A missing source IP stands for the vantage point, i.e. node 0.
      */
     fn convert_to_internal_node_v6(&mut self, node: &NodeV6) -> InternalNode {
-        let target_node_id = if self.global_ip_mapping.contains_key(&node.target_ip) {
-            *self.global_ip_mapping.get(&node.target_ip).unwrap()
-        } else {
-            let new_node_id = self.id_counter;
-            self.id_counter += 1;
-            self.global_ip_mapping.insert(node.target_ip, new_node_id);
-            new_node_id
-        };
-
-        let hop_node_id = if self.global_ip_mapping.contains_key(&node.hop_ip) {
-            *self.global_ip_mapping.get(&node.hop_ip).unwrap()
-        } else {
-            let new_node_id = self.id_counter;
-            self.id_counter += 1;
-            self.global_ip_mapping.insert(node.hop_ip, new_node_id);
-            new_node_id
-        };
+        let target_node_id = self.get_or_assign_node_id(node.target_ip);
+        let source_node_id = node.source_ip.map_or(0, |source_ip| self.get_or_assign_node_id(source_ip));
+        let hop_node_id = self.get_or_assign_node_id(node.hop_ip);
 
         InternalNode {
+            source_id: source_node_id,
             target_id: target_node_id,
+            trace: node.trace,
             hop_id: hop_node_id,
-            hop_count: node.hop_count.into(),
+            hop_count: node.hop_count,
+            rtt: node.rtt,
         }
     }
 
+    fn get_or_assign_node_id(&mut self, ip: u128) -> u64 {
+        if let Some(&node_id) = self.global_ip_mapping.get(&ip) {
+            return node_id;
+        }
+
+        let new_node_id = match self.registry.get(&ip) {
+            Some(&registered_node_id) => registered_node_id,
+            None => {
+                let new_node_id = self.id_counter;
+                self.id_counter += 1;
+                new_node_id
+            }
+        };
+        self.global_ip_mapping.insert(ip, new_node_id);
+        new_node_id
+    }
+
     fn create_path_for_bucket_id(&self, bucket_id: u8) -> PathBuf {
-        self.intermediate_path.join(format!("yarrp.{}.bin", bucket_id))
+        self.intermediate_path.join(self.format.bucket_file_name(bucket_id as u64))
     }
 
     /**
@@ -121,7 +154,7 @@ impl<'a> GraphBucketManager<'a> {
         // We XOR last byte of public half + last byte of private half
         // (that's the 8th and 16th bytes from the left)
 
-        let byte1 = u8::try_from((ip & 0x00_00_00_00_00_00_00_ff__00_00_00_00_00_00_00_00) >> (8 * 8)).unwrap();
+        let byte1 = u8::try_from((ip & 0x00_00_00_00_00_00_00_ff_00_00_00_00_00_00_00_00) >> (8 * 8)).unwrap();
         let byte2 = u8::try_from(ip & 0xff).unwrap();
 
         byte1 ^ byte2
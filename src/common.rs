@@ -1,2 +1,4 @@
+pub mod hash;
 pub mod parameters;
-pub mod structs;
\ No newline at end of file
+pub mod random;
+pub mod structs;
@@ -0,0 +1,54 @@
+/*!
+ * 64-bit FNV-1a hash, used to recognize inputs of intermediate results (e.g. the edge list of a graph
+ * snapshot). It is not cryptographic, but stable across runs and platforms, unlike the std hasher.
+ */
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+pub struct Fnv1a {
+    hash: u64,
+}
+
+impl Fnv1a {
+    pub fn new() -> Fnv1a {
+        Fnv1a { hash: FNV_OFFSET_BASIS }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(bytes: &[u8]) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(bytes);
+        hasher.finish()
+    }
+
+    #[test]
+    fn matches_the_reference_values() {
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn does_not_depend_on_how_the_input_is_split() {
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"foo");
+        hasher.write(b"bar");
+        assert_eq!(hasher.finish(), hash(b"foobar"));
+    }
+}
@@ -13,10 +13,188 @@ pub const NODE_INDEX_FILENAME: &str = "yarrp.node_index.bin";
 pub struct OutputPaths {
     pub mapping: PathBuf,
     pub edges: PathBuf,
+    pub edges_interface: PathBuf,
+    pub ip_to_router: PathBuf,
     pub edges_deduplicated: PathBuf,
     pub max_node_ids: PathBuf,
     pub betweenness: PathBuf,
+    pub edge_betweenness: PathBuf,
     pub degree: PathBuf,
+    pub degree_distribution: PathBuf,
+    pub degree_fit: PathBuf,
+    pub closeness: PathBuf,
+    pub spectral: PathBuf,
+    pub clustering: PathBuf,
+    pub clustering_summary: PathBuf,
+    pub components: PathBuf,
+    pub component_sizes: PathBuf,
+    pub giant_component_edges: PathBuf,
+    pub coreness: PathBuf,
+    pub core_sizes: PathBuf,
+    pub k_core_edges: PathBuf,
+    pub assortativity: PathBuf,
+    pub rich_club: PathBuf,
+    pub distance_distribution: PathBuf,
+    pub distance_summary: PathBuf,
+    pub eccentricity: PathBuf,
+    pub communities: PathBuf,
+    pub community_sizes: PathBuf,
+    pub community_summary: PathBuf,
+    pub community_as_comparison: PathBuf,
+    pub graph_export: PathBuf,
+    pub as_edges: PathBuf,
+    pub as_degree: PathBuf,
+    pub as_betweenness: PathBuf,
+    pub cross_border_edges: PathBuf,
+    pub country_subgraphs: PathBuf,
+}
+
+impl OutputPaths {
+    /**
+     * Returns the output paths with all graph statistics labelled as undirected
+     * (e.g. degree_undirected.csv), so that they cannot be confused with the directed ones.
+     */
+    pub fn for_undirected_graph(&self) -> OutputPaths {
+        OutputPaths {
+            betweenness: label_path(&self.betweenness, "undirected"),
+            edge_betweenness: label_path(&self.edge_betweenness, "undirected"),
+            degree: label_path(&self.degree, "undirected"),
+            degree_distribution: label_path(&self.degree_distribution, "undirected"),
+            degree_fit: label_path(&self.degree_fit, "undirected"),
+            closeness: label_path(&self.closeness, "undirected"),
+            spectral: label_path(&self.spectral, "undirected"),
+            clustering: label_path(&self.clustering, "undirected"),
+            clustering_summary: label_path(&self.clustering_summary, "undirected"),
+            components: label_path(&self.components, "undirected"),
+            component_sizes: label_path(&self.component_sizes, "undirected"),
+            giant_component_edges: label_path(&self.giant_component_edges, "undirected"),
+            coreness: label_path(&self.coreness, "undirected"),
+            core_sizes: label_path(&self.core_sizes, "undirected"),
+            k_core_edges: label_path(&self.k_core_edges, "undirected"),
+            assortativity: label_path(&self.assortativity, "undirected"),
+            rich_club: label_path(&self.rich_club, "undirected"),
+            distance_distribution: label_path(&self.distance_distribution, "undirected"),
+            distance_summary: label_path(&self.distance_summary, "undirected"),
+            eccentricity: label_path(&self.eccentricity, "undirected"),
+            communities: label_path(&self.communities, "undirected"),
+            community_sizes: label_path(&self.community_sizes, "undirected"),
+            community_summary: label_path(&self.community_summary, "undirected"),
+            community_as_comparison: label_path(&self.community_as_comparison, "undirected"),
+            graph_export: label_path(&self.graph_export, "undirected"),
+            as_degree: label_path(&self.as_degree, "undirected"),
+            as_betweenness: label_path(&self.as_betweenness, "undirected"),
+            ..self.clone()
+        }
+    }
+
+    /**
+     * Returns the output paths labelled as the graph statistics of the given parameters are written:
+     * undirected (-> for_undirected_graph) and restricted to the giant component (-> for_giant_component).
+     */
+    pub fn for_graph_parameters(&self, parameters: &GraphParametersToCompute) -> OutputPaths {
+        let output_paths = if parameters.undirected {
+            self.for_undirected_graph()
+        } else {
+            self.clone()
+        };
+
+        if parameters.restricted_to_giant_component() {
+            output_paths.for_giant_component()
+        } else {
+            output_paths
+        }
+    }
+
+    /**
+     * Returns the output paths with all statistics computed after the components labelled as giant
+     * component (e.g. degree_giant.csv), so that they cannot be confused with the ones of the whole
+     * graph. The component files and the AS-level statistics still cover the whole graph.
+     */
+    pub fn for_giant_component(&self) -> OutputPaths {
+        OutputPaths {
+            betweenness: label_path(&self.betweenness, "giant"),
+            edge_betweenness: label_path(&self.edge_betweenness, "giant"),
+            degree: label_path(&self.degree, "giant"),
+            degree_distribution: label_path(&self.degree_distribution, "giant"),
+            degree_fit: label_path(&self.degree_fit, "giant"),
+            closeness: label_path(&self.closeness, "giant"),
+            spectral: label_path(&self.spectral, "giant"),
+            clustering: label_path(&self.clustering, "giant"),
+            clustering_summary: label_path(&self.clustering_summary, "giant"),
+            coreness: label_path(&self.coreness, "giant"),
+            core_sizes: label_path(&self.core_sizes, "giant"),
+            k_core_edges: label_path(&self.k_core_edges, "giant"),
+            assortativity: label_path(&self.assortativity, "giant"),
+            rich_club: label_path(&self.rich_club, "giant"),
+            distance_distribution: label_path(&self.distance_distribution, "giant"),
+            distance_summary: label_path(&self.distance_summary, "giant"),
+            eccentricity: label_path(&self.eccentricity, "giant"),
+            communities: label_path(&self.communities, "giant"),
+            community_sizes: label_path(&self.community_sizes, "giant"),
+            community_summary: label_path(&self.community_summary, "giant"),
+            community_as_comparison: label_path(&self.community_as_comparison, "giant"),
+            ..self.clone()
+        }
+    }
+
+    /**
+     * Returns the output paths of the graph aggregated by prefixes of the given length
+     * (e.g. prefix24_edges.csv).
+     */
+    pub fn for_prefix_length(&self, prefix_length: u8) -> PrefixOutputPaths {
+        let path = |name: &str| self.mapping.with_file_name(format!("prefix{}_{}.csv", prefix_length, name));
+
+        PrefixOutputPaths {
+            mapping: path("mapping"),
+            edges: path("edges"),
+            degree: path("degree"),
+            betweenness: path("betweenness"),
+        }
+    }
+
+    /**
+     * Returns the CSV files of all per-node statistics, i.e. files with a node_id column.
+     * New statistics have to be added here to be picked up by the graph export.
+     */
+    pub fn node_metrics(&self) -> Vec<PathBuf> {
+        vec![
+            self.degree.clone(),
+            self.coreness.clone(),
+            self.betweenness.clone(),
+            self.closeness.clone(),
+            self.eccentricity.clone(),
+            self.spectral.clone(),
+            self.clustering.clone(),
+            self.components.clone(),
+            self.communities.clone(),
+        ]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PrefixOutputPaths {
+    pub mapping: PathBuf,
+    pub edges: PathBuf,
+    pub degree: PathBuf,
+    pub betweenness: PathBuf,
+}
+
+impl PrefixOutputPaths {
+    pub fn for_undirected_graph(&self) -> PrefixOutputPaths {
+        PrefixOutputPaths {
+            degree: label_path(&self.degree, "undirected"),
+            betweenness: label_path(&self.betweenness, "undirected"),
+            ..self.clone()
+        }
+    }
+}
+
+fn label_path(path: &Path, label: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap().to_str().unwrap();
+    match path.extension() {
+        Some(extension) => path.with_file_name(format!("{}_{}.{}", stem, label, extension.to_str().unwrap())),
+        None => path.with_file_name(format!("{}_{}", stem, label)),
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -39,6 +217,10 @@ pub struct DatasetConfig {
     pub input_path: PathBuf,
     pub intermediate_path: PathBuf,
     pub output_path: PathBuf,
+    pub id_registry_path: Option<PathBuf>,
+    pub prefix_to_as_path: Option<PathBuf>,
+    pub alias_path: Option<PathBuf>,
+    pub geo_db_path: Option<PathBuf>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -47,40 +229,260 @@ pub struct FeatureToggle {
     pub should_merge: bool,
     pub should_persist_index: bool,
     pub should_persist_edges: bool,
+    pub should_resolve_aliases: bool,
     pub should_deduplicate_edges: bool,
     pub should_compute_graph: bool,
+    pub should_annotate: bool,
+    pub should_contract_to_as_level: bool,
+    pub should_aggregate_prefixes: bool,
+    pub should_export_graph: bool,
+    pub node_ids: NodeIdParameters,
+    pub deduplication: DeduplicationParameters,
     pub parameters: GraphParametersToCompute,
+    pub geo: GeoParameters,
+    pub as_level: AsLevelParameters,
+    pub prefixes: PrefixParameters,
+    pub export: ExportParameters,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PrefixParameters {
+    pub lengths: Vec<u8>,
+    pub unknown_nodes: UnknownNodeRule,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GeoParameters {
+    pub cross_border_edges: bool,
+    pub country_subgraphs: bool,
+    pub unknown_nodes: UnknownNodeRule,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AsLevelParameters {
+    pub unknown_nodes: UnknownNodeRule,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum UnknownNodeRule {
+    Drop,
+    Bridge,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExportParameters {
+    pub formats: Vec<ExportFormat>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ExportFormat {
+    GraphML,
+    GEXF,
+    DOT,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeduplicationParameters {
+    pub external_sort: bool,
+    pub memory_budget_mb: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NodeIdParameters {
+    pub canonical_order: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct GraphParametersToCompute {
+    pub undirected: bool,
+    pub snapshot: bool,
     pub degree: DegreeParameters,
+    pub cores: CoreParameters,
+    pub assortativity: AssortativityParameters,
     pub betweenness: BetweennessParameters,
+    pub closeness: ClosenessParameters,
+    pub distances: DistanceParameters,
+    pub communities: CommunityParameters,
+    pub spectral: SpectralParameters,
+    pub clustering: ClusteringParameters,
+    pub components: ComponentParameters,
+}
+
+impl GraphParametersToCompute {
+    /**
+     * Whether the statistics after the components are computed on the giant component only.
+     */
+    pub fn restricted_to_giant_component(&self) -> bool {
+        self.components.enabled && self.components.restrict_to_giant_component
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DegreeParameters {
     pub enabled: bool,
+    pub distribution: bool,
+    pub bootstrap_samples: u32,
+    pub seed: u64,
+    pub max_thread_count: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CoreParameters {
+    pub enabled: bool,
+    pub export_k: Option<u32>,
+    pub export_kind: CoreKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CoreKind {
+    In,
+    Out,
+    Total,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AssortativityParameters {
+    pub enabled: bool,
+    pub rich_club: bool,
+    pub randomizations: u32,
+    pub swaps_per_edge: u32,
+    pub seed: u64,
+    pub max_thread_count: u16,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct BetweennessParameters {
     pub enabled: bool,
+    pub mode: BetweennessMode,
+    pub edges: bool,
+    pub sample_size: u32,
+    pub epsilon: f64,
+    pub delta: f64,
+    pub seed: u64,
     pub save_intermediate_results_periodically: bool,
     pub result_batch_size: u32,
     pub max_thread_count: u16,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BetweennessMode {
+    Exact,
+    Uniform,
+    DegreeProportional,
+    Adaptive,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClosenessParameters {
+    pub enabled: bool,
+    pub sample_size: u32,
+    pub seed: u64,
+    pub save_intermediate_results_periodically: bool,
+    pub result_batch_size: u32,
+    pub max_thread_count: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DistanceParameters {
+    pub enabled: bool,
+    pub sample_size: u32,
+    pub seed: u64,
+    pub effective_diameter_percentile: f64,
+    pub diameter_sweeps: u32,
+    pub save_intermediate_results_periodically: bool,
+    pub result_batch_size: u32,
+    pub max_thread_count: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CommunityParameters {
+    pub louvain: bool,
+    pub label_propagation: bool,
+    pub resolution: f64,
+    pub seed: u64,
+    pub max_iterations: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpectralParameters {
+    pub pagerank: bool,
+    pub eigenvector: bool,
+    pub katz: bool,
+    pub damping_factor: f64,
+    pub dangling_nodes: DanglingNodeRule,
+    pub katz_alpha: f64,
+    pub katz_beta: f64,
+    pub tolerance: f64,
+    pub max_iterations: u32,
+    pub max_thread_count: u16,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DanglingNodeRule {
+    Uniform,
+    Drop,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClusteringParameters {
+    pub enabled: bool,
+    pub max_thread_count: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ComponentParameters {
+    pub enabled: bool,
+    pub giant_component: ComponentKind,
+    pub restrict_to_giant_component: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ComponentKind {
+    Weak,
+    Strong,
+}
+
 pub fn compute_output_paths(config: &DatasetConfig) -> OutputPaths {
     ensure_paths_exist(config);
 
     OutputPaths {
         mapping: config.output_path.to_path_buf().join(Path::new("mapping.csv")),
         edges: config.output_path.to_path_buf().join(Path::new("edges.csv")),
+        edges_interface: config.output_path.to_path_buf().join(Path::new("edges_interface.csv")),
+        ip_to_router: config.output_path.to_path_buf().join(Path::new("ip_to_router.csv")),
         edges_deduplicated: config.output_path.to_path_buf().join(Path::new("edges_deduplicated.csv")),
         max_node_ids: config.output_path.to_path_buf().join(Path::new("max_node_ids.csv")),
         betweenness: config.output_path.to_path_buf().join(Path::new("betweenness.csv")),
+        edge_betweenness: config.output_path.to_path_buf().join(Path::new("edge_betweenness.csv")),
         degree: config.output_path.to_path_buf().join(Path::new("degree.csv")),
+        degree_distribution: config.output_path.to_path_buf().join(Path::new("degree_distribution.csv")),
+        degree_fit: config.output_path.to_path_buf().join(Path::new("degree_fit.csv")),
+        closeness: config.output_path.to_path_buf().join(Path::new("closeness.csv")),
+        spectral: config.output_path.to_path_buf().join(Path::new("spectral.csv")),
+        clustering: config.output_path.to_path_buf().join(Path::new("clustering.csv")),
+        clustering_summary: config.output_path.to_path_buf().join(Path::new("clustering_summary.csv")),
+        components: config.output_path.to_path_buf().join(Path::new("components.csv")),
+        component_sizes: config.output_path.to_path_buf().join(Path::new("component_sizes.csv")),
+        giant_component_edges: config.output_path.to_path_buf().join(Path::new("giant_component_edges.csv")),
+        coreness: config.output_path.to_path_buf().join(Path::new("coreness.csv")),
+        core_sizes: config.output_path.to_path_buf().join(Path::new("core_sizes.csv")),
+        k_core_edges: config.output_path.to_path_buf().join(Path::new("k_core_edges.csv")),
+        assortativity: config.output_path.to_path_buf().join(Path::new("assortativity.csv")),
+        rich_club: config.output_path.to_path_buf().join(Path::new("rich_club.csv")),
+        distance_distribution: config.output_path.to_path_buf().join(Path::new("distance_distribution.csv")),
+        distance_summary: config.output_path.to_path_buf().join(Path::new("distance_summary.csv")),
+        eccentricity: config.output_path.to_path_buf().join(Path::new("eccentricity.csv")),
+        communities: config.output_path.to_path_buf().join(Path::new("communities.csv")),
+        community_sizes: config.output_path.to_path_buf().join(Path::new("community_sizes.csv")),
+        community_summary: config.output_path.to_path_buf().join(Path::new("community_summary.csv")),
+        community_as_comparison: config.output_path.to_path_buf().join(Path::new("community_as_comparison.csv")),
+        graph_export: config.output_path.to_path_buf().join(Path::new("graph")),
+        as_edges: config.output_path.to_path_buf().join(Path::new("as_edges.csv")),
+        as_degree: config.output_path.to_path_buf().join(Path::new("as_degree.csv")),
+        as_betweenness: config.output_path.to_path_buf().join(Path::new("as_betweenness.csv")),
+        cross_border_edges: config.output_path.to_path_buf().join(Path::new("cross_border_edges.csv")),
+        country_subgraphs: config.output_path.to_path_buf().join(Path::new("country_subgraphs")),
     }
 }
 
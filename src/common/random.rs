@@ -0,0 +1,74 @@
+/*!
+ * Small seeded pseudo random number generator (SplitMix64). Sampling results have to be
+ * reproducible from the seed in Config.toml, so no system randomness is used.
+ */
+
+use crate::graph::common::graph::NodeIndex;
+
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    /**
+     * Returns an independent generator for the given stream (e.g. the number of a sample), so that
+     * parallel sampling yields the same results regardless of which thread draws which sample.
+     */
+    pub fn for_stream(seed: u64, stream: u64) -> Random {
+        let mut mixer = Random::new(seed ^ stream.wrapping_mul(0xd1b54a32d192ed03));
+        Random::new(mixer.next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /**
+     * Returns a uniformly distributed number in [0, 1).
+     */
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /**
+     * Returns a uniformly distributed number in [0, bound).
+     */
+    pub fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+
+    /**
+     * Shuffles the items in place (Fisher-Yates shuffle).
+     */
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+
+    /**
+     * Draws sample_size of the given nodes without replacement (partial Fisher-Yates shuffle).
+     * The sample is sorted, so that it only depends on the seed and not on the drawing order.
+     */
+    pub fn sample_nodes(&mut self, nodes: &[NodeIndex], sample_size: usize) -> Vec<NodeIndex> {
+        let mut nodes = nodes.to_vec();
+        let sample_size = sample_size.min(nodes.len());
+        for i in 0..sample_size {
+            let j = i + self.below(nodes.len() - i);
+            nodes.swap(i, j);
+        }
+
+        nodes.truncate(sample_size);
+        nodes.sort_unstable();
+        nodes
+    }
+}
@@ -9,26 +9,40 @@ pub mod util {
 }
 
 pub mod parse_data {
-    use std::ops::RangeInclusive;
+    use std::net::IpAddr;
     use serde::Serialize;
     use serde::Deserialize;
 
+    /**
+     * A hop towards a target. Without a source IP, the path starts at the vantage point (node 0).
+     * Hops with the same source, target and a trace number other than 0 form one traceroute, which
+     * is never joined with any other traceroute (-> see also bucket.rs).
+     */
     pub struct NodeV4 {
+        pub source_ip: Option<u32>,
         pub target_ip: u32,
+        pub trace: u32,
         pub hop_ip: u32,
         pub hop_count: u8,
+        pub rtt: Option<u32>,
     }
 
     pub struct NodeV6 {
+        pub source_ip: Option<u128>,
         pub target_ip: u128,
+        pub trace: u32,
         pub hop_ip: u128,
         pub hop_count: u8,
+        pub rtt: Option<u32>,
     }
 
     pub struct InternalNode {
+        pub source_id: u64,
         pub target_id: u64,
+        pub trace: u32,
         pub hop_id: u64,
         pub hop_count: u8,
+        pub rtt: Option<u32>,
     }
 
     #[derive(Debug, Deserialize, Eq, PartialEq)]
@@ -37,31 +51,28 @@ pub mod parse_data {
         pub to: i64,
     }
 
+    /**
+     * One observation of an edge as written to edges.csv. The RTT (in microseconds) is the one
+     * measured at the `to` hop, the delta is the RTT difference to the `from` hop. Both are
+     * empty if one of the hops is unknown or the hops are not consecutive.
+     */
+    #[derive(Debug, Deserialize, PartialEq)]
+    pub struct CsvEdgeObservation {
+        pub from: i64,
+        pub to: i64,
+        pub rtt: Option<u32>,
+        pub rtt_delta: Option<i64>,
+    }
+
     #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
     pub struct MaxNodeIds {
         pub known: usize,
         pub unknown: usize,
     }
 
-    #[derive(Clone)]
-    pub struct NodeBoundaries {
-        min_node: i64,
-        max_node: i64,
+    #[derive(Debug, Deserialize)]
+    pub struct CsvNodeMapping {
+        pub ip: IpAddr,
+        pub node_id: i64,
     }
-
-    impl NodeBoundaries {
-        pub fn new(max_node_ids: MaxNodeIds) -> NodeBoundaries {
-            NodeBoundaries {
-                min_node: -(max_node_ids.unknown as i64),
-                max_node: max_node_ids.known as i64,
-            }
-        }
-
-        pub fn range_inclusive(&self) -> RangeInclusive<i64> {
-            self.min_node..=self.max_node
-        }
-        pub fn len(&self) -> usize {
-            (self.min_node + self.max_node + 1) as usize
-        }
-    }
-}
\ No newline at end of file
+}
@@ -0,0 +1,3 @@
+pub mod as_grapher;
+pub mod prefix_grapher;
+pub mod graph_contractor;
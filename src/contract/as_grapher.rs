@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+use std::process::exit;
+
+use log::{error, info, warn};
+
+use crate::annotate::as_annotator::ASN_COLUMN;
+use crate::annotate::node_columns::read_node_column;
+use crate::contract::graph_contractor::{contracted_graph, write_contracted_edges, GraphContractor};
+use crate::graph::common::graph::Graph;
+use crate::graph::communities::community_detector::{LABEL_PROPAGATION_COLUMN, LOUVAIN_COLUMN};
+use crate::graph::communities::partition_comparison::normalized_mutual_information;
+use crate::graph::grapher::Grapher;
+use crate::{AsLevelParameters, DatasetConfig, GraphParametersToCompute, OutputPaths};
+
+pub struct AsGrapher {
+    config: DatasetConfig,
+    output_paths: OutputPaths,
+    params: AsLevelParameters,
+    parameters: GraphParametersToCompute,
+    grapher: Grapher,
+}
+
+impl AsGrapher {
+    pub fn new(
+        config: &DatasetConfig,
+        output_paths: &OutputPaths,
+        params: &AsLevelParameters,
+        parameters: &GraphParametersToCompute,
+    ) -> AsGrapher {
+        // The grapher labels its output paths itself, they must not be labelled twice
+        AsGrapher {
+            config: config.clone(),
+            output_paths: output_paths.for_graph_parameters(parameters),
+            params: params.clone(),
+            parameters: parameters.clone(),
+            grapher: Grapher::new(config, output_paths, parameters),
+        }
+    }
+
+    /**
+     * Contracts the router-level graph into an AS-level graph, using the ASN annotation of the
+     * nodes. Every AS-level edge carries the number of router-level links it consists of.
+     * Afterwards, the enabled statistics (degree, betweenness) are calculated on the AS level and
+     * detected communities are compared with the ASes.
+     *
+     *  Requires: edges_deduplicated.csv, mapping.csv with asn column (-> should_annotate),
+     *            optionally communities.csv
+     * Generates: as_edges.csv (from_asn,to_asn,weight), as_degree.csv, as_betweenness.csv,
+     *            community_as_comparison.csv
+     */
+    pub fn collect_as_level_stats(&self) {
+        info!("Step: Contracting graph to AS level ({:?} unknown nodes).", self.params.unknown_nodes);
+
+        let groups = self.load_asns();
+        self.compare_communities(&groups);
+        let router_graph = Graph::new(
+            &self.output_paths,
+            /* from_deduplicated = */ true,
+            /* undirected = */ false
+        );
+
+        let as_edges = GraphContractor::new(&groups, self.params.unknown_nodes.clone()).contract(&router_graph);
+        drop(router_graph);
+        write_contracted_edges(&self.output_paths.as_edges, ("from_asn", "to_asn", "weight"), &as_edges);
+
+        let mut graph = contracted_graph(&as_edges, self.parameters.undirected);
+        info!("AS-level graph has {} ASes and {} edges.", graph.node_count(), graph.edge_count());
+
+        if self.parameters.degree.enabled {
+            graph = self.grapher.calculate_degree(graph, &self.output_paths.as_degree, ASN_COLUMN);
+        }
+        if self.parameters.betweenness.enabled {
+            let intermediate_path = self.config.intermediate_path.join("as_level");
+            let as_betweenness = &self.output_paths.as_betweenness;
+            self.grapher.calculate_betweenness(graph, &intermediate_path, as_betweenness, None, ASN_COLUMN);
+        }
+    }
+
+    /**
+     * Compares the communities of every algorithm in communities.csv with the ASes of the nodes by
+     * their normalized mutual information, over the nodes with a known ASN. Skipped if no
+     * communities were detected, algorithms without any such node are skipped with a warning.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn compare_communities(&self, asns: &HashMap<i64, i64>) {
+        let columns = [("louvain", LOUVAIN_COLUMN), ("label_propagation", LABEL_PROPAGATION_COLUMN)];
+        let results: Vec<(&str, HashMap<i64, String>)> = columns.iter()
+            .filter_map(|&(algorithm, column)| {
+                read_node_column(&self.output_paths.communities, column).map(|communities| (algorithm, communities))
+            })
+            .collect();
+        if results.is_empty() {
+            info!("No communities found at {}, skipping the comparison with the ASes.",
+                self.output_paths.communities.to_str().unwrap());
+            return;
+        }
+
+        let path = &self.output_paths.community_as_comparison;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing the AS comparison at {}", path.to_str().unwrap()));
+        writer.serialize(("algorithm", "nodes", "communities", "ases", "nmi")).unwrap();
+
+        for (algorithm, communities) in results {
+            let pairs: Vec<(u32, i64)> = communities.iter()
+                .filter_map(|(node_id, community)| {
+                    let community = community.parse().expect("Invalid community in communities file");
+                    asns.get(node_id).map(|&asn| (community, asn))
+                })
+                .collect();
+            if pairs.is_empty() {
+                warn!("No node of the {} communities has an ASN, skipping the comparison.", algorithm);
+                continue;
+            }
+
+            let community_count = pairs.iter().map(|&(community, _)| community).collect::<HashSet<u32>>().len();
+            let as_count = pairs.iter().map(|&(_, asn)| asn).collect::<HashSet<i64>>().len();
+            let nmi = normalized_mutual_information(&pairs);
+
+            info!(
+                "{} communities vs. ASes: NMI {:.4} over {} nodes ({} communities, {} ASes)",
+                algorithm, nmi, pairs.len(), community_count, as_count
+            );
+            writer.serialize((algorithm, pairs.len(), community_count, as_count, nmi)).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    fn load_asns(&self) -> HashMap<i64, i64> {
+        let asns = read_node_column(&self.output_paths.mapping, ASN_COLUMN).unwrap_or_else(|| {
+            error!("{} has no {} column. Please enable should_annotate with a prefix_to_as_path first.",
+                self.output_paths.mapping.to_str().unwrap(), ASN_COLUMN);
+            exit(1);
+        });
+
+        asns.into_iter()
+            .map(|(node_id, asn)| (node_id, asn.parse().expect("Invalid ASN in node mapping")))
+            .collect()
+    }
+}
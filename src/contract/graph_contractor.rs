@@ -0,0 +1,150 @@
+/*!
+ * Contracts a graph by merging all nodes of the same group (e.g. the same AS) into one node.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use log::info;
+use serde::Serialize;
+
+use crate::graph::common::graph::{Graph, NodeIndex};
+use crate::UnknownNodeRule;
+
+pub struct GraphContractor<'a> {
+    groups: &'a HashMap<i64, i64>,
+    unknown_nodes: UnknownNodeRule,
+}
+
+impl<'a> GraphContractor<'a> {
+    /**
+     * Nodes that are not contained in groups are treated according to the given rule.
+     */
+    pub fn new(groups: &'a HashMap<i64, i64>, unknown_nodes: UnknownNodeRule) -> GraphContractor<'a> {
+        GraphContractor { groups, unknown_nodes }
+    }
+
+    /**
+     * Returns the edges between the groups as (from, to, weight), sorted by (from, to). The weight
+     * is the number of distinct node pairs the edge consists of. Edges within a group are dropped.
+     *
+     * Nodes without a group are either dropped with all their edges (Drop), or bridged (Bridge):
+     * a node with a group is then connected to all grouped nodes it reaches via nodes without group.
+     */
+    pub fn contract(&self, graph: &Graph) -> Vec<(i64, i64, u64)> {
+        let group_of: Vec<Option<i64>> = graph.indices()
+            .map(|node| self.groups.get(&graph.node_id(node)).copied())
+            .collect();
+
+        let mut weights: BTreeMap<(i64, i64), u64> = BTreeMap::new();
+        let mut reachable = ReachableGroupedNodes::new(graph.node_count());
+        for node in graph.indices() {
+            let from_group = match group_of[node as usize] {
+                Some(group) => group,
+                None => continue,
+            };
+
+            let targets = match self.unknown_nodes {
+                UnknownNodeRule::Drop => graph.neighbors(node).to_vec(),
+                UnknownNodeRule::Bridge => reachable.collect(graph, &group_of, node),
+            };
+
+            for target in targets {
+                if let Some(to_group) = group_of[target as usize] {
+                    if to_group != from_group {
+                        *weights.entry((from_group, to_group)).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        weights.into_iter()
+            .map(|((from, to), weight)| (from, to, weight))
+            .collect()
+    }
+}
+
+/**
+ * Builds the graph of the contracted edges, e.g. for calculating statistics on it.
+ */
+pub fn contracted_graph(edges: &[(i64, i64, u64)], undirected: bool) -> Graph {
+    let mut graph_edges: Vec<(i64, i64)> = Vec::with_capacity(edges.len());
+    for &(from, to, _) in edges {
+        graph_edges.push((from, to));
+        if undirected {
+            graph_edges.push((to, from));
+        }
+    }
+
+    Graph::from_edges(graph_edges, undirected)
+}
+
+/**
+ * Writes the contracted edges as CSV with the given header (from, to, weight).
+ */
+#[allow(clippy::expect_fun_call)]
+pub fn write_contracted_edges<E: Serialize>(path: &Path, header: (&str, &str, &str), edges: &[E]) {
+    let mut writer = csv::Writer::from_path(path).expect(&format!(
+        "Could not create file for storing contracted edges at {}",
+        path.to_str().unwrap()
+    ));
+
+    writer.serialize(header).unwrap();
+    for edge in edges {
+        writer.serialize(edge).unwrap();
+    }
+    writer.flush().unwrap();
+
+    info!("Stored {} contracted edges to {}.", edges.len(), path.to_str().unwrap());
+}
+
+/**
+ * Depth-first search through nodes without group. The visited flags are reused across searches.
+ */
+struct ReachableGroupedNodes {
+    visited: Vec<bool>,
+    visited_nodes: Vec<NodeIndex>,
+    stack: Vec<NodeIndex>,
+}
+
+impl ReachableGroupedNodes {
+    fn new(node_count: usize) -> ReachableGroupedNodes {
+        ReachableGroupedNodes {
+            visited: vec![false; node_count],
+            visited_nodes: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn collect(&mut self, graph: &Graph, group_of: &[Option<i64>], start: NodeIndex) -> Vec<NodeIndex> {
+        let mut grouped_nodes = Vec::new();
+
+        self.stack.push(start);
+        self.visit(start);
+        while let Some(node) = self.stack.pop() {
+            for &neighbor in graph.neighbors(node) {
+                if self.visited[neighbor as usize] {
+                    continue;
+                }
+                self.visit(neighbor);
+
+                if group_of[neighbor as usize].is_some() {
+                    grouped_nodes.push(neighbor);
+                } else {
+                    self.stack.push(neighbor);
+                }
+            }
+        }
+
+        for node in self.visited_nodes.drain(..) {
+            self.visited[node as usize] = false;
+        }
+
+        grouped_nodes
+    }
+
+    fn visit(&mut self, node: NodeIndex) {
+        self.visited[node as usize] = true;
+        self.visited_nodes.push(node);
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::process::exit;
+
+use log::{error, info};
+
+use crate::common::structs::parse_data::CsvNodeMapping;
+use crate::contract::graph_contractor::{contracted_graph, write_contracted_edges, GraphContractor};
+use crate::graph::common::graph::Graph;
+use crate::graph::grapher::Grapher;
+use crate::preprocess::node_registry::ip_from_numeric;
+use crate::preprocess::parser::ip_to_numeric;
+use crate::{DatasetConfig, GraphParametersToCompute, IpType, OutputPaths, PrefixOutputPaths, PrefixParameters};
+
+const PREFIX_ID_COLUMN: &str = "prefix_id";
+
+pub struct PrefixGrapher {
+    config: DatasetConfig,
+    output_paths: OutputPaths,
+    params: PrefixParameters,
+    parameters: GraphParametersToCompute,
+}
+
+impl PrefixGrapher {
+    pub fn new(
+        config: &DatasetConfig,
+        output_paths: &OutputPaths,
+        params: &PrefixParameters,
+        parameters: &GraphParametersToCompute,
+    ) -> PrefixGrapher {
+        PrefixGrapher {
+            config: config.clone(),
+            output_paths: output_paths.clone(),
+            params: params.clone(),
+            parameters: parameters.clone(),
+        }
+    }
+
+    /**
+     * Aggregates the graph by address prefix for every configured prefix length: All known nodes
+     * within the same prefix are merged into one node, and the edges between the prefixes carry
+     * the number of node-level links as weight. The enabled statistics (degree, betweenness) are
+     * then calculated on every prefix graph.
+     *
+     *  Requires: edges_deduplicated.csv, mapping.csv
+     * Generates: For every prefix length N: prefixN_mapping.csv (prefix,prefix_id),
+     *            prefixN_edges.csv (from,to,weight), prefixN_degree.csv, prefixN_betweenness.csv
+     */
+    pub fn collect_prefix_level_stats(&self) {
+        let address_bits = if self.config.address_type == IpType::V4 { 32 } else { 128 };
+        if let Some(length) = self.params.lengths.iter().find(|&&length| length > address_bits) {
+            error!("Prefix length {} exceeds the length of IP{:?} addresses", length, self.config.address_type);
+            exit(1);
+        }
+
+        let nodes = self.load_nodes();
+        let router_graph = Graph::new(
+            &self.output_paths,
+            /* from_deduplicated = */ true,
+            /* undirected = */ false
+        );
+
+        for &prefix_length in &self.params.lengths {
+            info!("Step: Aggregating graph by /{} prefixes ({:?} unknown nodes).", prefix_length, self.params.unknown_nodes);
+
+            let mut paths = self.output_paths.for_prefix_length(prefix_length);
+            if self.parameters.undirected {
+                paths = paths.for_undirected_graph();
+            }
+
+            let groups = self.group_by_prefix(&nodes, prefix_length, address_bits, &paths);
+            let prefix_edges = GraphContractor::new(&groups, self.params.unknown_nodes.clone()).contract(&router_graph);
+            write_contracted_edges(&paths.edges, ("from", "to", "weight"), &prefix_edges);
+
+            let graph = contracted_graph(&prefix_edges, self.parameters.undirected);
+            info!("/{} prefix graph has {} prefixes and {} edges.", prefix_length, graph.node_count(), graph.edge_count());
+            self.calculate_graph_parameters(graph, prefix_length, &paths);
+        }
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn load_nodes(&self) -> Vec<(i64, u128)> {
+        let mapping_path = &self.output_paths.mapping;
+        let mut reader = csv::Reader::from_path(mapping_path).expect(&format!(
+            "Could not read node mapping at {}",
+            mapping_path.to_str().unwrap()
+        ));
+
+        reader.deserialize()
+            .map(|row: Result<CsvNodeMapping, _>| row.expect("Invalid row in node mapping"))
+            .map(|row| (row.node_id, ip_to_numeric(row.ip)))
+            .collect()
+    }
+
+    /**
+     * Assigns every node to its prefix and writes the prefix mapping. The prefixes are numbered
+     * in ascending order, starting at 1.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn group_by_prefix(
+        &self,
+        nodes: &[(i64, u128)],
+        prefix_length: u8,
+        address_bits: u8,
+        paths: &PrefixOutputPaths,
+    ) -> HashMap<i64, i64> {
+        let prefix_mask = prefix_mask(prefix_length, address_bits);
+
+        let mut prefixes: Vec<u128> = nodes.iter().map(|&(_, ip)| ip & prefix_mask).collect();
+        prefixes.sort_unstable();
+        prefixes.dedup();
+
+        let mut writer = csv::Writer::from_path(&paths.mapping).expect(&format!(
+            "Could not create file for storing prefix mapping at {}",
+            paths.mapping.to_str().unwrap()
+        ));
+        writer.serialize(("prefix", PREFIX_ID_COLUMN)).unwrap();
+        for (position, &prefix) in prefixes.iter().enumerate() {
+            let prefix_str = format!("{}/{}", ip_from_numeric(prefix, &self.config.address_type), prefix_length);
+            writer.serialize((prefix_str, position + 1)).unwrap();
+        }
+        writer.flush().unwrap();
+
+        nodes.iter()
+            .map(|&(node_id, ip)| {
+                let position = prefixes.binary_search(&(ip & prefix_mask)).unwrap();
+                (node_id, position as i64 + 1)
+            })
+            .collect()
+    }
+
+    fn calculate_graph_parameters(&self, graph: Graph, prefix_length: u8, paths: &PrefixOutputPaths) {
+        let mut graph = graph;
+
+        let grapher = Grapher::new(&self.config, &self.output_paths, &self.parameters);
+        if self.parameters.degree.enabled {
+            graph = grapher.calculate_degree(graph, &paths.degree, PREFIX_ID_COLUMN);
+        }
+        if self.parameters.betweenness.enabled {
+            let intermediate_path = self.config.intermediate_path.join(format!("prefix{}", prefix_length));
+            grapher.calculate_betweenness(graph, &intermediate_path, &paths.betweenness, None, PREFIX_ID_COLUMN);
+        }
+    }
+}
+
+/**
+ * Returns the mask that keeps the first prefix_length bits of an address with the given width.
+ */
+fn prefix_mask(prefix_length: u8, address_bits: u8) -> u128 {
+    if prefix_length == 0 {
+        return 0;
+    }
+
+    // the prefix bits are set at the top of the u128 first and then aligned with the address width
+    (u128::MAX << (128 - prefix_length as u32)) >> (128 - address_bits as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_keeps_the_prefix_bits_of_the_address_width() {
+        assert_eq!(prefix_mask(0, 32), 0);
+        assert_eq!(prefix_mask(8, 32), 0xff00_0000);
+        assert_eq!(prefix_mask(24, 32), 0xffff_ff00);
+        assert_eq!(prefix_mask(32, 32), 0xffff_ffff);
+        assert_eq!(prefix_mask(48, 128), 0xffff_ffff_ffff << 80);
+        assert_eq!(prefix_mask(128, 128), u128::MAX);
+    }
+}
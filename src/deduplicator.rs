@@ -1 +1,4 @@
-pub mod deduplicator;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod deduplicator;
+mod edge_latency;
+mod external_sorter;
@@ -1,62 +1,91 @@
 use std::fs::File;
+use std::process::exit;
 
 use csv::Writer;
-use log::info;
-use pbr::ProgressBar;
+use log::{error, info};
 
-use crate::graph::common::graph::Graph;
-use crate::OutputPaths;
+use crate::deduplicator::edge_latency::{read_edge_observations, write_edge_statistics};
+use crate::deduplicator::external_sorter::ExternalSorter;
+use crate::{DatasetConfig, DeduplicationParameters, OutputPaths};
 
 pub struct Deduplicator {
+    config: DatasetConfig,
     output_paths: OutputPaths,
+    params: DeduplicationParameters,
 }
 
 impl Deduplicator {
-    pub fn new(output_paths: &OutputPaths) -> Deduplicator {
+    pub fn new(
+        config: &DatasetConfig,
+        output_paths: &OutputPaths,
+        params: &DeduplicationParameters,
+    ) -> Deduplicator {
         Deduplicator {
+            config: config.clone(),
             output_paths: output_paths.clone(),
+            params: params.clone(),
         }
     }
 
     /**
-     * Reads in the graph from the list of edges, deduplicates them and writes them to a new file.
-     *  Requires: edges.csv as generated by the previous
-     *            step (merge for YARRP / preprocess for WARTS)
-     * Generates: edges_deduplicated.csv
+     * Deduplicates the list of edges and writes them to a new file, sorted by (from, to).
+     * The RTT observations of all duplicates of an edge are condensed into latency statistics
+     * (minimum RTT, median RTT and median RTT delta between the two hops, all in microseconds).
+     *
+     * By default, all edge observations are read into memory and sorted there. With external sorting
+     * enabled, the memory usage is bounded by the configured budget instead, at the cost of temporary
+     * files in the intermediate folder. Both modes write the same statistics.
+     *
+     *  Requires: edges.csv as generated by the merge step
+     * Generates: edges_deduplicated.csv (from,to,rtt_min,rtt_median,rtt_delta_median)
      */
     pub fn deduplicate_edges(&self) {
-        info!("Starting edge deduplication by reading in graph...");
+        if self.params.external_sort {
+            self.deduplicate_edges_externally();
+        } else {
+            self.deduplicate_edges_in_memory();
+        }
+    }
+
+    fn deduplicate_edges_externally(&self) {
+        info!(
+            "Starting edge deduplication using external sorting with a memory budget of {} MB...",
+            self.params.memory_budget_mb
+        );
         let mut writer = self.create_file_writer();
 
-        let graph = Graph::new(&self.output_paths, /* from_deduplicated = */ false);
+        writer.serialize(("from", "to", "rtt_min", "rtt_median", "rtt_delta_median")).unwrap();
+        let sorter = ExternalSorter::new(&self.config.intermediate_path, self.params.memory_budget_mb);
+        let edge_count = sorter.deduplicate(&self.output_paths.edges, &mut writer).unwrap_or_else(|e| {
+            error!("Could not sort the edges in {}: {}", self.config.intermediate_path.to_str().unwrap(), e);
+            exit(1);
+        });
+        writer.flush().unwrap();
+
+        info!("Stored {} deduplicated edges to disk.", edge_count);
+    }
+
+    fn deduplicate_edges_in_memory(&self) {
+        info!("Starting edge deduplication by reading in all edge observations...");
+        let mut writer = self.create_file_writer();
+
+        let mut observations = Vec::new();
+        read_edge_observations(&self.output_paths.edges, |edge| observations.push(edge));
 
         info!("Storing deduplicated paths to disk...");
-        let edges = graph.edges();
-
-        let mut progress_bar = ProgressBar::new(graph.boundaries().len() as u64);
-        let mut counter = 0;
-
-        writer.serialize(("from", "to")).unwrap();
-        for node in graph.boundaries().range_inclusive() {
-            let neighbors = &edges[node];
-            for neighbor in neighbors {
-                writer.serialize((node, neighbor)).unwrap();
-            }
-
-            counter += 1;
-            if counter % 10_000 == 0 {
-                progress_bar.add(10_000);
-            }
-        }
-        progress_bar.finish();
+        writer.serialize(("from", "to", "rtt_min", "rtt_median", "rtt_delta_median")).unwrap();
+        let edge_count = write_edge_statistics(observations, &mut writer);
         writer.flush().unwrap();
+
+        info!("Stored {} deduplicated edges to disk.", edge_count);
     }
 
+    #[allow(clippy::expect_fun_call)]
     fn create_file_writer(&self) -> Writer<File> {
         csv::Writer::from_path(&self.output_paths.edges_deduplicated)
             .expect(&format!(
                 "Could not create file for storing deduplicated edges at {}",
-                &self.output_paths.degree.to_str().unwrap()
+                &self.output_paths.edges_deduplicated.to_str().unwrap()
             ))
     }
 }
\ No newline at end of file
@@ -0,0 +1,251 @@
+/*!
+ * Aggregates the RTT observations per edge into latency statistics of bounded size. The observations
+ * are expected in (from, to) order, so that only the statistics of the current edge are kept in
+ * memory and every edge is written as soon as its last observation has been added.
+ */
+
+use std::fs::File;
+use std::path::Path;
+
+use csv::Writer;
+use log::warn;
+
+use crate::common::random::Random;
+use crate::common::structs::parse_data::CsvEdgeObservation;
+
+/**
+ * Number of values kept per edge for the median. Up to this many observations, the median is exact.
+ */
+pub const MEDIAN_SAMPLE_LIMIT: usize = 64;
+
+// seed of the median samples, every edge draws from its own stream (-> see Random::for_stream)
+const SAMPLE_SEED: u64 = 0x5eed;
+
+/**
+ * Uniform sample of at most MEDIAN_SAMPLE_LIMIT values (reservoir sampling). Beyond the limit, the
+ * median is estimated from the sample. The i-th value replaces a sampled one with probability
+ * limit / i.
+ */
+#[derive(Default)]
+struct Reservoir<T> {
+    values: Vec<T>,
+    count: u64,
+}
+
+impl<T: Ord + Copy + Into<i64>> Reservoir<T> {
+    fn add(&mut self, value: T, random: &mut Random) {
+        self.count += 1;
+        if self.values.len() < MEDIAN_SAMPLE_LIMIT {
+            self.values.push(value);
+            return;
+        }
+
+        let position = random.below(self.count as usize);
+        if position < MEDIAN_SAMPLE_LIMIT {
+            self.values[position] = value;
+        }
+    }
+
+    fn median(&mut self) -> Option<f64> {
+        median(&mut self.values)
+    }
+}
+
+/**
+ * Latency statistics of one edge, aggregated while adding the observations: the exact minimum RTT
+ * and samples of bounded size for the medians (-> see Reservoir). The samples of the n-th edge are
+ * drawn from stream n, so the same input always yields the same statistics.
+ */
+pub struct LatencySamples {
+    rtt_min: Option<u32>,
+    rtts: Reservoir<u32>,
+    rtt_deltas: Reservoir<i64>,
+    random: Random,
+}
+
+impl LatencySamples {
+    pub fn new(edge_index: u64) -> LatencySamples {
+        LatencySamples {
+            rtt_min: None,
+            rtts: Reservoir::default(),
+            rtt_deltas: Reservoir::default(),
+            random: Random::for_stream(SAMPLE_SEED, edge_index),
+        }
+    }
+
+    pub fn add(&mut self, rtt: Option<u32>, rtt_delta: Option<i64>) {
+        if let Some(value) = rtt {
+            self.rtt_min = Some(self.rtt_min.map_or(value, |rtt_min| rtt_min.min(value)));
+            self.rtts.add(value, &mut self.random);
+        }
+        if let Some(value) = rtt_delta {
+            self.rtt_deltas.add(value, &mut self.random);
+        }
+    }
+
+    /**
+     * Returns (rtt_min, rtt_median, rtt_delta_median). Values without samples are None.
+     */
+    pub fn statistics(&mut self) -> (Option<u32>, Option<f64>, Option<f64>) {
+        (self.rtt_min, self.rtts.median(), self.rtt_deltas.median())
+    }
+}
+
+/**
+ * Writes every distinct edge once with its latency statistics, given the observations in (from, to)
+ * order. Observations of the same edge with equal order are sampled in the order they are added.
+ */
+pub struct EdgeStatisticsWriter<'a> {
+    writer: &'a mut Writer<File>,
+    current_edge: Option<(i64, i64)>,
+    samples: LatencySamples,
+    edge_count: u64,
+}
+
+impl<'a> EdgeStatisticsWriter<'a> {
+    pub fn new(writer: &'a mut Writer<File>) -> EdgeStatisticsWriter<'a> {
+        EdgeStatisticsWriter {
+            writer,
+            current_edge: None,
+            samples: LatencySamples::new(0),
+            edge_count: 0,
+        }
+    }
+
+    pub fn add(&mut self, from: i64, to: i64, rtt: Option<u32>, rtt_delta: Option<i64>) {
+        if self.current_edge != Some((from, to)) {
+            if self.current_edge.is_some() {
+                self.write_current_edge();
+                self.samples = LatencySamples::new(self.edge_count);
+            }
+            self.current_edge = Some((from, to));
+        }
+        self.samples.add(rtt, rtt_delta);
+    }
+
+    /**
+     * Writes the last edge and returns the number of distinct edges.
+     */
+    pub fn finish(mut self) -> u64 {
+        if self.current_edge.is_some() {
+            self.write_current_edge();
+        }
+        self.edge_count
+    }
+
+    fn write_current_edge(&mut self) {
+        let (from, to) = self.current_edge.unwrap();
+        let (rtt_min, rtt_median, rtt_delta_median) = self.samples.statistics();
+        self.writer.serialize((from, to, rtt_min, rtt_median, rtt_delta_median)).unwrap();
+        self.edge_count += 1;
+    }
+}
+
+/**
+ * Reads all edge observations from the given edge list (edges.csv) in file order. Malformed rows
+ * are skipped and logged.
+ */
+pub fn read_edge_observations<F>(edges_path: &Path, mut consumer: F)
+    where F: FnMut(CsvEdgeObservation) {
+    let mut edges_reader = csv::Reader::from_path(edges_path).unwrap();
+    let mut skipped_rows = 0;
+    for edge in edges_reader.deserialize() {
+        match edge {
+            Ok(edge) => consumer(edge),
+            Err(error) => {
+                warn!("SKIPPING ROW: Could not parse edge observation: {}", error);
+                skipped_rows += 1;
+            }
+        }
+    }
+
+    if skipped_rows > 0 {
+        warn!("Skipped {} malformed rows of {}.", skipped_rows, edges_path.to_str().unwrap());
+    }
+}
+
+/**
+ * Sorts the given observations by (from, to) in memory and writes every distinct edge once with its
+ * latency statistics. Returns the number of distinct edges.
+ */
+pub fn write_edge_statistics(mut observations: Vec<CsvEdgeObservation>, writer: &mut Writer<File>) -> u64 {
+    // stable, so that the observations of an edge stay in the order of the edge list
+    observations.sort_by_key(|edge| (edge.from, edge.to));
+
+    let mut statistics = EdgeStatisticsWriter::new(writer);
+    for edge in observations {
+        statistics.add(edge.from, edge.to, edge.rtt, edge.rtt_delta);
+    }
+    statistics.finish()
+}
+
+/**
+ * Calculates the median in O(n) by partially sorting the given values in place.
+ */
+pub fn median<T: Ord + Copy + Into<i64>>(values: &mut [T]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let length = values.len();
+    let (lower, &mut upper_median, _) = values.select_nth_unstable(length / 2);
+
+    if length % 2 == 1 {
+        Some(upper_median.into() as f64)
+    } else {
+        let lower_median = *lower.iter().max().unwrap();
+        Some((lower_median.into() + upper_median.into()) as f64 / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&mut [5u32, 1, 3]), Some(3.0));
+        assert_eq!(median(&mut [4i64, -2, 10, 1]), Some(2.5));
+        assert_eq!(median::<u32>(&mut []), None);
+    }
+
+    #[test]
+    fn statistics_are_exact_up_to_the_sample_limit() {
+        let mut samples = LatencySamples::new(0);
+        for rtt in (1..=MEDIAN_SAMPLE_LIMIT as u32).rev() {
+            samples.add(Some(rtt), Some(rtt as i64 - 10));
+        }
+        samples.add(None, None);
+
+        let expected_median = (MEDIAN_SAMPLE_LIMIT as f64 + 1.0) / 2.0;
+        assert_eq!(samples.statistics(), (Some(1), Some(expected_median), Some(expected_median - 10.0)));
+    }
+
+    #[test]
+    fn sample_stays_bounded_and_keeps_the_exact_minimum() {
+        let mut samples = LatencySamples::new(0);
+        for rtt in 0..10_000u32 {
+            samples.add(Some(10_000 - rtt), None);
+        }
+
+        assert_eq!(samples.rtts.values.len(), MEDIAN_SAMPLE_LIMIT);
+        assert_eq!(samples.rtts.count, 10_000);
+
+        let (rtt_min, rtt_median, rtt_delta_median) = samples.statistics();
+        assert_eq!(rtt_min, Some(1));
+        assert!((rtt_median.unwrap() - 5_000.0).abs() < 2_000.0);
+        assert_eq!(rtt_delta_median, None);
+    }
+
+    #[test]
+    fn edges_draw_independent_samples() {
+        let mut first = LatencySamples::new(0);
+        let mut second = LatencySamples::new(1);
+        for rtt in 0..1_000u32 {
+            first.add(Some(rtt), None);
+            second.add(Some(rtt), None);
+        }
+
+        assert_ne!(first.rtts.values, second.rtts.values);
+    }
+}
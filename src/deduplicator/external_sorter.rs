@@ -0,0 +1,292 @@
+/*!
+ * Out-of-core edge deduplication. The edge observations are sorted in runs of bounded size which
+ * are stored on disk, then merged and deduplicated while streaming.
+ */
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use csv::Writer;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::deduplicator::edge_latency::{EdgeStatisticsWriter, read_edge_observations};
+
+#[derive(Serialize, Deserialize)]
+struct EdgeRecord {
+    from: i64,
+    to: i64,
+    rtt: Option<u32>,
+    rtt_delta: Option<i64>,
+}
+
+impl EdgeRecord {
+    fn key(&self) -> (i64, i64) {
+        (self.from, self.to)
+    }
+}
+
+/**
+ * Share of the memory budget used for the records of a run. The rest is left for the sorting, the
+ * CSV and run buffers, the latency samples during merging and allocator overhead.
+ */
+const RUN_BUDGET_FRACTION: f64 = 0.5;
+
+/**
+ * Maximum number of runs merged (and thus files opened) at once. With more runs, groups of runs are
+ * first merged into intermediate runs, so that the default limit of open files is never reached.
+ */
+const MAX_MERGE_FAN_IN: usize = 128;
+
+pub struct ExternalSorter {
+    run_directory: PathBuf,
+    records_per_run: usize,
+    merge_fan_in: usize,
+}
+
+impl ExternalSorter {
+    pub fn new(intermediate_path: &Path, memory_budget_mb: usize) -> ExternalSorter {
+        let run_budget_bytes = (memory_budget_mb * 1024 * 1024) as f64 * RUN_BUDGET_FRACTION;
+        let records_per_run = run_budget_bytes as usize / mem::size_of::<EdgeRecord>();
+
+        ExternalSorter {
+            run_directory: intermediate_path.join("deduplication"),
+            records_per_run: records_per_run.max(1),
+            merge_fan_in: MAX_MERGE_FAN_IN,
+        }
+    }
+
+    /**
+     * Sorts the edge observations of the given edge list by (from, to) and writes every distinct
+     * edge once, together with its latency statistics. Returns the number of distinct edges.
+     * Malformed rows are skipped and logged. While merging, only the latency statistics of the
+     * current edge are kept in memory, which are bounded in size (-> see edge_latency.rs).
+     * The observations of an edge keep their order in the edge list, so the statistics are the
+     * same as when sorting in memory.
+     */
+    pub fn deduplicate(&self, edges_path: &Path, writer: &mut Writer<File>) -> io::Result<u64> {
+        fs::create_dir_all(&self.run_directory)?;
+
+        info!("Sorting edges in runs of at most {} edges...", self.records_per_run);
+        let mut runs = self.write_sorted_runs(edges_path)?;
+
+        let mut pass = 1;
+        while runs.len() > self.merge_fan_in {
+            info!("Merging {} sorted runs in groups of {}...", runs.len(), self.merge_fan_in);
+            runs = self.merge_pass(&runs, pass)?;
+            pass += 1;
+        }
+
+        info!("Merging {} sorted runs...", runs.len());
+        let mut statistics = EdgeStatisticsWriter::new(writer);
+        Self::merge_runs(&runs, |record| {
+            statistics.add(record.from, record.to, record.rtt, record.rtt_delta);
+            Ok(())
+        })?;
+
+        for run in runs {
+            fs::remove_file(run)?;
+        }
+
+        Ok(statistics.finish())
+    }
+
+    fn write_sorted_runs(&self, edges_path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut runs = Vec::new();
+        let mut records: Vec<EdgeRecord> = Vec::with_capacity(self.records_per_run);
+
+        let mut result = Ok(());
+        read_edge_observations(edges_path, |edge| {
+            if result.is_err() {
+                return;
+            }
+
+            records.push(EdgeRecord {
+                from: edge.from,
+                to: edge.to,
+                rtt: edge.rtt,
+                rtt_delta: edge.rtt_delta,
+            });
+
+            if records.len() == self.records_per_run {
+                result = self.write_run(runs.len(), &mut records).map(|run| runs.push(run));
+            }
+        });
+        result?;
+
+        if !records.is_empty() {
+            runs.push(self.write_run(runs.len(), &mut records)?);
+        }
+
+        Ok(runs)
+    }
+
+    fn write_run(&self, run_index: usize, records: &mut Vec<EdgeRecord>) -> io::Result<PathBuf> {
+        // stable, so that the observations of an edge stay in the order of the edge list
+        records.sort_by_key(EdgeRecord::key);
+
+        let path = self.run_directory.join(format!("run_{}.bin", run_index));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for record in records.iter() {
+            Self::write_record(&mut writer, record)?;
+        }
+        writer.flush()?;
+        records.clear();
+
+        Ok(path)
+    }
+
+    /**
+     * Merges groups of at most merge_fan_in runs into one run each and removes the merged runs.
+     * The groups are consecutive, so the merged runs keep the order of the edge list.
+     */
+    fn merge_pass(&self, runs: &[PathBuf], pass: usize) -> io::Result<Vec<PathBuf>> {
+        let mut merged_runs = Vec::new();
+        for group in runs.chunks(self.merge_fan_in) {
+            let path = self.run_directory.join(format!("run_{}_{}.bin", pass, merged_runs.len()));
+            let mut writer = BufWriter::new(File::create(&path)?);
+            Self::merge_runs(group, |record| Self::write_record(&mut writer, &record))?;
+            writer.flush()?;
+
+            for run in group {
+                fs::remove_file(run)?;
+            }
+            merged_runs.push(path);
+        }
+
+        Ok(merged_runs)
+    }
+
+    /**
+     * Passes the records of all given runs to the consumer in (from, to) order. Records with the same
+     * key are passed in the order of the runs.
+     */
+    fn merge_runs<F>(runs: &[PathBuf], mut consumer: F) -> io::Result<()>
+        where F: FnMut(EdgeRecord) -> io::Result<()> {
+        let mut readers: Vec<BufReader<File>> = Vec::with_capacity(runs.len());
+        for run in runs {
+            readers.push(BufReader::new(File::open(run)?));
+        }
+
+        let mut heap: BinaryHeap<Reverse<((i64, i64), usize)>> = BinaryHeap::new();
+        let mut heads: Vec<Option<EdgeRecord>> = Vec::with_capacity(readers.len());
+        for (run_index, reader) in readers.iter_mut().enumerate() {
+            let head = Self::read_record(reader)?;
+            if let Some(record) = &head {
+                heap.push(Reverse((record.key(), run_index)));
+            }
+            heads.push(head);
+        }
+
+        while let Some(Reverse((_, run_index))) = heap.pop() {
+            let record = heads[run_index].take().unwrap();
+            consumer(record)?;
+
+            let next = Self::read_record(&mut readers[run_index])?;
+            if let Some(next_record) = &next {
+                heap.push(Reverse((next_record.key(), run_index)));
+            }
+            heads[run_index] = next;
+        }
+
+        Ok(())
+    }
+
+    fn read_record(reader: &mut BufReader<File>) -> io::Result<Option<EdgeRecord>> {
+        match bincode::deserialize_from(reader) {
+            Ok(record) => Ok(Some(record)),
+            Err(error) => match *error {
+                // The runs do not store a record count, so the end of the file shows up as an error
+                bincode::ErrorKind::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+                error => Err(io::Error::other(error)),
+            },
+        }
+    }
+
+    fn write_record(writer: &mut BufWriter<File>, record: &EdgeRecord) -> io::Result<()> {
+        bincode::serialize_into(writer, record).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::env;
+
+    use super::*;
+    use crate::common::random::Random;
+    use crate::deduplicator::edge_latency::{MEDIAN_SAMPLE_LIMIT, write_edge_statistics};
+
+    const ROW_COUNT: usize = 3_000;
+
+    /**
+     * Writes a random edge list with a malformed row to a fresh directory. The edges are observed
+     * more often than MEDIAN_SAMPLE_LIMIT on average. Returns the directory and the distinct edges.
+     */
+    fn write_edge_list(name: &str) -> (PathBuf, HashSet<(i64, i64)>) {
+        let directory = env::temp_dir().join(format!("external_sorter_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let mut random = Random::new(7);
+        let mut writer = csv::Writer::from_path(directory.join("edges.csv")).unwrap();
+        writer.write_record(["from", "to", "rtt", "rtt_delta"]).unwrap();
+        let mut edges = HashSet::new();
+        for row in 0..ROW_COUNT {
+            let from = random.below(6) as i64 - 2;
+            let to = random.below(6) as i64;
+            let rtt = (row % 7 != 0).then(|| random.below(1_000) as u32);
+            let rtt_delta = (row % 5 != 0).then(|| random.below(200) as i64 - 100);
+            writer.serialize((from, to, rtt, rtt_delta)).unwrap();
+            edges.insert((from, to));
+
+            if row == 150 {
+                writer.write_record(["a", "b", "1", "2"]).unwrap();
+            }
+        }
+        writer.flush().unwrap();
+
+        assert!(ROW_COUNT / edges.len() > MEDIAN_SAMPLE_LIMIT);
+        (directory, edges)
+    }
+
+    fn read_lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn external_sort_matches_in_memory_deduplication() {
+        let (directory, edges) = write_edge_list("dedup");
+        let edges_path = directory.join("edges.csv");
+
+        // 177 runs, merged in three passes
+        let sorter = ExternalSorter {
+            run_directory: directory.join("deduplication"),
+            records_per_run: 17,
+            merge_fan_in: 4,
+        };
+        let external_path = directory.join("external.csv");
+        let mut writer = csv::Writer::from_path(&external_path).unwrap();
+        let edge_count = sorter.deduplicate(&edges_path, &mut writer).unwrap();
+        writer.flush().unwrap();
+
+        let mut observations = Vec::new();
+        read_edge_observations(&edges_path, |edge| observations.push(edge));
+        let in_memory_path = directory.join("in_memory.csv");
+        let mut writer = csv::Writer::from_path(&in_memory_path).unwrap();
+        let in_memory_edge_count = write_edge_statistics(observations, &mut writer);
+        writer.flush().unwrap();
+
+        assert_eq!(edge_count as usize, edges.len());
+        assert_eq!(in_memory_edge_count, edge_count);
+        assert_eq!(read_lines(&external_path), read_lines(&in_memory_path));
+        assert_eq!(fs::read_dir(directory.join("deduplication")).unwrap().count(), 0);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,6 @@
+pub mod graph_exporter;
+mod node_attributes;
+mod format_writer;
+mod graphml_writer;
+mod gexf_writer;
+mod dot_writer;
@@ -0,0 +1,92 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::export::format_writer::FormatWriter;
+use crate::export::node_attributes::{AttributeColumn, AttributeKind};
+
+pub struct DotWriter {
+    edge_operator: &'static str,
+    node_columns: Vec<(String, AttributeKind)>,
+    edge_columns: Vec<(String, AttributeKind)>,
+}
+
+impl DotWriter {
+    pub fn new() -> DotWriter {
+        DotWriter {
+            edge_operator: "->",
+            node_columns: Vec::new(),
+            edge_columns: Vec::new(),
+        }
+    }
+
+    fn describe(columns: &[AttributeColumn]) -> Vec<(String, AttributeKind)> {
+        columns.iter()
+            .map(|column| (quote(&column.name), column.kind))
+            .collect()
+    }
+
+    fn write_attributes(out: &mut BufWriter<File>, columns: &[(String, AttributeKind)], values: &[Option<&str>]) {
+        let attributes: Vec<String> = columns.iter()
+            .zip(values)
+            .filter_map(|((name, kind), value)| value.map(|value| {
+                // doubles may be written in exponent notation, which is no valid DOT numeral
+                match kind {
+                    AttributeKind::Boolean | AttributeKind::Integer => format!("{}={}", name, value),
+                    AttributeKind::Double | AttributeKind::String => format!("{}={}", name, quote(value)),
+                }
+            }))
+            .collect();
+
+        if !attributes.is_empty() {
+            write!(out, " [{}]", attributes.join(", ")).unwrap();
+        }
+    }
+}
+
+impl FormatWriter for DotWriter {
+    fn extension(&self) -> &'static str {
+        "dot"
+    }
+
+    fn write_header(
+        &mut self,
+        out: &mut BufWriter<File>,
+        undirected: bool,
+        node_columns: &[AttributeColumn],
+        edge_columns: &[AttributeColumn],
+    ) {
+        self.node_columns = Self::describe(node_columns);
+        self.edge_columns = Self::describe(edge_columns);
+
+        if undirected {
+            self.edge_operator = "--";
+            writeln!(out, "graph G {{").unwrap();
+        } else {
+            self.edge_operator = "->";
+            writeln!(out, "digraph G {{").unwrap();
+        }
+    }
+
+    fn write_node(&mut self, out: &mut BufWriter<File>, node_id: i64, values: &[Option<&str>]) {
+        write!(out, "  \"{}\"", node_id).unwrap();
+        Self::write_attributes(out, &self.node_columns, values);
+        writeln!(out, ";").unwrap();
+    }
+
+    fn write_edge(&mut self, out: &mut BufWriter<File>, _edge_id: u64, from: i64, to: i64, values: &[Option<&str>]) {
+        write!(out, "  \"{}\" {} \"{}\"", from, self.edge_operator, to).unwrap();
+        Self::write_attributes(out, &self.edge_columns, values);
+        writeln!(out, ";").unwrap();
+    }
+
+    fn write_footer(&mut self, out: &mut BufWriter<File>) {
+        writeln!(out, "}}").unwrap();
+    }
+}
+
+/**
+ * Quotes the value as DOT string, which allows arbitrary characters in names and values.
+ */
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,38 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::export::node_attributes::AttributeColumn;
+
+/**
+ * A graph file format. The exporter calls the functions in the order header, nodes, edges, footer.
+ * Attribute values are passed as found in the CSV files, aligned with the declared columns.
+ */
+pub trait FormatWriter {
+    fn extension(&self) -> &'static str;
+
+    fn write_header(
+        &mut self,
+        out: &mut BufWriter<File>,
+        undirected: bool,
+        node_columns: &[AttributeColumn],
+        edge_columns: &[AttributeColumn],
+    );
+
+    fn write_node(&mut self, out: &mut BufWriter<File>, node_id: i64, values: &[Option<&str>]);
+
+    /**
+     * Called once between the last node and the first edge.
+     */
+    fn start_edges(&mut self, _out: &mut BufWriter<File>) {}
+
+    fn write_edge(&mut self, out: &mut BufWriter<File>, edge_id: u64, from: i64, to: i64, values: &[Option<&str>]);
+
+    fn write_footer(&mut self, out: &mut BufWriter<File>);
+}
+
+pub fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,94 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::export::format_writer::{escape_xml, FormatWriter};
+use crate::export::node_attributes::{AttributeColumn, AttributeKind};
+
+pub struct GexfWriter {}
+
+impl GexfWriter {
+    pub fn new() -> GexfWriter {
+        GexfWriter {}
+    }
+
+    fn type_name(kind: AttributeKind) -> &'static str {
+        match kind {
+            AttributeKind::Boolean => "boolean",
+            AttributeKind::Integer => "long",
+            AttributeKind::Double => "double",
+            AttributeKind::String => "string",
+        }
+    }
+
+    fn write_attributes(out: &mut BufWriter<File>, class: &str, columns: &[AttributeColumn]) {
+        writeln!(out, "    <attributes class=\"{}\">", class).unwrap();
+        for (index, column) in columns.iter().enumerate() {
+            writeln!(
+                out,
+                "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>",
+                index, escape_xml(&column.name), Self::type_name(column.kind)
+            ).unwrap();
+        }
+        writeln!(out, "    </attributes>").unwrap();
+    }
+
+    fn write_values(out: &mut BufWriter<File>, values: &[Option<&str>]) {
+        if values.iter().all(Option::is_none) {
+            return;
+        }
+
+        write!(out, "<attvalues>").unwrap();
+        for (index, value) in values.iter().enumerate() {
+            if let Some(value) = value {
+                write!(out, "<attvalue for=\"{}\" value=\"{}\"/>", index, escape_xml(value)).unwrap();
+            }
+        }
+        write!(out, "</attvalues>").unwrap();
+    }
+}
+
+impl FormatWriter for GexfWriter {
+    fn extension(&self) -> &'static str {
+        "gexf"
+    }
+
+    fn write_header(
+        &mut self,
+        out: &mut BufWriter<File>,
+        undirected: bool,
+        node_columns: &[AttributeColumn],
+        edge_columns: &[AttributeColumn],
+    ) {
+        let edge_type = if undirected { "undirected" } else { "directed" };
+
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+        writeln!(out, "<gexf xmlns=\"http://www.gexf.net/1.2draft\" version=\"1.2\">").unwrap();
+        writeln!(out, "  <graph mode=\"static\" defaultedgetype=\"{}\">", edge_type).unwrap();
+        Self::write_attributes(out, "node", node_columns);
+        Self::write_attributes(out, "edge", edge_columns);
+        writeln!(out, "    <nodes>").unwrap();
+    }
+
+    fn write_node(&mut self, out: &mut BufWriter<File>, node_id: i64, values: &[Option<&str>]) {
+        write!(out, "      <node id=\"{}\" label=\"{}\">", node_id, node_id).unwrap();
+        Self::write_values(out, values);
+        writeln!(out, "</node>").unwrap();
+    }
+
+    fn start_edges(&mut self, out: &mut BufWriter<File>) {
+        writeln!(out, "    </nodes>").unwrap();
+        writeln!(out, "    <edges>").unwrap();
+    }
+
+    fn write_edge(&mut self, out: &mut BufWriter<File>, edge_id: u64, from: i64, to: i64, values: &[Option<&str>]) {
+        write!(out, "      <edge id=\"{}\" source=\"{}\" target=\"{}\">", edge_id, from, to).unwrap();
+        Self::write_values(out, values);
+        writeln!(out, "</edge>").unwrap();
+    }
+
+    fn write_footer(&mut self, out: &mut BufWriter<File>) {
+        writeln!(out, "    </edges>").unwrap();
+        writeln!(out, "  </graph>").unwrap();
+        writeln!(out, "</gexf>").unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use log::info;
+
+use crate::export::dot_writer::DotWriter;
+use crate::export::format_writer::FormatWriter;
+use crate::export::gexf_writer::GexfWriter;
+use crate::export::graphml_writer::GraphMlWriter;
+use crate::export::node_attributes::{AttributeColumn, AttributeKind, NodeAttributes};
+use crate::graph::common::graph::Graph;
+use crate::{ExportFormat, ExportParameters, GraphParametersToCompute, OutputPaths};
+
+pub struct GraphExporter {
+    output_paths: OutputPaths,
+    params: ExportParameters,
+    undirected: bool,
+}
+
+impl GraphExporter {
+    /**
+     * The graph parameters determine which statistic files are read (e.g. degree_undirected.csv or
+     * degree_giant.csv).
+     */
+    pub fn new(
+        output_paths: &OutputPaths,
+        params: &ExportParameters,
+        parameters: &GraphParametersToCompute,
+    ) -> GraphExporter {
+        GraphExporter {
+            output_paths: output_paths.for_graph_parameters(parameters),
+            params: params.clone(),
+            undirected: parameters.undirected,
+        }
+    }
+
+    /**
+     * Writes the deduplicated graph in all configured formats. Every node carries its IP (if
+     * known), a flag whether it is known and all computed per-node statistics. Every edge carries
+     * its latency statistics. On undirected graphs, edges present in both directions are written
+     * once.
+     *
+     *  Requires: edges_deduplicated.csv, mapping.csv and optionally the statistic CSV files
+     * Generates: graph.graphml, graph.gexf and/or graph.dot (suffixed with _undirected if undirected)
+     */
+    pub fn export_graph(&self) {
+        info!("Step: Exporting graph in formats {:?}.", self.params.formats);
+
+        let graph = Graph::new(
+            &self.output_paths,
+            /* from_deduplicated = */ true,
+            /* undirected = */ false
+        );
+
+        let mut attributes = NodeAttributes::new();
+        let is_node = |node_id: i64| graph.index_of(node_id).is_some();
+        attributes.add_csv(&self.output_paths.mapping, &is_node);
+        for metric_path in self.output_paths.node_metrics() {
+            attributes.add_csv(&metric_path, &is_node);
+        }
+
+        let mut node_columns = vec![AttributeColumn { name: "known".to_string(), kind: AttributeKind::Boolean }];
+        node_columns.extend_from_slice(attributes.columns());
+        let edge_columns = self.read_edge_columns();
+
+        for format in &self.params.formats {
+            let mut writer: Box<dyn FormatWriter> = match format {
+                ExportFormat::GraphML => Box::new(GraphMlWriter::new()),
+                ExportFormat::GEXF => Box::new(GexfWriter::new()),
+                ExportFormat::DOT => Box::new(DotWriter::new()),
+            };
+            self.export_as(writer.as_mut(), &graph, &attributes, &node_columns, &edge_columns);
+        }
+    }
+
+    /**
+     * All columns of the deduplicated edge list except from and to are exported as edge attributes.
+     */
+    fn read_edge_columns(&self) -> Vec<AttributeColumn> {
+        let mut reader = csv::Reader::from_path(&self.output_paths.edges_deduplicated).unwrap();
+        reader.headers().unwrap()
+            .iter()
+            .skip(2)
+            .map(|name| AttributeColumn { name: name.to_string(), kind: AttributeKind::Double })
+            .collect()
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn export_as(
+        &self,
+        writer: &mut dyn FormatWriter,
+        graph: &Graph,
+        attributes: &NodeAttributes,
+        node_columns: &[AttributeColumn],
+        edge_columns: &[AttributeColumn],
+    ) {
+        let path = self.output_paths.graph_export.with_extension(writer.extension());
+        info!("Writing graph to {}...", path.to_str().unwrap());
+
+        let file = File::create(&path).expect(&format!(
+            "Could not create file for exporting the graph at {}",
+            path.to_str().unwrap()
+        ));
+        let mut out = BufWriter::new(file);
+
+        writer.write_header(&mut out, self.undirected, node_columns, edge_columns);
+
+        for node in graph.indices() {
+            let node_id = graph.node_id(node);
+            let known = if node_id >= 0 { "true" } else { "false" };
+
+            let mut values = vec![Some(known)];
+            values.extend(attributes.values(node_id));
+            writer.write_node(&mut out, node_id, &values);
+        }
+
+        writer.start_edges(&mut out);
+
+        let mut edge_count = 0;
+        let mut reader = csv::Reader::from_path(&self.output_paths.edges_deduplicated).unwrap();
+        for record in reader.records() {
+            let record = record.unwrap();
+            let from: i64 = record[0].parse().unwrap();
+            let to: i64 = record[1].parse().unwrap();
+
+            if self.undirected && from > to && Self::has_edge(graph, to, from) {
+                continue;
+            }
+
+            let values: Vec<Option<&str>> = record.iter()
+                .skip(2)
+                .map(|value| if value.is_empty() { None } else { Some(value) })
+                .collect();
+            writer.write_edge(&mut out, edge_count, from, to, &values);
+            edge_count += 1;
+        }
+
+        writer.write_footer(&mut out);
+        out.flush().unwrap();
+
+        info!("Exported {} nodes and {} edges.", graph.node_count(), edge_count);
+    }
+
+    fn has_edge(graph: &Graph, from: i64, to: i64) -> bool {
+        graph.index_of(from)
+            .zip(graph.index_of(to))
+            .and_then(|(from, to)| graph.edge_position(from, to))
+            .is_some()
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::export::format_writer::{escape_xml, FormatWriter};
+use crate::export::node_attributes::{AttributeColumn, AttributeKind};
+
+pub struct GraphMlWriter {}
+
+impl GraphMlWriter {
+    pub fn new() -> GraphMlWriter {
+        GraphMlWriter {}
+    }
+
+    fn type_name(kind: AttributeKind) -> &'static str {
+        match kind {
+            AttributeKind::Boolean => "boolean",
+            AttributeKind::Integer => "long",
+            AttributeKind::Double => "double",
+            AttributeKind::String => "string",
+        }
+    }
+
+    fn write_keys(out: &mut BufWriter<File>, domain: &str, prefix: &str, columns: &[AttributeColumn]) {
+        for (index, column) in columns.iter().enumerate() {
+            writeln!(
+                out,
+                "  <key id=\"{}{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+                prefix, index, domain, escape_xml(&column.name), Self::type_name(column.kind)
+            ).unwrap();
+        }
+    }
+
+    fn write_data(out: &mut BufWriter<File>, prefix: &str, values: &[Option<&str>]) {
+        for (index, value) in values.iter().enumerate() {
+            if let Some(value) = value {
+                write!(out, "<data key=\"{}{}\">{}</data>", prefix, index, escape_xml(value)).unwrap();
+            }
+        }
+    }
+}
+
+impl FormatWriter for GraphMlWriter {
+    fn extension(&self) -> &'static str {
+        "graphml"
+    }
+
+    fn write_header(
+        &mut self,
+        out: &mut BufWriter<File>,
+        undirected: bool,
+        node_columns: &[AttributeColumn],
+        edge_columns: &[AttributeColumn],
+    ) {
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+        writeln!(
+            out,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\" \
+            xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+            xsi:schemaLocation=\"http://graphml.graphdrawing.org/xmlns \
+            http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd\">"
+        ).unwrap();
+        Self::write_keys(out, "node", "n", node_columns);
+        Self::write_keys(out, "edge", "e", edge_columns);
+
+        let edge_default = if undirected { "undirected" } else { "directed" };
+        writeln!(out, "  <graph id=\"G\" edgedefault=\"{}\">", edge_default).unwrap();
+    }
+
+    fn write_node(&mut self, out: &mut BufWriter<File>, node_id: i64, values: &[Option<&str>]) {
+        write!(out, "    <node id=\"{}\">", node_id).unwrap();
+        Self::write_data(out, "n", values);
+        writeln!(out, "</node>").unwrap();
+    }
+
+    fn write_edge(&mut self, out: &mut BufWriter<File>, edge_id: u64, from: i64, to: i64, values: &[Option<&str>]) {
+        write!(out, "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">", edge_id, from, to).unwrap();
+        Self::write_data(out, "e", values);
+        writeln!(out, "</edge>").unwrap();
+    }
+
+    fn write_footer(&mut self, out: &mut BufWriter<File>) {
+        writeln!(out, "  </graph>").unwrap();
+        writeln!(out, "</graphml>").unwrap();
+    }
+}
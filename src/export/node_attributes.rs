@@ -0,0 +1,209 @@
+/*!
+ * Joins arbitrary CSV files with a node_id column into one attribute table per node. The values
+ * are kept as they appear in the CSV files, only the type of every column is inferred.
+ */
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use log::{info, warn};
+
+const NODE_ID_COLUMN: &str = "node_id";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AttributeKind {
+    Boolean,
+    Integer,
+    Double,
+    String,
+}
+
+impl AttributeKind {
+    fn infer(values: &[&str]) -> AttributeKind {
+        if values.iter().all(|value| value.parse::<i64>().is_ok()) {
+            AttributeKind::Integer
+        } else if values.iter().all(|value| value.parse::<f64>().is_ok()) {
+            AttributeKind::Double
+        } else {
+            AttributeKind::String
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AttributeColumn {
+    pub name: String,
+    pub kind: AttributeKind,
+}
+
+pub struct NodeAttributes {
+    columns: Vec<AttributeColumn>,
+    values: HashMap<i64, Vec<Option<String>>>,
+}
+
+impl NodeAttributes {
+    pub fn new() -> NodeAttributes {
+        NodeAttributes {
+            columns: Vec::new(),
+            values: HashMap::new(),
+        }
+    }
+
+    /**
+     * Adds all columns (except node_id) of the given CSV file. Files that do not exist (e.g. because
+     * the statistic was not computed) are skipped. A column whose name is already taken is skipped if
+     * it only repeats the values of that column (as the annotations do, e.g. asn), otherwise it is
+     * prefixed with the file name (e.g. closeness_mode). Every renamed or skipped column is logged.
+     * Rows of nodes that are not part of the given node set are ignored.
+     */
+    #[allow(clippy::expect_fun_call)]
+    pub fn add_csv(&mut self, path: &Path, nodes: &dyn Fn(i64) -> bool) {
+        if !path.exists() {
+            info!("No attributes found at {}, skipping.", path.to_str().unwrap());
+            return;
+        }
+
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let node_id_position = match headers.iter().position(|header| header == NODE_ID_COLUMN) {
+            Some(position) => position,
+            None => {
+                info!("{} has no {} column, skipping.", path.to_str().unwrap(), NODE_ID_COLUMN);
+                return;
+            }
+        };
+
+        // (position in the file, column name, column with the same original name)
+        let file_stem = path.file_stem().unwrap().to_str().unwrap();
+        let mut new_columns: Vec<(usize, String, Option<usize>)> = Vec::new();
+        for (position, header) in headers.iter().enumerate() {
+            if position == node_id_position {
+                continue;
+            }
+
+            match self.columns.iter().position(|column| column.name == header) {
+                Some(existing_column) => {
+                    let prefixed_name = format!("{}_{}", file_stem, header);
+                    if self.has_column(&prefixed_name) {
+                        warn!(
+                            "Columns {} and {} of {} are already taken, skipping.",
+                            header, prefixed_name, path.to_str().unwrap()
+                        );
+                    } else {
+                        new_columns.push((position, prefixed_name, Some(existing_column)));
+                    }
+                }
+                None => new_columns.push((position, header.to_string(), None)),
+            }
+        }
+
+        let first_new_column = self.columns.len();
+        let column_count = first_new_column + new_columns.len();
+        for record in reader.records() {
+            let record = record.expect(&format!("Invalid row in {}", path.to_str().unwrap()));
+            let node_id: i64 = match record[node_id_position].parse() {
+                Ok(node_id) if nodes(node_id) => node_id,
+                _ => continue,
+            };
+
+            let row = self.values.entry(node_id).or_default();
+            row.resize(column_count, None);
+            for (offset, (position, _, _)) in new_columns.iter().enumerate() {
+                let value = &record[*position];
+                if !value.is_empty() {
+                    row[first_new_column + offset] = Some(value.to_string());
+                }
+            }
+        }
+
+        let mut keep = vec![true; column_count];
+        for (offset, (_, name, existing_column)) in new_columns.into_iter().enumerate() {
+            let column = first_new_column + offset;
+            if let Some(existing_column) = existing_column {
+                let existing_name = &self.columns[existing_column].name;
+                // only the rows of this file reach the new columns
+                let is_copy = self.values.values()
+                    .filter(|row| row.len() == column_count)
+                    .all(|row| row[column] == row[existing_column]);
+                if is_copy {
+                    info!(
+                        "Column {} of {} repeats the values of the existing column, skipping.",
+                        existing_name, path.to_str().unwrap()
+                    );
+                    keep[column] = false;
+                    continue;
+                }
+                info!(
+                    "Column {} of {} is already taken, adding it as {}.",
+                    existing_name, path.to_str().unwrap(), name
+                );
+            }
+
+            let values: Vec<&str> = self.values.values()
+                .filter_map(|row| row.get(column).and_then(|value| value.as_deref()))
+                .collect();
+            self.columns.push(AttributeColumn { name, kind: AttributeKind::infer(&values) });
+        }
+
+        if keep.contains(&false) {
+            for row in self.values.values_mut() {
+                let mut column = 0;
+                row.retain(|_| {
+                    column += 1;
+                    keep[column - 1]
+                });
+            }
+        }
+
+        info!("Added attributes from {}", path.to_str().unwrap());
+    }
+
+    fn has_column(&self, name: &str) -> bool {
+        self.columns.iter().any(|column| column.name == name)
+    }
+
+    pub fn columns(&self) -> &[AttributeColumn] {
+        &self.columns
+    }
+
+    /**
+     * Returns the values of the node, aligned with the columns. Missing values are None.
+     */
+    pub fn values(&self, node_id: i64) -> Vec<Option<&str>> {
+        let row = self.values.get(&node_id);
+        (0..self.columns.len())
+            .map(|column| row.and_then(|row| row.get(column)).and_then(|value| value.as_deref()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn repeated_columns_are_skipped_and_conflicting_ones_prefixed() {
+        let directory = env::temp_dir().join(format!("node_attributes_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let mapping_path = directory.join("mapping.csv");
+        let closeness_path = directory.join("closeness.csv");
+        fs::write(&mapping_path, "ip,node_id,asn,mode\n1.1.1.1,1,13335,a\n8.8.8.8,2,15169,a\n").unwrap();
+        fs::write(&closeness_path, "node_id,closeness,asn,mode\n1,0.5,13335,b\n2,0.25,15169,b\n").unwrap();
+
+        let mut attributes = NodeAttributes::new();
+        attributes.add_csv(&mapping_path, &|_| true);
+        attributes.add_csv(&closeness_path, &|_| true);
+
+        let names: Vec<&str> = attributes.columns().iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, vec!["ip", "asn", "mode", "closeness", "closeness_mode"]);
+        assert_eq!(
+            attributes.values(2),
+            vec![Some("8.8.8.8"), Some("15169"), Some("a"), Some("0.25"), Some("b")]
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
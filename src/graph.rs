@@ -1,4 +1,12 @@
 pub mod grapher;
 pub mod common;
+mod assortativity;
 mod betweenness;
+mod closeness;
+mod clustering;
+pub mod communities;
+mod components;
+mod cores;
+mod distances;
+mod spectral;
 mod degree;
@@ -0,0 +1,2 @@
+pub mod assortativity_calculator;
+mod rich_club;
@@ -0,0 +1,194 @@
+use log::{info, warn};
+use rayon::prelude::*;
+
+use crate::{AssortativityParameters, OutputPaths};
+use crate::common::random::Random;
+use crate::graph::assortativity::rich_club::UndirectedEdges;
+use crate::graph::common::graph::{Graph, NodeIndex};
+
+#[derive(Clone, Copy)]
+enum DegreeKind {
+    In,
+    Out,
+}
+
+pub struct AssortativityCalculator {
+    graph: Graph,
+    params: AssortativityParameters,
+}
+
+impl AssortativityCalculator {
+    pub fn new(graph: Graph, params: AssortativityParameters) -> AssortativityCalculator {
+        AssortativityCalculator {
+            graph,
+            params,
+        }
+    }
+
+    /**
+     * Calculates the degree assortativity and writes it to the assortativity CSV file. If enabled,
+     * the rich-club coefficients are written to the rich club CSV file.
+     */
+    pub fn calculate_and_persist(&self, output_paths: &OutputPaths) {
+        self.write_assortativity(output_paths);
+        if self.params.rich_club {
+            self.write_rich_club(output_paths);
+        }
+    }
+
+    /**
+     * The degree assortativity is the Pearson correlation of the degrees at both ends of all edges,
+     * with the given kind of degree at the source and the target. On directed graphs, all four
+     * combinations of in and out degree are written (in networkx, x is the source and y the target
+     * degree). On undirected graphs, every edge counts in both directions, so there is only one.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn write_assortativity(&self, output_paths: &OutputPaths) {
+        let path = &output_paths.assortativity;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing assortativity at {}", path.to_str().unwrap()));
+        writer.serialize(("source_degree", "target_degree", "assortativity")).unwrap();
+
+        let combinations = if self.graph.is_undirected() {
+            vec![(DegreeKind::Out, DegreeKind::Out)]
+        } else {
+            vec![
+                (DegreeKind::In, DegreeKind::In),
+                (DegreeKind::In, DegreeKind::Out),
+                (DegreeKind::Out, DegreeKind::In),
+                (DegreeKind::Out, DegreeKind::Out),
+            ]
+        };
+
+        for (source_kind, target_kind) in combinations {
+            let assortativity = self.degree_assortativity(source_kind, target_kind);
+            let (source_name, target_name) = (self.kind_name(source_kind), self.kind_name(target_kind));
+            info!("Degree ASSORTATIVITY ({} - {}): {}", source_name, target_name, assortativity);
+            writer.serialize((source_name, target_name, assortativity)).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    /**
+     * The Pearson correlation of the source and target degrees over all edges, computed with the
+     * means subtracted first for numerical stability. NaN if one of the degrees does not vary.
+     */
+    fn degree_assortativity(&self, source_kind: DegreeKind, target_kind: DegreeKind) -> f64 {
+        let source_degree = |v: NodeIndex| self.degree(v, source_kind);
+        let target_degree = |v: NodeIndex| self.degree(v, target_kind);
+        let edge_count = self.graph.edge_count() as f64;
+        let (mut source_sum, mut target_sum) = (0.0, 0.0);
+        for v in self.graph.indices() {
+            let neighbors = self.graph.neighbors(v);
+            source_sum += (neighbors.len() * source_degree(v)) as f64;
+            target_sum += neighbors.iter().map(|&w| target_degree(w)).sum::<usize>() as f64;
+        }
+        let (source_mean, target_mean) = (source_sum / edge_count, target_sum / edge_count);
+
+        let (mut covariance, mut source_variance, mut target_variance) = (0.0, 0.0, 0.0);
+        for v in self.graph.indices() {
+            let x = source_degree(v) as f64 - source_mean;
+            for &w in self.graph.neighbors(v) {
+                let y = target_degree(w) as f64 - target_mean;
+                covariance += x * y;
+                source_variance += x * x;
+                target_variance += y * y;
+            }
+        }
+
+        covariance / (source_variance * target_variance).sqrt()
+    }
+
+    fn degree(&self, v: NodeIndex, kind: DegreeKind) -> usize {
+        match kind {
+            DegreeKind::In => self.graph.in_degree(v),
+            DegreeKind::Out => self.graph.out_degree(v),
+        }
+    }
+
+    fn kind_name(&self, kind: DegreeKind) -> &'static str {
+        match (self.graph.is_undirected(), kind) {
+            (true, _) => "degree",
+            (false, DegreeKind::In) => "in",
+            (false, DegreeKind::Out) => "out",
+        }
+    }
+
+    /**
+     * Writes the rich-club coefficient of every degree. Edge directions are ignored. If
+     * randomizations are requested, the coefficients are additionally divided by the average
+     * coefficients of as many random graphs with the same degrees.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn write_rich_club(&self, output_paths: &OutputPaths) {
+        info!("Calculating the RICH-CLUB COEFFICIENT");
+        let undirected_edges = UndirectedEdges::new(&self.graph);
+        let levels = undirected_edges.rich_club();
+
+        let random_coefficients = if self.params.randomizations > 0 {
+            self.random_coefficients(&undirected_edges, levels.len())
+        } else {
+            Vec::new()
+        };
+
+        let path = &output_paths.rich_club;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing the rich club at {}", path.to_str().unwrap()));
+        if random_coefficients.is_empty() {
+            writer.serialize(("degree", "nodes", "edges", "rich_club")).unwrap();
+            for level in &levels {
+                writer.serialize((level.degree, level.nodes, level.edges, level.coefficient)).unwrap();
+            }
+        } else {
+            writer.serialize(("degree", "nodes", "edges", "rich_club", "rich_club_random", "rich_club_normalized"))
+                .unwrap();
+            for (level, random_coefficient) in levels.iter().zip(random_coefficients) {
+                let normalized = level.coefficient / random_coefficient;
+                writer.serialize(
+                    (level.degree, level.nodes, level.edges, level.coefficient, random_coefficient, normalized)
+                ).unwrap();
+            }
+        }
+        writer.flush().unwrap();
+    }
+
+    /**
+     * The average rich-club coefficients of the randomized graphs. Every graph is obtained by
+     * swaps_per_edge double edge swaps per edge, in parallel. Since the degrees stay the same, the
+     * coefficients exist for the same degrees as for the graph itself.
+     */
+    fn random_coefficients(&self, undirected_edges: &UndirectedEdges, level_count: usize) -> Vec<f64> {
+        let swaps = self.params.swaps_per_edge as u64 * undirected_edges.edge_count() as u64;
+        info!(
+            "Randomizing the graph {} times with {} double edge swaps each",
+            self.params.randomizations, swaps
+        );
+
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.params.max_thread_count as usize)
+            .build()
+            .expect("Could not create thread pool for the rich club randomizations");
+
+        let sums = thread_pool.install(|| {
+            (0..self.params.randomizations).into_par_iter()
+                .map(|index| {
+                    let mut random = Random::for_stream(self.params.seed, index as u64);
+                    let (randomized, done) = undirected_edges.randomized(swaps, &mut random);
+                    if done < swaps {
+                        warn!("Randomization {} only managed {} of {} double edge swaps", index, done, swaps);
+                    }
+                    randomized.rich_club().into_iter().map(|level| level.coefficient).collect::<Vec<f64>>()
+                })
+                .reduce(
+                    || vec![0.0; level_count],
+                    |a, b| a.iter().zip(&b).map(|(x, y)| x + y).collect(),
+                )
+        });
+
+        sums.into_iter().map(|sum| sum / self.params.randomizations as f64).collect()
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
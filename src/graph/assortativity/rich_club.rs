@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use crate::common::random::Random;
+use crate::graph::common::graph::{Graph, NodeIndex};
+
+pub struct RichClubLevel {
+    pub degree: usize,
+    pub nodes: usize,
+    pub edges: usize,
+    pub coefficient: f64,
+}
+
+/**
+ * The graph without directions and self loops as list of edges (u, w) with u < w, together with the
+ * degree of every node in it.
+ */
+pub struct UndirectedEdges {
+    edges: Vec<(NodeIndex, NodeIndex)>,
+    degrees: Vec<usize>,
+}
+
+impl UndirectedEdges {
+    pub fn new(graph: &Graph) -> UndirectedEdges {
+        let mut edges = Vec::new();
+        let mut degrees = vec![0; graph.node_count()];
+        for v in graph.indices() {
+            graph.for_each_undirected_neighbor(v, |w, _| {
+                degrees[v as usize] += 1;
+                if v < w {
+                    edges.push((v, w));
+                }
+            });
+        }
+
+        UndirectedEdges {
+            edges,
+            degrees,
+        }
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /**
+     * The rich-club coefficient phi(k) = 2 E_k / (N_k (N_k - 1)) for every degree k with more than
+     * one node of a higher degree, where N_k is the number of these nodes and E_k the number of
+     * edges among them.
+     */
+    pub fn rich_club(&self) -> Vec<RichClubLevel> {
+        let max_degree = self.degrees.iter().copied().max().unwrap_or(0);
+
+        // nodes and edges by degree (of the lower degree node for edges), summed up from the top
+        let mut nodes = vec![0usize; max_degree + 1];
+        for &degree in &self.degrees {
+            nodes[degree] += 1;
+        }
+        let mut edges = vec![0usize; max_degree + 1];
+        for &(u, w) in &self.edges {
+            edges[self.degrees[u as usize].min(self.degrees[w as usize])] += 1;
+        }
+
+        let mut levels = Vec::new();
+        let (mut richer_nodes, mut richer_edges) = (0, 0);
+        for degree in (0..max_degree).rev() {
+            richer_nodes += nodes[degree + 1];
+            richer_edges += edges[degree + 1];
+            if richer_nodes > 1 {
+                levels.push(RichClubLevel {
+                    degree,
+                    nodes: richer_nodes,
+                    edges: richer_edges,
+                    coefficient: 2.0 * richer_edges as f64 / (richer_nodes * (richer_nodes - 1)) as f64,
+                });
+            }
+        }
+
+        levels.reverse();
+        levels
+    }
+
+    /**
+     * Returns a random graph with the same degrees, obtained by the given number of double edge
+     * swaps: two edges (a, b) and (c, d) are replaced by (a, d) and (c, b), unless this creates a
+     * self loop or an existing edge. Gives up after ten times as many attempts. Returns the graph
+     * and the number of swaps done.
+     */
+    pub fn randomized(&self, swaps: u64, random: &mut Random) -> (UndirectedEdges, u64) {
+        let normalize = |u: NodeIndex, w: NodeIndex| if u < w { (u, w) } else { (w, u) };
+        let mut edges = self.edges.clone();
+        let mut existing: HashSet<(NodeIndex, NodeIndex)> = edges.iter().copied().collect();
+        let edge_count = edges.len();
+
+        let mut done = 0;
+        if edge_count < 2 {
+            return (UndirectedEdges { edges, degrees: self.degrees.clone() }, done);
+        }
+
+        for _ in 0..swaps.saturating_mul(10) {
+            if done == swaps {
+                break;
+            }
+
+            let (i, j) = (random.below(edge_count), random.below(edge_count));
+            let (a, b) = edges[i];
+            let (mut c, mut d) = edges[j];
+            if random.next_u64() & 1 == 1 {
+                (c, d) = (d, c);
+            }
+            if a == c || b == d || a == d || c == b {
+                continue;
+            }
+
+            let (first, second) = (normalize(a, d), normalize(c, b));
+            if existing.contains(&first) || existing.contains(&second) {
+                continue;
+            }
+
+            existing.remove(&edges[i]);
+            existing.remove(&edges[j]);
+            existing.insert(first);
+            existing.insert(second);
+            edges[i] = first;
+            edges[j] = second;
+            done += 1;
+        }
+
+        (UndirectedEdges { edges, degrees: self.degrees.clone() }, done)
+    }
+}
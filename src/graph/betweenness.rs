@@ -1,2 +1,3 @@
 pub mod brandes_calculator;
-mod brandes_memory;
\ No newline at end of file
+pub mod brandes_memory;
+mod path_sampler;
@@ -1,26 +1,26 @@
-use std::collections::{HashSet, VecDeque};
-use std::fs;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use csv::Writer;
-use log::info;
+use log::{info, warn};
 use pbr::ProgressBar;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use crate::BetweennessParameters;
+use crate::{BetweennessMode, BetweennessParameters};
 
+use crate::common::random::Random;
 use crate::graph::betweenness::brandes_memory::BrandesMemory;
-use crate::graph::common::graph::Graph;
-use crate::graph::common::sparse_list::SparseList;
-use crate::preprocess::file_util::write_binary_to_file;
-
-#[derive(Serialize)]
-#[derive(Deserialize)]
-struct BrandesThreadState {
-    index: u32,
-    counter: u32,
-    local_c_list: SparseList<f64>,
+use crate::graph::betweenness::path_sampler::PathSampler;
+use crate::graph::common::graph::{Graph, NodeIndex};
+use crate::graph::common::source_runner::{SourceRunner, SourceVisitor};
+
+/**
+ * The dependencies accumulated per node and, if edge betweenness is requested, per edge (indexed by
+ * edge position, empty otherwise).
+ */
+#[derive(Serialize, Deserialize)]
+pub struct BetweennessState {
+    nodes: Vec<f64>,
+    edges: Vec<f64>,
 }
 
 pub struct BrandesCalculator {
@@ -28,36 +28,107 @@ pub struct BrandesCalculator {
     intermediate_folder_path: PathBuf,
     params: BetweennessParameters,
     writer: Writer<File>,
+    edge_writer: Option<Writer<File>>,
+    node_column: &'static str,
+    // weight of the dependencies of every sampled source node, empty if all nodes are sources
+    source_weights: Vec<f64>,
 }
 
 impl BrandesCalculator {
     pub fn new(
         graph: Graph,
-        intermediate_folder_path: &PathBuf,
+        intermediate_folder_path: &Path,
         params: BetweennessParameters,
         writer: Writer<File>
     ) -> BrandesCalculator {
         BrandesCalculator {
             graph,
-            intermediate_folder_path: intermediate_folder_path.clone(),
+            intermediate_folder_path: intermediate_folder_path.to_path_buf(),
             params,
-            writer
+            writer,
+            edge_writer: None,
+            node_column: "node_id",
+            source_weights: Vec::new(),
         }
     }
 
+    /**
+     * Sets the header of the node column, for graphs whose nodes are not router-level node IDs.
+     */
+    pub fn with_node_column(mut self, node_column: &'static str) -> BrandesCalculator {
+        self.node_column = node_column;
+        self
+    }
+
+    /**
+     * Additionally calculates the edge betweenness and writes it to the given writer.
+     */
+    pub fn with_edge_writer(mut self, edge_writer: Writer<File>) -> BrandesCalculator {
+        self.edge_writer = Some(edge_writer);
+        self
+    }
+
     /**
      * Calculates the betweenness centrality for each node and writes the values to the betweenness CSV file.
+     * On undirected graphs, every shortest path is found from both of its ends, so the values are halved.
+     * Every row states the mode and the sample size (source nodes or sampled paths) that produced it.
+     * The edge betweenness, if requested, is written by the same Brandes pass.
      */
     pub fn calculate_and_persist(&mut self) {
-        let c_list = &self.compute_betweenness_in_parallel();
+        let (c_list, sample_size) = match self.params.mode {
+            BetweennessMode::Adaptive => {
+                if self.edge_writer.take().is_some() {
+                    warn!("The edge betweenness is not available with the Adaptive mode and is skipped");
+                }
+                PathSampler::new(&self.graph, &self.params, &self.intermediate_folder_path).estimate()
+            }
+            _ => {
+                let (result, sample_size) = self.compute_betweenness_in_parallel();
+                self.write_edge_betweenness(&result.edges);
+                (result.nodes, sample_size)
+            }
+        };
+        let normalization = if self.graph.is_undirected() { 2.0 } else { 1.0 };
+        let mode = format!("{:?}", self.params.mode);
 
-        self.writer.serialize(("node_id", "betweenness")).unwrap();
-        for s in self.graph.boundaries().range_inclusive() {
-            let value = c_list[s];
+        self.writer.serialize((self.node_column, "betweenness", "mode", "sample_size")).unwrap();
+        for s in self.graph.indices() {
+            let value = c_list[s as usize] / normalization;
             if value != 0.0 {
-                self.writer.serialize((s, value)).unwrap();
+                self.writer.serialize((self.graph.node_id(s), value, &mode, sample_size)).unwrap();
+            }
+        }
+    }
+
+    /**
+     * Writes the betweenness of every edge that lies on a shortest path. On undirected graphs, both
+     * directions of an edge are summed up and halved, like the node values, and written once.
+     */
+    fn write_edge_betweenness(&mut self, edge_values: &[f64]) {
+        let edge_writer = match self.edge_writer.as_mut() {
+            Some(edge_writer) => edge_writer,
+            None => return,
+        };
+
+        edge_writer.serialize(("from", "to", "value")).unwrap();
+        for v in self.graph.indices() {
+            for (position, &w) in self.graph.edge_range(v).zip(self.graph.neighbors(v)) {
+                let value = if self.graph.is_undirected() {
+                    if w < v {
+                        continue;
+                    }
+                    let reverse_position = self.graph.edge_position(w, v).unwrap();
+                    (edge_values[position] + edge_values[reverse_position]) / 2.0
+                } else {
+                    edge_values[position]
+                };
+
+                if value != 0.0 {
+                    edge_writer.serialize((self.graph.node_id(v), self.graph.node_id(w), value)).unwrap();
+                }
             }
         }
+        edge_writer.flush().unwrap();
     }
 
     /**
@@ -70,211 +141,146 @@ impl BrandesCalculator {
      *
      * IMPORTANT: If intermediate files are present, it will read them in and continue computation
      * from there. If you want to start a new calculation, DELETE THE INTERMEDIATE FILES!
+     *
+     * Returns the values and the number of source nodes they were computed from.
      */
-    fn compute_betweenness_in_parallel(&mut self) -> SparseList<f64> {
-        let edges = self.graph.edges();
-
-        let nodes = edges.keys();
-
-        if self.params.save_intermediate_results_periodically {
-            info!("Saving of intermediate results to binary files is ENABLED.");
-        } else {
-            info!("Saving of intermediate results to binary files is DISABLED.");
-        }
-
-        info!("Processing {} nodes...", nodes.len());
-
-        let mut partial_results: Vec<SparseList<f64>> = Vec::new();
-
-        let mut thread_counter: u32 = 0;
-        let num_of_threads = self.params.max_thread_count as f64;
-        nodes.chunks(((nodes.len() as f64) / num_of_threads).ceil() as usize)
-            .map(|chunk| {
-                let result = (chunk, thread_counter);
-                thread_counter += 1;
-                result
-            })
-            .collect::<Vec<(&[i64], u32)>>()
-            .into_par_iter()
-            .map(|(nodes_to_visit, thread_id)| {
-                let total_node_count = nodes_to_visit.len();
-
-                let thread_info = format!("Thread {}: {} nodes", thread_id, total_node_count);
-                info!("{}", thread_info);
-
-                let (mut local_c_list, mut counter) = self.restore_or_create_state(thread_id);
-
-                Self::print_thread_progress(thread_id, counter, total_node_count);
-
-                let nodes_left_to_visit = &nodes_to_visit[(counter as usize)..];
-                for &s in nodes_left_to_visit {
-                    self.calculate_delta_for_node(edges, &mut local_c_list, s);
-                    counter += 1;
-                    let batch_size = self.params.result_batch_size;
-                    if counter % batch_size == 0 {
-                        Self::print_thread_progress(thread_id, counter, total_node_count);
-                        if self.params.save_intermediate_results_periodically {
-                            local_c_list = self.persist_current_state(thread_id, counter, local_c_list);
-                        }
-                    }
-                }
-
-                info!("Thread {}: finished", thread_id);
-                local_c_list
-            })
-            .collect_into_vec(&mut partial_results);
+    fn compute_betweenness_in_parallel(&mut self) -> (BetweennessState, u64) {
+        let (nodes, sample_size) = self.select_sources();
+        let graph = &self.graph;
+
+        // checkpoints with edge values are kept apart, as their states differ
+        let directory_name = match (self.edge_writer.is_some(), graph.is_undirected()) {
+            (false, false) => "betweenness",
+            (false, true) => "betweenness_undirected",
+            (true, false) => "betweenness_with_edges",
+            (true, true) => "betweenness_with_edges_undirected",
+        };
+        let runner = SourceRunner::new(
+            &self.intermediate_folder_path.join(directory_name),
+            self.params.save_intermediate_results_periodically,
+            self.params.result_batch_size,
+            self.params.max_thread_count,
+        );
+        let partial_results = runner.run(self, graph.content_hash(), &nodes);
 
         let result_count = partial_results.len() as u64;
         let mut progress_bar = ProgressBar::new(result_count);
-        let mut global_c_list = SparseList::new(0.0);
+        let mut global_result = self.fresh_state();
         for result in partial_results {
-            for (&node, &value) in result.iter() {
-                global_c_list[node] += value;
+            for (global_value, value) in global_result.nodes.iter_mut().zip(result.nodes) {
+                *global_value += value;
+            }
+            for (global_value, value) in global_result.edges.iter_mut().zip(result.edges) {
+                *global_value += value;
             }
             progress_bar.inc();
         }
         progress_bar.set(result_count);
 
-        global_c_list
-    }
-
-    fn print_thread_progress(index: u32, counter: u32, total_node_count: usize) {
-        let thread_info = format!("Thread {}: {} / {}", index, counter, total_node_count);
-        info!("{}", thread_info);
-    }
-
-    fn restore_or_create_state(&self, index: u32) -> (SparseList<f64>, u32) {
-        let file = self.get_state_path_for_index(index);
-        if !file.exists() {
-            (SparseList::new(0.0), 0)
-        } else {
-            let state: BrandesThreadState = Self::read_from_file(&file).unwrap_or(
-                Self::get_fresh_thread_state(index)
-            );
-
-            (state.local_c_list, state.counter)
-        }
+        (global_result, sample_size)
     }
 
-    fn persist_current_state(&self, index: u32, counter: u32, local_c_list: SparseList<f64>) -> SparseList<f64> {
-        let file = self.get_state_path_for_index(index);
-
-        let state = BrandesThreadState {
-            index, counter, local_c_list
-        };
-
-        write_binary_to_file(&file, &state);
-
-        state.local_c_list
-    }
-
-    fn get_state_path_for_index(&self, index: u32) -> PathBuf {
-        let directory = self.intermediate_folder_path.join("betweenness");
-        fs::create_dir_all(&directory).expect("Could not create intermediary directory for betweenness");
-
-        directory.join(format!("thread_{}.bin", index))
-    }
-
-    fn read_from_file(path: &PathBuf) -> Option<BrandesThreadState> {
-        let f = File::open(path);
-        if f.is_ok() {
-            let file = f.unwrap();
-
-            let data = bincode::deserialize_from(file);
-
-            if data.is_ok() {
-                Some(data.unwrap())
-            } else {
-                info!(
-                    "File at {} does not contain or contains invalid thread state data",
-                    path.to_str().unwrap()
-                );
-                None
+    /**
+     * Returns the source nodes of the Brandes passes and the sample size. In the sampling modes,
+     * the weights of the sources are set so that the weighted sum of their dependencies is an
+     * unbiased estimate of the exact betweenness.
+     */
+    fn select_sources(&mut self) -> (Vec<NodeIndex>, u64) {
+        // nodes without outgoing edges do not contribute to the betweenness of any other node
+        let candidates: Vec<NodeIndex> = self.graph.indices()
+            .filter(|&s| self.graph.out_degree(s) > 0)
+            .collect();
+        let sample_size = self.params.sample_size as usize;
+        let mut random = Random::new(self.params.seed);
+
+        match self.params.mode {
+            BetweennessMode::Uniform if sample_size > 0 && sample_size < candidates.len() => {
+                info!("Sampling {} of {} source nodes uniformly", sample_size, candidates.len());
+                let sources = random.sample_nodes(&candidates, sample_size);
+                let weight = candidates.len() as f64 / sample_size as f64;
+
+                self.source_weights = vec![0.0; self.graph.node_count()];
+                for &s in &sources {
+                    self.source_weights[s as usize] = weight;
+                }
+                (sources, sample_size as u64)
             }
-        } else {
-            None
-        }
-    }
+            BetweennessMode::DegreeProportional if sample_size > 0 && !candidates.is_empty() => {
+                info!("Sampling {} source nodes proportionally to their out degree", sample_size);
+                let cumulative_degrees: Vec<usize> = candidates.iter()
+                    .scan(0, |sum, &s| {
+                        *sum += self.graph.out_degree(s);
+                        Some(*sum)
+                    })
+                    .collect();
+                let degree_sum = *cumulative_degrees.last().unwrap();
+
+                // drawn with replacement, a source drawn c times with probability p has the weight c / (k * p)
+                self.source_weights = vec![0.0; self.graph.node_count()];
+                for _ in 0..sample_size {
+                    let target = random.below(degree_sum);
+                    let position = cumulative_degrees.partition_point(|&sum| sum <= target);
+                    let s = candidates[position];
+                    let probability = self.graph.out_degree(s) as f64 / degree_sum as f64;
+                    self.source_weights[s as usize] += 1.0 / (sample_size as f64 * probability);
+                }
 
-    fn get_fresh_thread_state(index: u32) -> BrandesThreadState {
-        BrandesThreadState {
-            index,
-            counter: 0,
-            local_c_list: SparseList::new(0.0),
+                let sources = candidates.into_iter()
+                    .filter(|&s| self.source_weights[s as usize] > 0.0)
+                    .collect();
+                (sources, sample_size as u64)
+            }
+            _ => {
+                let source_count = candidates.len() as u64;
+                (candidates, source_count)
+            }
         }
     }
 
+    /**
+     * Runs one iteration of the Brandes algorithm from the source node s and adds the dependencies
+     * to the state. Instead of storing predecessor lists, the predecessors of a node w are found
+     * among its reverse neighbors v as those with d[v] = d[w] - 1.
+     */
     pub fn calculate_delta_for_node(
         &self,
-        neighbors: &SparseList<HashSet<i64>>,
-        c_list: &mut SparseList<f64>,
-        s: i64,
+        memory: &mut BrandesMemory,
+        state: &mut BetweennessState,
+        s: NodeIndex,
     ) {
-        let memory = BrandesMemory::new();
-        let mut s_stack = memory.s_stack;
-        let mut p_list = memory.p_list;
-        let mut sigma = memory.sigma;
-        let mut d = memory.d;
-        let mut q = memory.q;
-        let mut delta = memory.delta;
-
-        sigma.set(s, 1);
-        d.set(s, 0);
-        q.push_back(s);
+        memory.search_from(&self.graph, s);
+        self.accumulate_dependency(s, state, memory);
 
-        self.calculate_dependencies(&neighbors, &mut s_stack, &mut p_list, &mut sigma, d, q);
-
-        self.accumulate_dependency(s, c_list, &mut s_stack, &mut p_list, &mut sigma, &mut delta)
+        memory.reset();
     }
 
-    fn calculate_dependencies(
-        &self,
-        neighbors: &SparseList<HashSet<i64>>,
-        s_stack: &mut Vec<i64>,
-        p_list: &mut SparseList<Vec<i64>>,
-        sigma: &mut SparseList<u64>,
-        mut d: SparseList<i64>,
-        mut q: VecDeque<i64>
-    ) {
-        while !q.is_empty() {
-            let v = q.pop_front().unwrap();
-            s_stack.push(v);
-            for &w in &neighbors[v] {
-                if d[w] < 0 {
-                    q.push_back(w);
-                    d.set(w, d[v] + 1);
-                }
-                if d[w] == d[v] + 1 {
-                    let sigma_w = sigma[w];
-                    let sigma_v = sigma[v];
-                    sigma.set(w, sigma_w + sigma_v);
-                    p_list[w].push(v);
+    /**
+     * The share sigma[v] / sigma[w] * (1 + delta[w]) of the dependency of w that passes on to a
+     * predecessor v is also the dependency of the edge (v, w).
+     */
+    fn accumulate_dependency(&self, s: NodeIndex, state: &mut BetweennessState, memory: &mut BrandesMemory) {
+        let weight = if self.source_weights.is_empty() { 1.0 } else { self.source_weights[s as usize] };
+        let sigma = &memory.sigma;
+        let d = &memory.d;
+        let delta = &mut memory.delta;
+
+        for &w in memory.s_stack.iter().rev() {
+            let w_index = w as usize;
+            let sigma_w = sigma[w_index] as f64;
+            let coefficient = (1.0 + delta[w_index]) / sigma_w;
+            for &v in self.graph.reverse_neighbors(w) {
+                let v_index = v as usize;
+                if d[v_index] >= 0 && d[v_index] == d[w_index] - 1 {
+                    let dependency = sigma[v_index] as f64 * coefficient;
+                    delta[v_index] += dependency;
+                    if !state.edges.is_empty() {
+                        state.edges[self.graph.edge_position(v, w).unwrap()] += weight * dependency;
+                    }
                 }
             }
-        }
-    }
-
-    fn accumulate_dependency(
-        &self,
-        s: i64,
-        c_list: &mut SparseList<f64>,
-        s_stack: &mut Vec<i64>,
-        p_list: &mut SparseList<Vec<i64>>,
-        sigma: &mut SparseList<u64>,
-        delta: &mut SparseList<f64>
-    ) {
-        while !s_stack.is_empty() {
-            let w = s_stack.pop().unwrap();
-            for &v in &p_list[w] {
-                let delta_v = delta[v];
-                let delta_w = delta[w];
-                let sigma_v = sigma[v] as f64;
-                let sigma_w = sigma[w] as f64;
-                delta.set(v, delta_v + (sigma_v / sigma_w) * (1.0 + delta_w));
 
-                if w != s {
-                    c_list[w] += delta[w];
-                }
+            if w != s {
+                state.nodes[w_index] += weight * delta[w_index];
             }
         }
     }
@@ -283,3 +289,24 @@ impl BrandesCalculator {
         self.graph
     }
 }
+
+impl SourceVisitor for BrandesCalculator {
+    type State = BetweennessState;
+    type Memory = BrandesMemory;
+
+    fn fresh_state(&self) -> BetweennessState {
+        let edge_count = if self.edge_writer.is_some() { self.graph.edge_count() } else { 0 };
+        BetweennessState {
+            nodes: vec![0.0; self.graph.node_count()],
+            edges: vec![0.0; edge_count],
+        }
+    }
+
+    fn new_memory(&self) -> BrandesMemory {
+        BrandesMemory::new(self.graph.node_count())
+    }
+
+    fn visit(&self, memory: &mut BrandesMemory, state: &mut BetweennessState, s: NodeIndex) {
+        self.calculate_delta_for_node(memory, state, s);
+    }
+}
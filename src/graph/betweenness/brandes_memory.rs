@@ -1,31 +1,74 @@
 use std::collections::VecDeque;
-use crate::graph::common::sparse_list::SparseList;
+
+use crate::graph::common::graph::{Graph, NodeIndex};
 
 /**
  * Utility struct for the required data structures for the Brandes algorithm.
+ * The structures are dense (indexed by node index) and reused for all source nodes of a thread,
+ * so after every source node only the entries of the visited nodes have to be reset.
  */
 pub struct BrandesMemory {
-    pub s_stack: Vec<i64>,
-    pub p_list: SparseList<Vec<i64>>,
-    pub sigma: SparseList<u64>,
-    pub d: SparseList<i64>,
-    pub q: VecDeque<i64>,
-    pub delta: SparseList<f64>,
+    pub s_stack: Vec<NodeIndex>,
+    pub sigma: Vec<u64>,
+    pub d: Vec<i64>,
+    pub q: VecDeque<NodeIndex>,
+    pub delta: Vec<f64>,
 
     // this field is not used, though its purpose is to prevent public instantiation of the struct
     _init_gate: bool,
 }
 
 impl BrandesMemory {
-    pub fn new() -> BrandesMemory {
+    pub fn new(node_count: usize) -> BrandesMemory {
         BrandesMemory {
             s_stack: Vec::new(),
-            p_list: SparseList::new(Vec::<i64>::new()),
-            sigma: SparseList::new(0),
-            d: SparseList::new(-1),
+            sigma: vec![0; node_count],
+            d: vec![-1; node_count],
             q: VecDeque::new(),
-            delta: SparseList::new(0.0),
+            delta: vec![0.0; node_count],
             _init_gate: true,
         }
     }
-}
\ No newline at end of file
+
+    /**
+     * Runs the BFS of the Brandes algorithm from the source node s: afterwards, d holds the distance
+     * and sigma the number of shortest paths from s of every reached node, and the stack holds the
+     * reached nodes in the order of their distance.
+     */
+    pub fn search_from(&mut self, graph: &Graph, s: NodeIndex) {
+        let sigma = &mut self.sigma;
+        let d = &mut self.d;
+        sigma[s as usize] = 1;
+        d[s as usize] = 0;
+        self.q.push_back(s);
+
+        while let Some(v) = self.q.pop_front() {
+            self.s_stack.push(v);
+            let v = v as usize;
+            for &w in graph.neighbors(v as NodeIndex) {
+                let w_index = w as usize;
+                if d[w_index] < 0 {
+                    self.q.push_back(w);
+                    d[w_index] = d[v] + 1;
+                }
+                if d[w_index] == d[v] + 1 {
+                    sigma[w_index] += sigma[v];
+                }
+            }
+        }
+    }
+
+    /**
+     * Resets the entries of all nodes visited from the last source node. Expects the stack to
+     * still contain the visited nodes.
+     */
+    pub fn reset(&mut self) {
+        for w in self.s_stack.drain(..) {
+            let w = w as usize;
+            self.sigma[w] = 0;
+            self.d[w] = -1;
+            self.delta[w] = 0.0;
+        }
+        self.q.clear();
+    }
+}
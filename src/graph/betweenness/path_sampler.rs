@@ -0,0 +1,338 @@
+/*!
+ * Adaptive betweenness approximation by shortest path sampling (KADABRA, Borassi and Natale 2016).
+ * Every sample draws a pair of nodes (u, v) uniformly at random and one of the shortest paths from
+ * u to v uniformly at random. The fraction of samples whose path runs through a node estimates its
+ * normalized betweenness b(x) = BC(x) / (n * (n - 1)).
+ *
+ * The samples are drawn in rounds. After every round, the stopping condition of KADABRA checks
+ * whether all estimates are within epsilon of the normalized betweenness with probability at least
+ * 1 - delta. Independent of this, the sampling stops after omega samples, the sample size of
+ * Riondato and Kornaropoulos (2016) that guarantees the same bound based on the vertex diameter.
+ */
+
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::BetweennessParameters;
+use crate::common::random::Random;
+use crate::graph::common::graph::{Graph, NodeIndex};
+use crate::preprocess::file_util::write_binary_to_file;
+
+// Universal constant of the sample size bound, as estimated by Löffler and Phillips (2009)
+const OMEGA_CONSTANT: f64 = 0.5;
+
+#[derive(Serialize, Deserialize)]
+struct SamplerState {
+    node_count: usize,
+    graph_hash: u64,
+    seed: u64,
+    epsilon: f64,
+    delta: f64,
+    sample_count: u64,
+    path_counts: Vec<u32>,
+}
+
+/**
+ * Reusable BFS buffers of a thread. Only the visited entries are reset after every sample.
+ */
+struct SamplerMemory {
+    distance: Vec<i64>,
+    sigma: Vec<f64>,
+    visited: Vec<NodeIndex>,
+    queue: VecDeque<NodeIndex>,
+}
+
+pub struct PathSampler<'a> {
+    graph: &'a Graph,
+    params: &'a BetweennessParameters,
+    state_path: PathBuf,
+}
+
+impl<'a> PathSampler<'a> {
+    pub fn new(
+        graph: &'a Graph,
+        params: &'a BetweennessParameters,
+        intermediate_folder_path: &Path,
+    ) -> PathSampler<'a> {
+        let directory_name = if graph.is_undirected() {
+            "betweenness_adaptive_undirected"
+        } else {
+            "betweenness_adaptive"
+        };
+
+        PathSampler {
+            graph,
+            params,
+            state_path: intermediate_folder_path.join(directory_name).join("state.bin"),
+        }
+    }
+
+    /**
+     * Samples shortest paths until the requested accuracy is reached. Returns the estimated
+     * betweenness of every node (scaled to the value range of the exact betweenness, i.e. counting
+     * ordered pairs of nodes) and the number of samples.
+     *
+     * If the intermediate save feature is enabled, the sample counts are stored after every round
+     * and an interrupted run continues from there.
+     */
+    pub fn estimate(&self) -> (Vec<f64>, u64) {
+        let node_count = self.graph.node_count();
+        if node_count < 2 {
+            return (vec![0.0; node_count], 0);
+        }
+
+        let omega = self.omega();
+        info!(
+            "Sampling shortest paths until epsilon = {} and delta = {} are reached, at most {} samples",
+            self.params.epsilon, self.params.delta, omega
+        );
+
+        let graph_hash = self.graph.content_hash();
+        let mut state = self.restore_state(graph_hash).unwrap_or_else(|| SamplerState {
+            node_count,
+            graph_hash,
+            seed: self.params.seed,
+            epsilon: self.params.epsilon,
+            delta: self.params.delta,
+            sample_count: 0,
+            path_counts: vec![0; node_count],
+        });
+
+        let thread_count = self.params.max_thread_count.max(1) as u64;
+        let round_size = self.params.result_batch_size.max(1) as u64 * thread_count;
+        let mut memories: Vec<SamplerMemory> = (0..thread_count).map(|_| self.new_memory()).collect();
+
+        while state.sample_count < omega && !self.has_converged(&state, omega) {
+            let round_start = state.sample_count;
+            let round_end = (round_start + round_size).min(omega);
+            let samples_per_thread = (round_end - round_start).div_ceil(thread_count);
+
+            let paths: Vec<Vec<NodeIndex>> = memories.par_iter_mut()
+                .enumerate()
+                .map(|(thread_id, memory)| {
+                    let first = round_start + thread_id as u64 * samples_per_thread;
+                    let last = (first + samples_per_thread).min(round_end);
+                    let mut inner_nodes = Vec::new();
+                    for sample in first..last {
+                        let mut random = Random::for_stream(self.params.seed, sample);
+                        self.sample_path(memory, &mut random, &mut inner_nodes);
+                    }
+                    inner_nodes
+                })
+                .collect();
+
+            for node in paths.into_iter().flatten() {
+                state.path_counts[node as usize] += 1;
+            }
+            state.sample_count = round_end;
+
+            info!("Drew {} of at most {} samples", state.sample_count, omega);
+            if self.params.save_intermediate_results_periodically {
+                self.persist_state(&state);
+            }
+        }
+
+        info!("Finished sampling after {} samples", state.sample_count);
+
+        let pair_count = node_count as f64 * (node_count - 1) as f64;
+        let values = state.path_counts.iter()
+            .map(|&count| count as f64 / state.sample_count as f64 * pair_count)
+            .collect();
+
+        (values, state.sample_count)
+    }
+
+    /**
+     * The number of samples after which the estimates are within epsilon with probability
+     * 1 - delta / 2 (Riondato and Kornaropoulos), based on an upper bound of the vertex diameter.
+     */
+    fn omega(&self) -> u64 {
+        let vertex_diameter = self.vertex_diameter_bound();
+        let epsilon = self.params.epsilon;
+        let diameter_term = if vertex_diameter > 2 { ((vertex_diameter - 2) as f64).log2().floor() } else { 0.0 };
+        let omega = OMEGA_CONSTANT / (epsilon * epsilon) * (diameter_term + 1.0 + (2.0 / self.params.delta).ln());
+
+        omega.ceil() as u64
+    }
+
+    /**
+     * Upper bound of the number of nodes on a shortest path. In an undirected graph, two nodes of a
+     * component are at most twice the eccentricity of any node of the component apart. A directed
+     * shortest path can only be bounded by the size of its weakly connected component.
+     */
+    fn vertex_diameter_bound(&self) -> usize {
+        let mut component = vec![false; self.graph.node_count()];
+        let mut distance = vec![0usize; self.graph.node_count()];
+        let mut queue = VecDeque::new();
+        let mut bound = 0;
+
+        for start in self.graph.indices() {
+            if component[start as usize] {
+                continue;
+            }
+
+            component[start as usize] = true;
+            distance[start as usize] = 0;
+            queue.push_back(start);
+            let mut size = 0;
+            let mut eccentricity = 0;
+            while let Some(v) = queue.pop_front() {
+                size += 1;
+                eccentricity = eccentricity.max(distance[v as usize]);
+                let neighbors = self.graph.neighbors(v).iter().chain(self.graph.reverse_neighbors(v));
+                for &w in neighbors {
+                    if !component[w as usize] {
+                        component[w as usize] = true;
+                        distance[w as usize] = distance[v as usize] + 1;
+                        queue.push_back(w);
+                    }
+                }
+            }
+
+            let component_bound = if self.graph.is_undirected() { 2 * eccentricity + 1 } else { size };
+            bound = bound.max(component_bound);
+        }
+
+        bound
+    }
+
+    /**
+     * Stopping condition of KADABRA. The failure probability delta / 2 that remains besides omega
+     * is split evenly among the lower and upper deviations of all nodes.
+     */
+    fn has_converged(&self, state: &SamplerState, omega: u64) -> bool {
+        if state.sample_count == 0 {
+            return false;
+        }
+
+        let tau = state.sample_count as f64;
+        let omega = omega as f64;
+        let log_inverse_delta = (4.0 * state.node_count as f64 / self.params.delta).ln();
+        let epsilon = self.params.epsilon;
+
+        let mut checked_counts: Vec<u32> = state.path_counts.clone();
+        checked_counts.sort_unstable();
+        checked_counts.dedup();
+
+        checked_counts.into_iter().all(|count| {
+            let estimate = count as f64 / tau;
+            let lower_term = 1.0 / 3.0 - omega / tau;
+            let upper_term = 1.0 / 3.0 + omega / tau;
+            let root = |term: f64| (term * term + 2.0 * estimate * omega / log_inverse_delta).sqrt();
+
+            let lower_deviation = log_inverse_delta / tau * (lower_term + root(lower_term));
+            let upper_deviation = log_inverse_delta / tau * (upper_term + root(upper_term));
+            lower_deviation <= epsilon && upper_deviation <= epsilon
+        })
+    }
+
+    fn new_memory(&self) -> SamplerMemory {
+        SamplerMemory {
+            distance: vec![-1; self.graph.node_count()],
+            sigma: vec![0.0; self.graph.node_count()],
+            visited: Vec::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    /**
+     * Draws a pair of distinct nodes and, if the target is reachable, one of the shortest paths
+     * between them with probability proportional to its count. The inner nodes of the path are
+     * appended to inner_nodes.
+     */
+    fn sample_path(&self, memory: &mut SamplerMemory, random: &mut Random, inner_nodes: &mut Vec<NodeIndex>) {
+        let node_count = self.graph.node_count();
+        let u = random.below(node_count) as NodeIndex;
+        let mut v = random.below(node_count - 1) as NodeIndex;
+        if v >= u {
+            v += 1;
+        }
+
+        memory.distance[u as usize] = 0;
+        memory.sigma[u as usize] = 1.0;
+        memory.visited.push(u);
+        memory.queue.push_back(u);
+
+        // the BFS stops as soon as all shortest paths to v are counted
+        while let Some(x) = memory.queue.pop_front() {
+            let x_distance = memory.distance[x as usize];
+            let v_distance = memory.distance[v as usize];
+            if v_distance >= 0 && x_distance >= v_distance {
+                break;
+            }
+            for &w in self.graph.neighbors(x) {
+                let w_index = w as usize;
+                if memory.distance[w_index] < 0 {
+                    memory.distance[w_index] = x_distance + 1;
+                    memory.visited.push(w);
+                    memory.queue.push_back(w);
+                }
+                if memory.distance[w_index] == x_distance + 1 {
+                    memory.sigma[w_index] += memory.sigma[x as usize];
+                }
+            }
+        }
+
+        if memory.distance[v as usize] > 0 {
+            let mut current = v;
+            while memory.distance[current as usize] > 1 {
+                let predecessor_distance = memory.distance[current as usize] - 1;
+                let mut threshold = random.next_f64() * memory.sigma[current as usize];
+                let predecessors = self.graph.reverse_neighbors(current).iter()
+                    .filter(|&&p| memory.distance[p as usize] == predecessor_distance);
+
+                let mut chosen = None;
+                for &p in predecessors {
+                    chosen = Some(p);
+                    threshold -= memory.sigma[p as usize];
+                    if threshold < 0.0 {
+                        break;
+                    }
+                }
+
+                current = chosen.unwrap();
+                inner_nodes.push(current);
+            }
+        }
+
+        for x in memory.visited.drain(..) {
+            memory.distance[x as usize] = -1;
+            memory.sigma[x as usize] = 0.0;
+        }
+        memory.queue.clear();
+    }
+
+    fn restore_state(&self, graph_hash: u64) -> Option<SamplerState> {
+        let file = File::open(&self.state_path).ok()?;
+        let state: SamplerState = bincode::deserialize_from(file).ok()?;
+
+        let matches = state.node_count == self.graph.node_count()
+            && state.graph_hash == graph_hash
+            && state.seed == self.params.seed
+            && state.epsilon == self.params.epsilon
+            && state.delta == self.params.delta;
+        if !matches {
+            info!(
+                "Sampling state at {} belongs to a different graph or parameters, starting from scratch",
+                self.state_path.to_str().unwrap()
+            );
+            return None;
+        }
+
+        info!("Continuing from {} samples stored at {}", state.sample_count, self.state_path.to_str().unwrap());
+        Some(state)
+    }
+
+    fn persist_state(&self, state: &SamplerState) {
+        let directory = self.state_path.parent().unwrap();
+        fs::create_dir_all(directory).expect("Could not create intermediary directory for betweenness");
+
+        write_binary_to_file(&self.state_path, state);
+    }
+}
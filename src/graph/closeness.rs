@@ -0,0 +1 @@
+pub mod closeness_calculator;
@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use csv::Writer;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::ClosenessParameters;
+use crate::common::random::Random;
+use crate::graph::betweenness::brandes_memory::BrandesMemory;
+use crate::graph::common::graph::{Graph, NodeIndex};
+use crate::graph::common::source_runner::{SourceRunner, SourceVisitor};
+
+/**
+ * Distances of all nodes reached from the sources visited so far. The values are collected per
+ * target node, i.e. a BFS from s adds d(s, t) to every node t reachable from s.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct ClosenessState {
+    distance_sum: Vec<u64>,
+    reached_by: Vec<u32>,
+    harmonic: Vec<f64>,
+}
+
+pub struct ClosenessCalculator {
+    graph: Graph,
+    intermediate_folder_path: PathBuf,
+    params: ClosenessParameters,
+    writer: Writer<File>,
+}
+
+impl ClosenessCalculator {
+    pub fn new(
+        graph: Graph,
+        intermediate_folder_path: &Path,
+        params: ClosenessParameters,
+        writer: Writer<File>
+    ) -> ClosenessCalculator {
+        ClosenessCalculator {
+            graph,
+            intermediate_folder_path: intermediate_folder_path.to_path_buf(),
+            params,
+            writer,
+        }
+    }
+
+    /**
+     * Calculates the closeness and harmonic centrality of every node from the distances of all
+     * other nodes to it (for directed graphs, the incoming distances, as networkx does):
+     *
+     * - closeness(t) = (r - 1) / (n - 1) * (r - 1) / D, with r - 1 the number of other nodes that
+     *   reach t and D the sum of their distances to t (Wasserman-Faust scaling, which keeps the
+     *   values of nodes in small components low)
+     * - harmonic(t) = sum of 1 / d(s, t) over all other nodes s
+     *
+     * With sampling, only the distances from the sampled sources are known and both sums are
+     * scaled up to all source nodes.
+     */
+    pub fn calculate_and_persist(&mut self) {
+        let sources = self.sources();
+        let all_source_count = self.candidate_sources().len();
+
+        let directory_name = if self.graph.is_undirected() { "closeness_undirected" } else { "closeness" };
+        let runner = SourceRunner::new(
+            &self.intermediate_folder_path.join(directory_name),
+            self.params.save_intermediate_results_periodically,
+            self.params.result_batch_size,
+            self.params.max_thread_count,
+        );
+        let mut partial_results = runner.run(&*self, self.graph.content_hash(), &sources).into_iter();
+        let mut result = partial_results.next().unwrap_or_else(|| self.fresh_state());
+        for partial_result in partial_results {
+            Self::merge(&mut result, partial_result);
+        }
+
+        let scale = if sources.is_empty() { 0.0 } else { all_source_count as f64 / sources.len() as f64 };
+        let other_node_count = self.graph.node_count().saturating_sub(1) as f64;
+
+        self.writer.serialize(("node_id", "closeness", "harmonic")).unwrap();
+        for t in self.graph.indices() {
+            let t_index = t as usize;
+            let reached_by = result.reached_by[t_index] as f64 * scale;
+            let distance_sum = result.distance_sum[t_index] as f64 * scale;
+            let closeness = if distance_sum > 0.0 {
+                (reached_by / other_node_count) * (reached_by / distance_sum)
+            } else {
+                0.0
+            };
+            let harmonic = result.harmonic[t_index] * scale;
+
+            if closeness != 0.0 || harmonic != 0.0 {
+                self.writer.serialize((self.graph.node_id(t), closeness, harmonic)).unwrap();
+            }
+        }
+    }
+
+    /**
+     * Nodes without outgoing edges do not reach any other node, so they are never used as sources.
+     */
+    fn candidate_sources(&self) -> Vec<NodeIndex> {
+        self.graph.indices()
+            .filter(|&s| self.graph.out_degree(s) > 0)
+            .collect()
+    }
+
+    fn sources(&self) -> Vec<NodeIndex> {
+        let candidates = self.candidate_sources();
+        let sample_size = self.params.sample_size as usize;
+        if sample_size == 0 || sample_size >= candidates.len() {
+            info!("Calculating CLOSENESS from all {} source nodes", candidates.len());
+            return candidates;
+        }
+
+        info!(
+            "Calculating CLOSENESS from {} of {} source nodes sampled with seed {}",
+            sample_size, candidates.len(), self.params.seed
+        );
+        Random::new(self.params.seed).sample_nodes(&candidates, sample_size)
+    }
+
+    fn merge(result: &mut ClosenessState, other: ClosenessState) {
+        for (value, other_value) in result.distance_sum.iter_mut().zip(other.distance_sum) {
+            *value += other_value;
+        }
+        for (value, other_value) in result.reached_by.iter_mut().zip(other.reached_by) {
+            *value += other_value;
+        }
+        for (value, other_value) in result.harmonic.iter_mut().zip(other.harmonic) {
+            *value += other_value;
+        }
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
+
+impl SourceVisitor for ClosenessCalculator {
+    type State = ClosenessState;
+    type Memory = BrandesMemory;
+
+    fn fresh_state(&self) -> ClosenessState {
+        let node_count = self.graph.node_count();
+        ClosenessState {
+            distance_sum: vec![0; node_count],
+            reached_by: vec![0; node_count],
+            harmonic: vec![0.0; node_count],
+        }
+    }
+
+    fn new_memory(&self) -> BrandesMemory {
+        BrandesMemory::new(self.graph.node_count())
+    }
+
+    fn visit(&self, memory: &mut BrandesMemory, state: &mut ClosenessState, s: NodeIndex) {
+        memory.search_from(&self.graph, s);
+
+        // the stack starts with s itself
+        for &w in &memory.s_stack[1..] {
+            let w_index = w as usize;
+            let distance = memory.d[w_index];
+            state.distance_sum[w_index] += distance as u64;
+            state.reached_by[w_index] += 1;
+            state.harmonic[w_index] += 1.0 / distance as f64;
+        }
+
+        memory.reset();
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use csv::Writer;
+use log::info;
+use rayon::prelude::*;
+
+use crate::ClusteringParameters;
+use crate::graph::common::graph::{Graph, NodeIndex};
+
+/**
+ * The graph without directions, with every edge pointing from the node of lower degree to the node
+ * of higher degree (ties broken by index). Every triangle is then found exactly once, from its
+ * lowest node, and no node has more than sqrt(2m) outgoing edges, even if it is a hub in the graph.
+ * The multiplicity of an edge is 2 if it exists in both directions in the original graph.
+ */
+struct OrientedGraph {
+    offsets: Vec<usize>,
+    targets: Vec<NodeIndex>,
+    multiplicities: Vec<u8>,
+    degrees: Vec<usize>,
+    reciprocal_degrees: Vec<usize>,
+}
+
+impl OrientedGraph {
+    fn new(graph: &Graph) -> OrientedGraph {
+        let mut degrees = vec![0; graph.node_count()];
+        let mut reciprocal_degrees = vec![0; graph.node_count()];
+        for v in graph.indices() {
+            graph.for_each_undirected_neighbor(v, |_, multiplicity| {
+                degrees[v as usize] += 1;
+                if multiplicity == 2 {
+                    reciprocal_degrees[v as usize] += 1;
+                }
+            });
+        }
+
+        let is_higher = |v: NodeIndex, w: NodeIndex| (degrees[w as usize], w) > (degrees[v as usize], v);
+        let mut offsets = Vec::with_capacity(graph.node_count() + 1);
+        let mut targets = Vec::new();
+        let mut multiplicities = Vec::new();
+        offsets.push(0);
+        for v in graph.indices() {
+            graph.for_each_undirected_neighbor(v, |w, multiplicity| {
+                if is_higher(v, w) {
+                    targets.push(w);
+                    multiplicities.push(multiplicity);
+                }
+            });
+            offsets.push(targets.len());
+        }
+
+        OrientedGraph {
+            offsets,
+            targets,
+            multiplicities,
+            degrees,
+            reciprocal_degrees,
+        }
+    }
+
+    fn successors(&self, v: NodeIndex) -> (&[NodeIndex], &[u8]) {
+        let range = self.offsets[v as usize]..self.offsets[v as usize + 1];
+        (&self.targets[range.clone()], &self.multiplicities[range])
+    }
+}
+
+pub struct TriangleCounter {
+    graph: Graph,
+    params: ClusteringParameters,
+    writer: Writer<File>,
+}
+
+impl TriangleCounter {
+    pub fn new(graph: Graph, params: ClusteringParameters, writer: Writer<File>) -> TriangleCounter {
+        TriangleCounter {
+            graph,
+            params,
+            writer,
+        }
+    }
+
+    /**
+     * Counts the triangles of every node and writes the local clustering coefficients to the
+     * clustering CSV file, and the graph-wide values to the summary CSV file. Direction is ignored
+     * for the triangles and the clustering coefficient. On directed graphs, the directed triangles
+     * and the directed clustering coefficient of Fagiolo (2007) are added, which count every
+     * triangle once for each way its edges can be directed along it.
+     */
+    #[allow(clippy::expect_fun_call)]
+    pub fn calculate_and_persist(&mut self, summary_path: &Path) {
+        info!("Counting TRIANGLES");
+        let oriented_graph = OrientedGraph::new(&self.graph);
+
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.params.max_thread_count as usize)
+            .build()
+            .expect("Could not create thread pool for counting triangles");
+        let (triangles, directed_triangles) = thread_pool.install(|| self.count_triangles(&oriented_graph));
+
+        let directed = !self.graph.is_undirected();
+        if directed {
+            self.writer.serialize(
+                ("node_id", "triangles", "clustering", "directed_triangles", "clustering_directed")
+            ).unwrap();
+        } else {
+            self.writer.serialize(("node_id", "triangles", "clustering")).unwrap();
+        }
+
+        let mut clustering_sum = 0.0;
+        let mut directed_clustering_sum = 0.0;
+        for v in self.graph.indices() {
+            let v_index = v as usize;
+            let degree = oriented_graph.degrees[v_index] as f64;
+            let clustering = if degree > 1.0 {
+                2.0 * triangles[v_index] as f64 / (degree * (degree - 1.0))
+            } else {
+                0.0
+            };
+
+            // with the total degree d (in + out) and b edges in both directions, a node can be part
+            // of at most d (d - 1) - 2b directed triangles (Fagiolo)
+            let directed_degree = (oriented_graph.degrees[v_index] + oriented_graph.reciprocal_degrees[v_index]) as f64;
+            let possible_directed_triangles = directed_degree * (directed_degree - 1.0)
+                - 2.0 * oriented_graph.reciprocal_degrees[v_index] as f64;
+            let directed_clustering = if possible_directed_triangles > 0.0 {
+                directed_triangles[v_index] as f64 / possible_directed_triangles
+            } else {
+                0.0
+            };
+
+            clustering_sum += clustering;
+            directed_clustering_sum += directed_clustering;
+            if triangles[v_index] == 0 {
+                continue;
+            }
+
+            let node_id = self.graph.node_id(v);
+            if directed {
+                self.writer.serialize(
+                    (node_id, triangles[v_index], clustering, directed_triangles[v_index], directed_clustering)
+                ).unwrap();
+            } else {
+                self.writer.serialize((node_id, triangles[v_index], clustering)).unwrap();
+            }
+        }
+        self.writer.flush().unwrap();
+
+        let total_triangles = triangles.iter().sum::<u64>() / 3;
+        let connected_triples: f64 = oriented_graph.degrees.iter()
+            .map(|&degree| (degree * degree.saturating_sub(1)) as f64 / 2.0)
+            .sum();
+        let transitivity = if connected_triples > 0.0 { 3.0 * total_triangles as f64 / connected_triples } else { 0.0 };
+        let node_count = self.graph.node_count().max(1) as f64;
+        info!("Found {} triangles, transitivity is {}", total_triangles, transitivity);
+
+        let mut summary_writer = csv::Writer::from_path(summary_path)
+            .expect(&format!("Could not create clustering summary at {}", summary_path.to_str().unwrap()));
+        if directed {
+            summary_writer.serialize(
+                ("triangles", "transitivity", "average_clustering", "directed_triangles", "average_clustering_directed")
+            ).unwrap();
+            summary_writer.serialize((
+                total_triangles,
+                transitivity,
+                clustering_sum / node_count,
+                directed_triangles.iter().sum::<u64>() / 3,
+                directed_clustering_sum / node_count,
+            )).unwrap();
+        } else {
+            summary_writer.serialize(("triangles", "transitivity", "average_clustering")).unwrap();
+            summary_writer.serialize((total_triangles, transitivity, clustering_sum / node_count)).unwrap();
+        }
+        summary_writer.flush().unwrap();
+    }
+
+    /**
+     * Returns the number of triangles and of directed triangles of every node. Every triangle is
+     * found once from its lowest node by intersecting the successors of two of its nodes. A directed
+     * triangle count adds the product of the edge multiplicities of the triangle.
+     */
+    fn count_triangles(&self, oriented_graph: &OrientedGraph) -> (Vec<u64>, Vec<u64>) {
+        let node_count = self.graph.node_count();
+        let triangles: Vec<AtomicU64> = (0..node_count).map(|_| AtomicU64::new(0)).collect();
+        let directed_triangles: Vec<AtomicU64> = (0..node_count).map(|_| AtomicU64::new(0)).collect();
+
+        self.graph.indices().into_par_iter().for_each(|u| {
+            let (u_successors, u_multiplicities) = oriented_graph.successors(u);
+            for (&v, &uv_multiplicity) in u_successors.iter().zip(u_multiplicities) {
+                let (v_successors, v_multiplicities) = oriented_graph.successors(v);
+
+                let (mut i, mut j) = (0, 0);
+                while i < u_successors.len() && j < v_successors.len() {
+                    if u_successors[i] < v_successors[j] {
+                        i += 1;
+                    } else if u_successors[i] > v_successors[j] {
+                        j += 1;
+                    } else {
+                        let w = u_successors[i];
+                        let product = (uv_multiplicity * u_multiplicities[i] * v_multiplicities[j]) as u64;
+                        for node in [u, v, w] {
+                            triangles[node as usize].fetch_add(1, Ordering::Relaxed);
+                            directed_triangles[node as usize].fetch_add(product, Ordering::Relaxed);
+                        }
+                        i += 1;
+                        j += 1;
+                    }
+                }
+            }
+        });
+
+        (
+            triangles.into_iter().map(AtomicU64::into_inner).collect(),
+            directed_triangles.into_iter().map(AtomicU64::into_inner).collect(),
+        )
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
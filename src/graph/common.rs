@@ -1,2 +1,4 @@
-pub mod sparse_list;
+pub mod edge_filter;
 pub mod graph;
+pub mod graph_snapshot;
+pub mod source_runner;
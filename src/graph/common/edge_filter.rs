@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use crate::graph::common::graph::Graph;
+
+/**
+ * Copies the rows of the given edges CSV file (e.g. edges_deduplicated.csv) whose nodes are both
+ * selected, i.e. the edges of the subgraph induced by the selected nodes.
+ */
+#[allow(clippy::expect_fun_call)]
+pub fn write_edges_within(graph: &Graph, edges_path: &Path, path: &Path, selected: &[bool]) {
+    let mut reader = csv::Reader::from_path(edges_path).unwrap();
+    let mut writer = csv::Writer::from_path(path)
+        .expect(&format!("Could not create file for storing edges at {}", path.to_str().unwrap()));
+    writer.write_record(reader.headers().unwrap()).unwrap();
+
+    let is_selected = |field: &str| {
+        field.parse().ok()
+            .and_then(|node_id| graph.index_of(node_id))
+            .is_some_and(|index| selected[index as usize])
+    };
+    for record in reader.records() {
+        let record = record.unwrap();
+        if is_selected(&record[0]) && is_selected(&record[1]) {
+            writer.write_record(&record).unwrap();
+        }
+    }
+    writer.flush().unwrap();
+}
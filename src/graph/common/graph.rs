@@ -1,105 +1,288 @@
-/**
+/*!
  * Structure for parsing and holding the graph structure in memory.
+ *
+ * The graph is stored in compressed sparse row (CSR) form: The node IDs (unknown and known) are
+ * remapped to dense indices 0..n in ascending ID order, and the neighbors of every node are stored
+ * as one contiguous, sorted slice of node indices. The reverse graph is kept in the same form.
  */
 
-use std::collections::HashSet;
+use std::ops::Range;
 use std::path::PathBuf;
-use lazy_init::Lazy;
 
-use crate::common::structs::parse_data::{CsvEdge, MaxNodeIds, NodeBoundaries};
-use crate::graph::common::sparse_list::SparseList;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::common::hash::Fnv1a;
+use crate::common::structs::parse_data::CsvEdge;
 use crate::OutputPaths;
 
+pub type NodeIndex = u32;
+
+// Number of collected edges after which duplicates are removed for the first time while parsing
+const INITIAL_COMPACTION_THRESHOLD: usize = 1 << 20;
+
+#[derive(Serialize, Deserialize)]
 pub struct Graph {
-    edges: SparseList<HashSet<i64>>,
-    reverse: Lazy<SparseList<HashSet<i64>>>,
-    boundaries: NodeBoundaries,
+    node_ids: Vec<i64>,
+    offsets: Vec<usize>,
+    targets: Vec<NodeIndex>,
+    reverse_offsets: Vec<usize>,
+    reverse_targets: Vec<NodeIndex>,
+    undirected: bool,
 }
 
 impl Graph {
-    pub fn new(output_paths: &OutputPaths, from_deduplicated: bool) -> Graph {
-        let max_node_id_path = &output_paths.max_node_ids;
-
-        let max_node_ids: MaxNodeIds = csv::Reader::from_path(max_node_id_path).unwrap()
-            .deserialize()
-            .next()
-            .unwrap()
-            .unwrap();
-
+    /**
+     * Reads the graph from the edge list. If undirected is set, every edge is added in both
+     * directions, i.e. the graph is symmetrised.
+     */
+    pub fn new(output_paths: &OutputPaths, from_deduplicated: bool, undirected: bool) -> Graph {
         let edges_path = if from_deduplicated {
             &output_paths.edges_deduplicated
         } else {
             &output_paths.edges
         };
 
-        let mut graph = Graph::init(max_node_ids);
-        graph.parse(edges_path);
-        graph
+        let edges = Self::parse(edges_path, undirected);
+        Graph::from_edges(edges, undirected)
     }
 
-    fn init(max_node_ids: MaxNodeIds) -> Graph {
-        let boundaries = NodeBoundaries::new(max_node_ids);
+    /**
+     * Builds the graph from a list of (from, to) node ID pairs. Duplicate edges are removed.
+     * The edges are expected to already contain both directions if the graph is undirected.
+     */
+    pub fn from_edges(mut edges: Vec<(i64, i64)>, undirected: bool) -> Graph {
+        edges.sort_unstable();
+        edges.dedup();
+
+        let mut node_ids: Vec<i64> = edges.iter()
+            .flat_map(|&(from, to)| [from, to])
+            .collect();
+        node_ids.sort_unstable();
+        node_ids.dedup();
+
+        assert!(
+            node_ids.len() <= NodeIndex::MAX as usize,
+            "The graph has more nodes than can be indexed"
+        );
+
+        let index_of = |node_id: i64| node_ids.binary_search(&node_id).unwrap() as NodeIndex;
+        let mut indexed_edges: Vec<(NodeIndex, NodeIndex)> = edges.into_iter()
+            .map(|(from, to)| (index_of(from), index_of(to)))
+            .collect();
+
+        // The node IDs are sorted, so the indexed edges are still sorted by (from, to)
+        let (offsets, targets) = Self::compress(node_ids.len(), &indexed_edges);
+
+        indexed_edges.iter_mut().for_each(|edge| *edge = (edge.1, edge.0));
+        indexed_edges.sort_unstable();
+        let (reverse_offsets, reverse_targets) = Self::compress(node_ids.len(), &indexed_edges);
+
         Graph {
-            edges: SparseList::new(
-                HashSet::<i64>::new()
-            ),
-            reverse: Lazy::new(),
-            boundaries,
+            node_ids,
+            offsets,
+            targets,
+            reverse_offsets,
+            reverse_targets,
+            undirected,
         }
     }
 
-    pub fn ensure_reversed_edges_exist(&mut self) {
-        let edges = &self.edges;
-        self.reverse.get_or_create(|| Self::calculate_reverse_graph(edges));
+    /**
+     * Returns the subgraph induced by the nodes whose entry in keep is set.
+     */
+    pub fn subgraph(&self, keep: &[bool]) -> Graph {
+        let edges = self.indices()
+            .filter(|&from| keep[from as usize])
+            .flat_map(|from| {
+                self.neighbors(from).iter()
+                    .filter(|&&to| keep[to as usize])
+                    .map(move |&to| (self.node_id(from), self.node_id(to)))
+            })
+            .collect();
+
+        Graph::from_edges(edges, self.undirected)
+    }
+
+    /**
+     * Builds the offsets and target lists from edges sorted by (from, to).
+     */
+    fn compress(node_count: usize, sorted_edges: &[(NodeIndex, NodeIndex)]) -> (Vec<usize>, Vec<NodeIndex>) {
+        let mut offsets = vec![0; node_count + 1];
+        for &(from, _) in sorted_edges {
+            offsets[from as usize + 1] += 1;
+        }
+        for i in 0..node_count {
+            offsets[i + 1] += offsets[i];
+        }
+
+        let targets = sorted_edges.iter().map(|&(_, to)| to).collect();
+
+        (offsets, targets)
     }
 
     /**
-     * Returns a reversed version of the edge list.
-     * WARNING: You need to call `ensure_reversed_edges_exist` first (once),
-     * otherwise you get a panic!().
+     * Reads the edges from the given edge list. Since edges.csv usually contains lots of
+     * duplicates, they are removed periodically to keep the memory usage in check.
      */
-    pub fn edges_reversed(&self) -> &SparseList<HashSet<i64>> {
-        // We could also just make this function mutable and call get_or_create
-        // but then we'd lock the result in a mutable borrow, which creates issues
+    fn parse(edges_path: &PathBuf, undirected: bool) -> Vec<(i64, i64)> {
+        let mut edges: Vec<(i64, i64)> = Vec::new();
+        let mut compaction_threshold = INITIAL_COMPACTION_THRESHOLD;
 
-        if let Some(reversed) = self.reverse.get() {
-            reversed
-        } else {
-            panic!("ERROR: You tried accessing the reverse edges before initializing them. \
-            Please make sure to call `ensure_reversed_edges_exist` before using this function.");
+        let mut edges_reader = csv::Reader::from_path(edges_path).unwrap();
+        edges_reader.deserialize()
+            .filter_map(|edge: Result<CsvEdge, _>| edge.ok())
+            .for_each(|edge| {
+                edges.push((edge.from, edge.to));
+                if undirected {
+                    edges.push((edge.to, edge.from));
+                }
+
+                if edges.len() >= compaction_threshold {
+                    edges.sort_unstable();
+                    edges.dedup();
+                    compaction_threshold = (2 * edges.len()).max(INITIAL_COMPACTION_THRESHOLD);
+                }
+            });
+
+        info!("Read {} edges from {}", edges.len(), edges_path.to_str().unwrap());
+        edges
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /**
+     * Calculates the 64-bit FNV-1a hash of the node IDs and edges, so that intermediate results
+     * (e.g. checkpoints) of a different graph with the same node count are recognized.
+     */
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(&[self.undirected as u8]);
+        for node_id in &self.node_ids {
+            hasher.write(&node_id.to_le_bytes());
+        }
+        for offset in &self.offsets {
+            hasher.write(&(*offset as u64).to_le_bytes());
+        }
+        for target in &self.targets {
+            hasher.write(&target.to_le_bytes());
         }
+
+        hasher.finish()
     }
 
-    fn calculate_reverse_graph(edges: &SparseList<HashSet<i64>>) -> SparseList<HashSet<i64>> {
-        let mut reversed: SparseList<HashSet<i64>> = SparseList::new(
-            HashSet::<i64>::new(),
-        );
+    /**
+     * Returns the node ID (as used in the CSV files) of the node at the given index.
+     */
+    pub fn node_id(&self, index: NodeIndex) -> i64 {
+        self.node_ids[index as usize]
+    }
+
+    pub fn index_of(&self, node_id: i64) -> Option<NodeIndex> {
+        self.node_ids.binary_search(&node_id).ok().map(|index| index as NodeIndex)
+    }
 
-        for s in edges.keys() {
-            for &u in &edges[s] {
-                reversed[u].insert(s);
+    /**
+     * Returns the (sorted) indices of the nodes the given node has an edge to.
+     */
+    pub fn neighbors(&self, index: NodeIndex) -> &[NodeIndex] {
+        &self.targets[self.edge_range(index)]
+    }
+
+    /**
+     * Returns the (sorted) indices of the nodes that have an edge to the given node.
+     */
+    pub fn reverse_neighbors(&self, index: NodeIndex) -> &[NodeIndex] {
+        let index = index as usize;
+        &self.reverse_targets[self.reverse_offsets[index]..self.reverse_offsets[index + 1]]
+    }
+
+    /**
+     * Returns the positions of the outgoing edges of the given node. Every edge has a unique
+     * position in 0..edge_count(), which can be used to store values per edge.
+     */
+    pub fn edge_range(&self, index: NodeIndex) -> Range<usize> {
+        let index = index as usize;
+        self.offsets[index]..self.offsets[index + 1]
+    }
+
+    /**
+     * Returns the position of the edge (from, to), if it exists.
+     */
+    pub fn edge_position(&self, from: NodeIndex, to: NodeIndex) -> Option<usize> {
+        let range = self.edge_range(from);
+        self.targets[range.clone()].binary_search(&to)
+            .ok()
+            .map(|position| range.start + position)
+    }
+
+    /**
+     * Calls the function for every neighbor of the given node regardless of the direction (in
+     * ascending order, without the node itself) together with the number of directions the edge
+     * exists in.
+     */
+    pub fn for_each_undirected_neighbor<F>(&self, index: NodeIndex, mut function: F)
+        where F: FnMut(NodeIndex, u8) {
+        let successors = self.neighbors(index);
+        let predecessors = self.reverse_neighbors(index);
+        let (mut i, mut j) = (0, 0);
+
+        while i < successors.len() || j < predecessors.len() {
+            let successor = successors.get(i).copied().unwrap_or(NodeIndex::MAX);
+            let predecessor = predecessors.get(j).copied().unwrap_or(NodeIndex::MAX);
+            let (w, multiplicity) = if successor == predecessor {
+                i += 1;
+                j += 1;
+                (successor, 2)
+            } else if successor < predecessor {
+                i += 1;
+                (successor, 1)
+            } else {
+                j += 1;
+                (predecessor, 1)
+            };
+
+            if w != index {
+                function(w, multiplicity);
             }
         }
+    }
 
-        reversed
+    pub fn out_degree(&self, index: NodeIndex) -> usize {
+        self.offsets[index as usize + 1] - self.offsets[index as usize]
     }
 
-    fn parse(&mut self, edges_path: &PathBuf) {
-        let mut edges_reader = csv::Reader::from_path(edges_path).unwrap();
-        edges_reader.deserialize()
-            .filter(|edge| edge.is_ok())
-            .for_each(|edge: Result<CsvEdge, _>| {
-                let data = edge.unwrap();
-                let data_from = data.from;
-                self.edges[data_from].insert(data.to);
-            });
+    pub fn in_degree(&self, index: NodeIndex) -> usize {
+        self.reverse_offsets[index as usize + 1] - self.reverse_offsets[index as usize]
     }
 
-    pub fn edges(&self) -> &SparseList<HashSet<i64>> {
-        &self.edges
+    pub fn indices(&self) -> Range<NodeIndex> {
+        0..(self.node_count() as NodeIndex)
     }
 
-    pub fn boundaries(&self) -> &NodeBoundaries {
-        &self.boundaries
+    pub fn is_undirected(&self) -> bool {
+        self.undirected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_depends_on_the_edges_only() {
+        let graph = Graph::from_edges(vec![(1, 2), (2, 3), (3, 1)], false);
+        let reordered = Graph::from_edges(vec![(3, 1), (1, 2), (2, 3), (1, 2)], false);
+        let reversed = Graph::from_edges(vec![(2, 1), (3, 2), (1, 3)], false);
+
+        assert_eq!(graph.content_hash(), reordered.content_hash());
+        assert_eq!(graph.node_count(), reversed.node_count());
+        assert_ne!(graph.content_hash(), reversed.content_hash());
     }
 }
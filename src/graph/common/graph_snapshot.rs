@@ -0,0 +1,106 @@
+/*!
+ * Binary snapshot of a loaded graph, so that repeated runs on the same edge list do not have to
+ * parse the CSV file again. The snapshot records the hash of the edge list it was built from and
+ * is only used as long as the edge list is unchanged.
+ */
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::common::hash::Fnv1a;
+use crate::graph::common::graph::Graph;
+use crate::preprocess::file_util::write_binary_to_file;
+
+// Has to be increased whenever the layout of the graph changes, so that old snapshots are ignored
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+    source_hash: u64,
+    undirected: bool,
+}
+
+pub struct GraphSnapshot {
+    snapshot_path: PathBuf,
+}
+
+impl GraphSnapshot {
+    pub fn new(intermediate_path: &Path, undirected: bool) -> GraphSnapshot {
+        let filename = if undirected { "graph_undirected.bin" } else { "graph.bin" };
+
+        GraphSnapshot {
+            snapshot_path: intermediate_path.join(filename),
+        }
+    }
+
+    /**
+     * Loads the graph from the snapshot if it was built from the current version of the edge list,
+     * otherwise builds the graph with the given function and stores a new snapshot.
+     */
+    pub fn load_or_build<F>(&self, edges_path: &Path, undirected: bool, build: F) -> Graph
+        where F: FnOnce() -> Graph {
+        info!("Hashing {} to check for a graph snapshot...", edges_path.to_str().unwrap());
+        let source_hash = hash_file(edges_path);
+
+        if let Some(graph) = self.load(source_hash, undirected) {
+            info!("Loaded graph from snapshot at {}", self.snapshot_path.to_str().unwrap());
+            return graph;
+        }
+
+        let graph = build();
+
+        info!("Storing graph snapshot at {}", self.snapshot_path.to_str().unwrap());
+        let header = SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            source_hash,
+            undirected,
+        };
+        write_binary_to_file(&self.snapshot_path, &(header, &graph));
+
+        graph
+    }
+
+    fn load(&self, source_hash: u64, undirected: bool) -> Option<Graph> {
+        let file = File::open(&self.snapshot_path).ok()?;
+        let mut reader = BufReader::new(file);
+
+        let header: SnapshotHeader = bincode::deserialize_from(&mut reader).ok()?;
+        if header.version != SNAPSHOT_VERSION {
+            info!("Graph snapshot has version {}, expected {}. Rebuilding it.", header.version, SNAPSHOT_VERSION);
+            return None;
+        }
+        if header.source_hash != source_hash || header.undirected != undirected {
+            info!("Graph snapshot does not match the current edge list. Rebuilding it.");
+            return None;
+        }
+
+        bincode::deserialize_from(&mut reader).ok()
+    }
+}
+
+/**
+ * Calculates the 64-bit FNV-1a hash of the file contents.
+ */
+#[allow(clippy::expect_fun_call)]
+fn hash_file(path: &Path) -> u64 {
+    let mut reader = BufReader::new(File::open(path).expect(&format!(
+        "Could not open {} for hashing",
+        path.to_str().unwrap()
+    )));
+    let mut buffer = [0u8; 64 * 1024];
+    let mut hasher = Fnv1a::new();
+    loop {
+        let read = reader.read(&mut buffer).unwrap();
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
+
+    hasher.finish()
+}
@@ -0,0 +1,226 @@
+/*!
+ * Runs a computation from every source node (e.g. one BFS per node) in parallel. Every thread
+ * works through its own share of the source nodes, accumulates its results into a local state
+ * and periodically stores this state in a checkpoint directory, so that an interrupted run can
+ * be continued. The states of all threads are returned for merging by the caller.
+ */
+
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::common::hash::Fnv1a;
+use crate::graph::common::graph::NodeIndex;
+use crate::preprocess::file_util::write_binary_to_file;
+
+/**
+ * The per-source computation. The state is what a thread accumulates (and what is checkpointed),
+ * the memory holds the buffers that are reused between the source nodes of a thread.
+ */
+pub trait SourceVisitor: Sync {
+    type State: Serialize + DeserializeOwned + Send;
+    type Memory;
+
+    fn fresh_state(&self) -> Self::State;
+
+    fn new_memory(&self) -> Self::Memory;
+
+    fn visit(&self, memory: &mut Self::Memory, state: &mut Self::State, s: NodeIndex);
+}
+
+#[derive(Serialize, Deserialize)]
+struct ThreadState<S> {
+    index: u32,
+    counter: u32,
+    graph_hash: u64,
+    sources_hash: u64,
+    state: S,
+}
+
+pub struct SourceRunner {
+    checkpoint_directory: PathBuf,
+    save_periodically: bool,
+    batch_size: u32,
+    thread_count: u16,
+}
+
+impl SourceRunner {
+    pub fn new(
+        checkpoint_directory: &Path,
+        save_periodically: bool,
+        batch_size: u32,
+        thread_count: u16,
+    ) -> SourceRunner {
+        SourceRunner {
+            checkpoint_directory: checkpoint_directory.to_path_buf(),
+            save_periodically,
+            batch_size,
+            thread_count,
+        }
+    }
+
+    /**
+     * Visits all given source nodes and returns the state of every thread.
+     *
+     * IMPORTANT: If checkpoints are present, they are read in and the computation continues from
+     * there. Checkpoints of a different graph (-> see Graph::content_hash) or a different list of
+     * source nodes are ignored.
+     */
+    pub fn run<V: SourceVisitor>(&self, visitor: &V, graph_hash: u64, sources: &[NodeIndex]) -> Vec<V::State> {
+        if self.save_periodically {
+            info!("Saving of intermediate results to binary files is ENABLED.");
+        } else {
+            info!("Saving of intermediate results to binary files is DISABLED.");
+        }
+
+        info!("Processing {} nodes...", sources.len());
+        if sources.is_empty() {
+            return Vec::new();
+        }
+
+        let chunk_size = ((sources.len() as f64) / (self.thread_count as f64)).ceil() as usize;
+        let mut states: Vec<V::State> = Vec::new();
+        sources.chunks(chunk_size)
+            .enumerate()
+            .collect::<Vec<(usize, &[NodeIndex])>>()
+            .into_par_iter()
+            .map(|(thread_id, nodes_to_visit)| {
+                let thread_id = thread_id as u32;
+                let total_node_count = nodes_to_visit.len();
+                info!("Thread {}: {} nodes", thread_id, total_node_count);
+
+                let sources_hash = hash_sources(nodes_to_visit);
+                let mut thread_state = self.restore_state(thread_id, graph_hash, sources_hash)
+                    .unwrap_or_else(|| ThreadState {
+                        index: thread_id,
+                        counter: 0,
+                        graph_hash,
+                        sources_hash,
+                        state: visitor.fresh_state(),
+                    });
+                let mut memory = visitor.new_memory();
+
+                Self::print_thread_progress(thread_id, thread_state.counter, total_node_count);
+
+                for &s in &nodes_to_visit[(thread_state.counter as usize)..] {
+                    visitor.visit(&mut memory, &mut thread_state.state, s);
+                    thread_state.counter += 1;
+                    if thread_state.counter.is_multiple_of(self.batch_size) {
+                        Self::print_thread_progress(thread_id, thread_state.counter, total_node_count);
+                        if self.save_periodically {
+                            write_binary_to_file(&self.state_path(thread_id), &thread_state);
+                        }
+                    }
+                }
+
+                info!("Thread {}: finished", thread_id);
+                thread_state.state
+            })
+            .collect_into_vec(&mut states);
+
+        states
+    }
+
+    fn print_thread_progress(index: u32, counter: u32, total_node_count: usize) {
+        info!("Thread {}: {} / {}", index, counter, total_node_count);
+    }
+
+    fn restore_state<S: DeserializeOwned>(
+        &self,
+        index: u32,
+        graph_hash: u64,
+        sources_hash: u64,
+    ) -> Option<ThreadState<S>> {
+        let path = self.state_path(index);
+        let file = File::open(&path).ok()?;
+
+        let state: ThreadState<S> = match bincode::deserialize_from(file) {
+            Ok(state) => state,
+            Err(_) => {
+                info!("File at {} does not contain or contains invalid thread state data", path.to_str().unwrap());
+                return None;
+            }
+        };
+
+        // thread states of a different graph (e.g. from a previous dataset) cannot be continued
+        if state.graph_hash != graph_hash || state.sources_hash != sources_hash {
+            info!(
+                "Thread state at {} belongs to a different graph or source nodes, starting from scratch",
+                path.to_str().unwrap()
+            );
+            return None;
+        }
+
+        Some(state)
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn state_path(&self, index: u32) -> PathBuf {
+        fs::create_dir_all(&self.checkpoint_directory).expect(&format!(
+            "Could not create intermediary directory at {}",
+            self.checkpoint_directory.to_str().unwrap()
+        ));
+
+        self.checkpoint_directory.join(format!("thread_{}.bin", index))
+    }
+}
+
+/**
+ * Calculates the 64-bit FNV-1a hash of the source nodes of a thread.
+ */
+fn hash_sources(sources: &[NodeIndex]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    for source in sources {
+        hasher.write(&source.to_le_bytes());
+    }
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    /**
+     * Sums up the visited sources plus an offset, so that a continued run shows up in the result.
+     */
+    struct SumVisitor {
+        offset: u64,
+    }
+
+    impl SourceVisitor for SumVisitor {
+        type State = u64;
+        type Memory = ();
+
+        fn fresh_state(&self) -> u64 {
+            0
+        }
+
+        fn new_memory(&self) {}
+
+        fn visit(&self, _: &mut (), state: &mut u64, s: NodeIndex) {
+            *state += s as u64 + self.offset;
+        }
+    }
+
+    #[test]
+    fn checkpoints_of_a_different_graph_are_not_continued() {
+        let directory = env::temp_dir().join(format!("source_runner_{}", std::process::id()));
+        let runner = SourceRunner::new(&directory, true, 1, 1);
+        let sources: Vec<NodeIndex> = (0..4).collect();
+
+        assert_eq!(runner.run(&SumVisitor { offset: 0 }, 1, &sources), vec![6]);
+        // all sources are done, so the checkpoint is returned as it is
+        assert_eq!(runner.run(&SumVisitor { offset: 10 }, 1, &sources), vec![6]);
+        assert_eq!(runner.run(&SumVisitor { offset: 10 }, 2, &sources), vec![46]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,4 @@
+pub mod community_detector;
+pub mod partition_comparison;
+mod label_propagation;
+mod louvain;
@@ -0,0 +1,137 @@
+use log::{info, warn};
+
+use crate::{CommunityParameters, OutputPaths};
+use crate::common::random::Random;
+use crate::graph::common::graph::Graph;
+use crate::graph::communities::label_propagation::label_propagation;
+use crate::graph::communities::louvain::{louvain, modularity};
+
+pub const LOUVAIN_COLUMN: &str = "community_louvain";
+pub const LABEL_PROPAGATION_COLUMN: &str = "community_label_propagation";
+
+pub struct CommunityDetector {
+    graph: Graph,
+    params: CommunityParameters,
+}
+
+impl CommunityDetector {
+    pub fn new(graph: Graph, params: CommunityParameters) -> CommunityDetector {
+        CommunityDetector {
+            graph,
+            params,
+        }
+    }
+
+    /**
+     * Detects communities in the undirected projection of the graph with the enabled algorithms and
+     * writes
+     *     - the community of every node to communities.csv (one column per algorithm)
+     *     - the size of every community to community_sizes.csv
+     *     - the number of communities and their modularity to community_summary.csv
+     * Communities are numbered by decreasing size, so community 0 is the largest.
+     */
+    pub fn calculate_and_persist(&self, output_paths: &OutputPaths) {
+        let mut results: Vec<(&str, &str, Vec<u32>)> = Vec::new();
+        if self.params.louvain {
+            info!("Detecting communities with LOUVAIN (resolution {})", self.params.resolution);
+            let mut random = Random::for_stream(self.params.seed, 0);
+            let communities = louvain(&self.graph, self.params.resolution, &mut random);
+            results.push(("louvain", LOUVAIN_COLUMN, self.number_by_size(&communities)));
+        }
+        if self.params.label_propagation {
+            info!("Detecting communities with LABEL PROPAGATION");
+            let mut random = Random::for_stream(self.params.seed, 1);
+            let (labels, iterations) = label_propagation(&self.graph, self.params.max_iterations, &mut random);
+            if iterations == self.params.max_iterations {
+                warn!("Label propagation stopped after the maximum of {} rounds", iterations);
+            }
+            results.push(("label_propagation", LABEL_PROPAGATION_COLUMN, self.number_by_size(&labels)));
+        }
+
+        self.write_communities(output_paths, &results);
+        self.write_sizes_and_summary(output_paths, &results);
+    }
+
+    /**
+     * Renumbers the communities by decreasing size, communities of the same size by their first node.
+     */
+    fn number_by_size(&self, communities: &[u32]) -> Vec<u32> {
+        let community_count = communities.iter().map(|&c| c as usize + 1).max().unwrap_or(0);
+        let mut sizes = vec![0usize; community_count];
+        let mut first_nodes = vec![usize::MAX; community_count];
+        for (v, &c) in communities.iter().enumerate() {
+            sizes[c as usize] += 1;
+            first_nodes[c as usize] = first_nodes[c as usize].min(v);
+        }
+
+        let mut order: Vec<usize> = (0..community_count).filter(|&c| sizes[c] > 0).collect();
+        order.sort_unstable_by_key(|&c| (std::cmp::Reverse(sizes[c]), first_nodes[c]));
+        let mut numbers = vec![0u32; community_count];
+        for (number, c) in order.into_iter().enumerate() {
+            numbers[c] = number as u32;
+        }
+
+        communities.iter().map(|&c| numbers[c as usize]).collect()
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn write_communities(&self, output_paths: &OutputPaths, results: &[(&str, &str, Vec<u32>)]) {
+        let path = &output_paths.communities;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing communities at {}", path.to_str().unwrap()));
+
+        let mut header = vec!["node_id"];
+        header.extend(results.iter().map(|&(_, column, _)| column));
+        writer.write_record(&header).unwrap();
+
+        for v in self.graph.indices() {
+            let mut record = vec![self.graph.node_id(v).to_string()];
+            record.extend(results.iter().map(|(_, _, communities)| communities[v as usize].to_string()));
+            writer.write_record(&record).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    /**
+     * The modularity is computed with the configured resolution for all algorithms.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn write_sizes_and_summary(&self, output_paths: &OutputPaths, results: &[(&str, &str, Vec<u32>)]) {
+        let path = &output_paths.community_sizes;
+        let mut size_writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing community sizes at {}", path.to_str().unwrap()));
+        size_writer.serialize(("algorithm", "community", "size")).unwrap();
+
+        let path = &output_paths.community_summary;
+        let mut summary_writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing the community summary at {}", path.to_str().unwrap()));
+        summary_writer.serialize(("algorithm", "communities", "largest_community", "modularity", "resolution"))
+            .unwrap();
+
+        for (algorithm, _, communities) in results {
+            let community_count = communities.iter().map(|&c| c as usize + 1).max().unwrap_or(0);
+            let mut sizes = vec![0usize; community_count];
+            for &c in communities {
+                sizes[c as usize] += 1;
+            }
+            for (c, size) in sizes.iter().enumerate() {
+                size_writer.serialize((algorithm, c, size)).unwrap();
+            }
+
+            let modularity = modularity(&self.graph, communities, self.params.resolution);
+            let largest = sizes.first().copied().unwrap_or(0);
+            info!(
+                "{}: {} communities (largest with {} nodes), modularity {:.4}",
+                algorithm, community_count, largest, modularity
+            );
+            summary_writer.serialize((algorithm, community_count, largest, modularity, self.params.resolution))
+                .unwrap();
+        }
+        size_writer.flush().unwrap();
+        summary_writer.flush().unwrap();
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
@@ -0,0 +1,76 @@
+use crate::common::random::Random;
+use crate::graph::common::graph::Graph;
+
+/**
+ * Asynchronous label propagation (Raghavan et al. 2007) on the undirected projection of the graph,
+ * as asyn_lpa_communities in networkx: every node starts with its own label and, in random order,
+ * takes the most frequent label among its neighbors (a random one of them on ties), unless its
+ * label is already among the most frequent. Stops when no label changes, at the latest after
+ * max_iterations rounds. Returns the label of every node and the number of rounds.
+ */
+pub fn label_propagation(graph: &Graph, max_iterations: u32, random: &mut Random) -> (Vec<u32>, u32) {
+    let node_count = graph.node_count();
+    let mut labels: Vec<u32> = graph.indices().collect();
+    let mut order: Vec<u32> = graph.indices().collect();
+
+    // neighbor count of every label, with the labels touched so far
+    let mut counts = vec![0u32; node_count];
+    let mut touched: Vec<u32> = Vec::new();
+    let mut best_labels: Vec<u32> = Vec::new();
+
+    let mut iterations = 0;
+    while iterations < max_iterations {
+        iterations += 1;
+        random.shuffle(&mut order);
+
+        let mut changed = false;
+        for &v in &order {
+            graph.for_each_undirected_neighbor(v, |w, _| {
+                let label = labels[w as usize];
+                if counts[label as usize] == 0 {
+                    touched.push(label);
+                }
+                counts[label as usize] += 1;
+            });
+
+            let max_count = touched.iter().map(|&label| counts[label as usize]).max().unwrap_or(0);
+            best_labels.extend(touched.iter().copied().filter(|&label| counts[label as usize] == max_count));
+            // the order of the touched labels depends on the neighbor order only
+            best_labels.sort_unstable();
+
+            if !best_labels.is_empty() && !best_labels.contains(&labels[v as usize]) {
+                labels[v as usize] = best_labels[random.below(best_labels.len())];
+                changed = true;
+            }
+
+            for label in touched.drain(..) {
+                counts[label as usize] = 0;
+            }
+            best_labels.clear();
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    (labels, iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disconnected_cliques_get_one_label_each() {
+        // triangle 1-2-3 and the four clique 4-5-6-7
+        let edges = vec![(1, 2), (2, 3), (3, 1), (4, 5), (5, 6), (6, 4), (6, 7), (7, 4), (7, 5)];
+        let graph = Graph::from_edges(edges, false);
+        let (labels, iterations) = label_propagation(&graph, 100, &mut Random::new(3));
+
+        assert!(iterations < 100);
+        assert!(labels[..3].iter().all(|&label| label == labels[0]));
+        assert!(labels[3..].iter().all(|&label| label == labels[3]));
+        assert_ne!(labels[0], labels[3]);
+    }
+}
@@ -0,0 +1,288 @@
+/*!
+ * Louvain community detection (Blondel et al. 2008) with a resolution parameter. Nodes are moved
+ * to the neighboring community with the largest modularity gain until no move improves the
+ * modularity, then the communities are contracted to the nodes of the next level. This repeats
+ * until a level does not move any node.
+ */
+
+use std::collections::HashMap;
+
+use crate::common::random::Random;
+use crate::graph::common::graph::{Graph, NodeIndex};
+
+// smallest modularity gain that counts as an improvement, to stop on rounding noise
+const MIN_GAIN: f64 = 1e-12;
+
+/**
+ * Undirected graph with edge weights, i.e. a level of the Louvain algorithm. Every edge is stored in
+ * both adjacency lists, self loops (the edges within a contracted community) separately.
+ */
+struct WeightedGraph {
+    adjacency: Vec<Vec<(NodeIndex, f64)>>,
+    self_loops: Vec<f64>,
+    total_weight: f64,
+}
+
+impl WeightedGraph {
+    /**
+     * The undirected projection of the graph without self loops, every edge with weight 1.
+     */
+    fn from_graph(graph: &Graph) -> WeightedGraph {
+        let mut adjacency = vec![Vec::new(); graph.node_count()];
+        let mut total_weight = 0.0;
+        for v in graph.indices() {
+            graph.for_each_undirected_neighbor(v, |w, _| {
+                adjacency[v as usize].push((w, 1.0));
+                total_weight += 0.5;
+            });
+        }
+
+        WeightedGraph {
+            adjacency,
+            self_loops: vec![0.0; graph.node_count()],
+            total_weight,
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    /**
+     * The weighted degree of a node, where self loops count twice.
+     */
+    fn strength(&self, v: usize) -> f64 {
+        self.adjacency[v].iter().map(|&(_, weight)| weight).sum::<f64>() + 2.0 * self.self_loops[v]
+    }
+
+    /**
+     * Contracts every community to a single node. Edges within a community become its self loop,
+     * the edges between two communities are summed up.
+     */
+    fn aggregate(&self, communities: &[u32], community_count: usize) -> WeightedGraph {
+        let mut weights: Vec<HashMap<NodeIndex, f64>> = vec![HashMap::new(); community_count];
+        let mut self_loops = vec![0.0; community_count];
+        for v in 0..self.node_count() {
+            let c = communities[v] as usize;
+            self_loops[c] += self.self_loops[v];
+            for &(w, weight) in &self.adjacency[v] {
+                let d = communities[w as usize];
+                if d as usize == c {
+                    // every edge is seen from both of its ends
+                    self_loops[c] += weight / 2.0;
+                } else {
+                    *weights[c].entry(d).or_default() += weight;
+                }
+            }
+        }
+
+        let adjacency = weights.into_iter()
+            .map(|neighbors| {
+                let mut neighbors: Vec<(NodeIndex, f64)> = neighbors.into_iter().collect();
+                neighbors.sort_unstable_by_key(|&(w, _)| w);
+                neighbors
+            })
+            .collect();
+
+        WeightedGraph {
+            adjacency,
+            self_loops,
+            total_weight: self.total_weight,
+        }
+    }
+
+    /**
+     * Q = sum over all communities c of L_c / m - resolution * (d_c / 2m)^2, with L_c the weight of
+     * the edges within c, d_c the summed strength of its nodes and m the total edge weight.
+     */
+    fn modularity(&self, communities: &[u32], resolution: f64) -> f64 {
+        if self.total_weight == 0.0 {
+            return 0.0;
+        }
+
+        let community_count = communities.iter().map(|&c| c as usize + 1).max().unwrap_or(0);
+        let mut internal = vec![0.0; community_count];
+        let mut strengths = vec![0.0; community_count];
+        for v in 0..self.node_count() {
+            let c = communities[v] as usize;
+            strengths[c] += self.strength(v);
+            internal[c] += self.self_loops[v];
+            for &(w, weight) in &self.adjacency[v] {
+                if communities[w as usize] as usize == c {
+                    internal[c] += weight / 2.0;
+                }
+            }
+        }
+
+        let m = self.total_weight;
+        internal.iter().zip(&strengths)
+            .map(|(&internal, &strength)| internal / m - resolution * (strength / (2.0 * m)).powi(2))
+            .sum()
+    }
+
+    /**
+     * The local moving phase: visits the nodes in random order and moves every node to the
+     * community of a neighbor (or back to its own) with the largest gain of
+     * k_i,c - resolution * d_c * k_i / 2m, where k_i,c is the edge weight between the node and
+     * community c. Repeats until no node moves. Returns the communities renumbered from 0 and
+     * whether any node moved.
+     */
+    fn move_nodes(&self, resolution: f64, random: &mut Random) -> (Vec<u32>, bool) {
+        let node_count = self.node_count();
+        let strengths: Vec<f64> = (0..node_count).map(|v| self.strength(v)).collect();
+        let mut communities: Vec<u32> = (0..node_count as u32).collect();
+        let mut community_strengths = strengths.clone();
+        let scale = resolution / (2.0 * self.total_weight);
+
+        let mut order: Vec<usize> = (0..node_count).collect();
+        random.shuffle(&mut order);
+
+        // edge weight to every neighboring community, with the communities touched so far
+        let mut neighbor_weights = vec![0.0; node_count];
+        let mut neighbor_communities: Vec<u32> = Vec::new();
+
+        let mut moved_any = false;
+        loop {
+            let mut moved = false;
+            for &v in &order {
+                let own = communities[v];
+                for &(w, weight) in &self.adjacency[v] {
+                    let c = communities[w as usize];
+                    if neighbor_weights[c as usize] == 0.0 {
+                        neighbor_communities.push(c);
+                    }
+                    neighbor_weights[c as usize] += weight;
+                }
+
+                community_strengths[own as usize] -= strengths[v];
+                let gain = |c: u32| {
+                    neighbor_weights[c as usize] - scale * community_strengths[c as usize] * strengths[v]
+                };
+                let (mut best, mut best_gain) = (own, gain(own));
+                for &c in &neighbor_communities {
+                    let c_gain = gain(c);
+                    if c_gain > best_gain + MIN_GAIN {
+                        best = c;
+                        best_gain = c_gain;
+                    }
+                }
+                community_strengths[best as usize] += strengths[v];
+
+                if best != own {
+                    communities[v] = best;
+                    moved = true;
+                    moved_any = true;
+                }
+                for c in neighbor_communities.drain(..) {
+                    neighbor_weights[c as usize] = 0.0;
+                }
+            }
+
+            if !moved {
+                break;
+            }
+        }
+
+        (renumber(&communities), moved_any)
+    }
+}
+
+/**
+ * Numbers the communities from 0 in the order of their first node.
+ */
+fn renumber(communities: &[u32]) -> Vec<u32> {
+    let mut numbers = HashMap::new();
+    communities.iter()
+        .map(|&c| {
+            let next = numbers.len() as u32;
+            *numbers.entry(c).or_insert(next)
+        })
+        .collect()
+}
+
+/**
+ * Detects communities in the undirected projection of the graph. Returns the community of every
+ * node, numbered from 0.
+ */
+pub fn louvain(graph: &Graph, resolution: f64, random: &mut Random) -> Vec<u32> {
+    let mut level = WeightedGraph::from_graph(graph);
+    let mut communities: Vec<u32> = (0..graph.node_count() as u32).collect();
+    if level.total_weight == 0.0 {
+        return communities;
+    }
+
+    loop {
+        let (level_communities, moved) = level.move_nodes(resolution, random);
+        if !moved {
+            return communities;
+        }
+
+        for community in communities.iter_mut() {
+            *community = level_communities[*community as usize];
+        }
+        let community_count = level_communities.iter().map(|&c| c as usize + 1).max().unwrap_or(0);
+        level = level.aggregate(&level_communities, community_count);
+    }
+}
+
+/**
+ * The modularity of the given communities in the undirected projection of the graph.
+ */
+pub fn modularity(graph: &Graph, communities: &[u32], resolution: f64) -> f64 {
+    WeightedGraph::from_graph(graph).modularity(communities, resolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Two triangles 1-2-3 and 4-5-6, joined by the edge 3-4.
+     */
+    fn two_triangles() -> Graph {
+        Graph::from_edges(vec![(1, 2), (2, 3), (3, 1), (3, 4), (4, 5), (5, 6), (6, 4)], false)
+    }
+
+    #[test]
+    fn modularity_matches_networkx() {
+        let graph = two_triangles();
+        let communities = [0, 0, 0, 1, 1, 1];
+
+        assert!((modularity(&graph, &communities, 1.0) - 5.0 / 14.0).abs() < 1e-12);
+        assert!((modularity(&graph, &communities, 0.5) - 17.0 / 28.0).abs() < 1e-12);
+        assert!(modularity(&graph, &[0; 6], 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn modularity_counts_both_directions_of_an_edge_once() {
+        let directed = two_triangles();
+        let both_directions = Graph::from_edges(
+            vec![(1, 2), (2, 1), (2, 3), (3, 2), (3, 1), (1, 3), (3, 4), (4, 5), (5, 6), (6, 4)],
+            false
+        );
+        let communities = [0, 0, 0, 1, 1, 1];
+
+        let difference = modularity(&directed, &communities, 1.0) - modularity(&both_directions, &communities, 1.0);
+        assert!(difference.abs() < 1e-12);
+    }
+
+    #[test]
+    fn louvain_separates_the_triangles() {
+        let graph = two_triangles();
+        for seed in 0..10 {
+            let communities = louvain(&graph, 1.0, &mut Random::new(seed));
+            assert_eq!(communities, vec![0, 0, 0, 1, 1, 1], "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn louvain_keeps_isolated_edges_apart() {
+        let graph = Graph::from_edges(vec![(1, 2), (3, 4), (5, 6)], false);
+        let communities = louvain(&graph, 1.0, &mut Random::new(1));
+
+        assert_eq!(communities[0], communities[1]);
+        assert_eq!(communities[2], communities[3]);
+        assert_ne!(communities[0], communities[2]);
+        assert_ne!(communities[2], communities[4]);
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/**
+ * The normalized mutual information of two partitions of the same items, given as the pairs of
+ * the groups of every item: I(X; Y) / ((H(X) + H(Y)) / 2), i.e. with the arithmetic mean of the
+ * entropies (as in scikit-learn). 1 if the partitions are identical up to the group names, 0 if
+ * they are independent. If both partitions consist of a single group, they are identical.
+ * Without any items, the NMI is undefined (NaN).
+ */
+pub fn normalized_mutual_information<A, B>(pairs: &[(A, B)]) -> f64
+    where A: Eq + Hash + Copy, B: Eq + Hash + Copy {
+    if pairs.is_empty() {
+        return f64::NAN;
+    }
+
+    let total = pairs.len() as f64;
+    let mut first_sizes: HashMap<A, f64> = HashMap::new();
+    let mut second_sizes: HashMap<B, f64> = HashMap::new();
+    let mut joint_sizes: HashMap<(A, B), f64> = HashMap::new();
+    for &(a, b) in pairs {
+        *first_sizes.entry(a).or_default() += 1.0;
+        *second_sizes.entry(b).or_default() += 1.0;
+        *joint_sizes.entry((a, b)).or_default() += 1.0;
+    }
+
+    let entropy = |sizes: &mut dyn Iterator<Item = f64>| -> f64 {
+        sizes.map(|size| -size / total * (size / total).ln()).sum()
+    };
+    let first_entropy = entropy(&mut first_sizes.values().copied());
+    let second_entropy = entropy(&mut second_sizes.values().copied());
+    if first_entropy == 0.0 && second_entropy == 0.0 {
+        return 1.0;
+    }
+
+    let mutual_information: f64 = joint_sizes.iter()
+        .map(|((a, b), &size)| {
+            size / total * (size * total / (first_sizes[a] * second_sizes[b])).ln()
+        })
+        .sum();
+
+    // rounding can push the ratio slightly out of [0, 1]
+    (mutual_information / ((first_entropy + second_entropy) / 2.0)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_partitions_up_to_renaming() {
+        let pairs = [(0, 'b'), (0, 'b'), (1, 'a'), (1, 'a'), (2, 'c')];
+        assert!((normalized_mutual_information(&pairs) - 1.0).abs() < 1e-12);
+        assert_eq!(normalized_mutual_information(&[(0, 0), (0, 0)]), 1.0);
+    }
+
+    #[test]
+    fn undefined_without_items() {
+        assert!(normalized_mutual_information::<u32, i64>(&[]).is_nan());
+    }
+
+    #[test]
+    fn independent_partitions() {
+        let pairs = [(0, 0), (0, 1), (1, 0), (1, 1)];
+        assert!(normalized_mutual_information(&pairs).abs() < 1e-12);
+    }
+
+    #[test]
+    fn matches_scikit_learn() {
+        let pairs = [(0, 0), (0, 0), (0, 1), (1, 1), (1, 2), (1, 2)];
+        assert!((normalized_mutual_information(&pairs) - 0.515_803_742_979_388_8).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod component_counter;
+mod connected_components;
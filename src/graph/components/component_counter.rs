@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+
+use csv::Writer;
+use log::info;
+
+use crate::{ComponentKind, ComponentParameters, OutputPaths};
+use crate::graph::common::edge_filter::write_edges_within;
+use crate::graph::common::graph::Graph;
+use crate::graph::components::connected_components::{Components, strong_components, weak_components};
+
+pub struct ComponentCounter {
+    graph: Graph,
+    params: ComponentParameters,
+    writer: Writer<File>,
+}
+
+impl ComponentCounter {
+    pub fn new(graph: Graph, params: ComponentParameters, writer: Writer<File>) -> ComponentCounter {
+        ComponentCounter {
+            graph,
+            params,
+            writer,
+        }
+    }
+
+    /**
+     * Finds the connected components and writes
+     *     - the component of every node to the components CSV file
+     *     - the number of components of every size to component_sizes.csv
+     *     - the edges within the giant component to giant_component_edges.csv
+     * On undirected graphs, weak and strong components are the same, so only one of each is written.
+     *
+     * Returns for every node whether it is part of the giant component.
+     */
+    pub fn calculate_and_persist(&mut self, output_paths: &OutputPaths) -> Vec<bool> {
+        info!("Finding WEAKLY CONNECTED COMPONENTS");
+        let weak = weak_components(&self.graph);
+        Self::log_components("weakly", &weak, self.graph.node_count());
+
+        let strong = if self.graph.is_undirected() {
+            None
+        } else {
+            info!("Finding STRONGLY CONNECTED COMPONENTS");
+            let strong = strong_components(&self.graph);
+            Self::log_components("strongly", &strong, self.graph.node_count());
+            Some(strong)
+        };
+
+        self.write_node_components(&weak, strong.as_ref());
+        Self::write_component_sizes(output_paths, &weak, strong.as_ref());
+
+        let giant_component = match (&self.params.giant_component, &strong) {
+            (ComponentKind::Strong, Some(strong)) => strong.giant_component(),
+            _ => weak.giant_component(),
+        };
+        self.write_giant_component_edges(output_paths, &giant_component);
+
+        giant_component
+    }
+
+    fn log_components(kind: &str, components: &Components, node_count: usize) {
+        let giant_size = components.sizes.first().copied().unwrap_or(0);
+        info!(
+            "Found {} {} connected components, the giant component holds {} of {} nodes ({:.2}%)",
+            components.count(), kind, giant_size, node_count,
+            100.0 * giant_size as f64 / node_count.max(1) as f64
+        );
+    }
+
+    fn write_node_components(&mut self, weak: &Components, strong: Option<&Components>) {
+        match strong {
+            Some(strong) => {
+                self.writer.serialize(("node_id", "weak_component", "strong_component")).unwrap();
+                for v in self.graph.indices() {
+                    let v_index = v as usize;
+                    let node_id = self.graph.node_id(v);
+                    self.writer.serialize((node_id, weak.labels[v_index], strong.labels[v_index])).unwrap();
+                }
+            }
+            None => {
+                self.writer.serialize(("node_id", "component")).unwrap();
+                for v in self.graph.indices() {
+                    self.writer.serialize((self.graph.node_id(v), weak.labels[v as usize])).unwrap();
+                }
+            }
+        }
+        self.writer.flush().unwrap();
+    }
+
+    /**
+     * Writes the number of components per component size, in ascending order of the size.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn write_component_sizes(output_paths: &OutputPaths, weak: &Components, strong: Option<&Components>) {
+        let path = &output_paths.component_sizes;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing component sizes at {}", path.to_str().unwrap()));
+
+        let count_sizes = |components: &Components| {
+            let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+            for &size in &components.sizes {
+                *counts.entry(size).or_default() += 1;
+            }
+            counts
+        };
+
+        let weak_counts = count_sizes(weak);
+        match strong.map(count_sizes) {
+            Some(strong_counts) => {
+                writer.serialize(("size", "weak_components", "strong_components")).unwrap();
+                let mut sizes: Vec<usize> = weak_counts.keys().chain(strong_counts.keys()).copied().collect();
+                sizes.sort_unstable();
+                sizes.dedup();
+                for size in sizes {
+                    let weak_count = weak_counts.get(&size).copied().unwrap_or(0);
+                    let strong_count = strong_counts.get(&size).copied().unwrap_or(0);
+                    writer.serialize((size, weak_count, strong_count)).unwrap();
+                }
+            }
+            None => {
+                writer.serialize(("size", "components")).unwrap();
+                for (size, count) in weak_counts {
+                    writer.serialize((size, count)).unwrap();
+                }
+            }
+        }
+        writer.flush().unwrap();
+    }
+
+    fn write_giant_component_edges(&self, output_paths: &OutputPaths, giant_component: &[bool]) {
+        let path = &output_paths.giant_component_edges;
+        info!("Writing the edges of the giant component to {}", path.to_str().unwrap());
+        write_edges_within(&self.graph, &output_paths.edges_deduplicated, path, giant_component);
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+
+use crate::graph::common::graph::{Graph, NodeIndex};
+
+const UNVISITED: u32 = u32::MAX;
+
+/**
+ * The component of every node. The components are numbered by descending size (ties broken by
+ * their smallest node index), so component 0 is always the giant component.
+ */
+pub struct Components {
+    pub labels: Vec<u32>,
+    pub sizes: Vec<usize>,
+}
+
+impl Components {
+    /**
+     * Renumbers arbitrary component labels by descending component size.
+     */
+    fn from_labels(labels: Vec<u32>, component_count: usize) -> Components {
+        let mut sizes = vec![0; component_count];
+        for &label in &labels {
+            sizes[label as usize] += 1;
+        }
+
+        // components of equal size are ordered by their smallest node index
+        let mut first_seen = vec![usize::MAX; component_count];
+        for (index, &label) in labels.iter().enumerate() {
+            first_seen[label as usize] = first_seen[label as usize].min(index);
+        }
+
+        let mut order: Vec<usize> = (0..component_count).collect();
+        order.sort_unstable_by_key(|&label| (usize::MAX - sizes[label], first_seen[label]));
+        let mut new_labels = vec![0u32; component_count];
+        for (new_label, &label) in order.iter().enumerate() {
+            new_labels[label] = new_label as u32;
+        }
+
+        Components {
+            labels: labels.into_iter().map(|label| new_labels[label as usize]).collect(),
+            sizes: order.into_iter().map(|label| sizes[label]).collect(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /**
+     * Returns for every node whether it is part of the giant (largest) component.
+     */
+    pub fn giant_component(&self) -> Vec<bool> {
+        self.labels.iter().map(|&label| label == 0).collect()
+    }
+}
+
+/**
+ * Finds the weakly connected components, i.e. the components of the graph without directions.
+ */
+pub fn weak_components(graph: &Graph) -> Components {
+    let mut labels = vec![UNVISITED; graph.node_count()];
+    let mut queue = VecDeque::new();
+    let mut component_count = 0;
+
+    for start in graph.indices() {
+        if labels[start as usize] != UNVISITED {
+            continue;
+        }
+
+        labels[start as usize] = component_count;
+        queue.push_back(start);
+        while let Some(v) = queue.pop_front() {
+            for &w in graph.neighbors(v).iter().chain(graph.reverse_neighbors(v)) {
+                if labels[w as usize] == UNVISITED {
+                    labels[w as usize] = component_count;
+                    queue.push_back(w);
+                }
+            }
+        }
+        component_count += 1;
+    }
+
+    Components::from_labels(labels, component_count as usize)
+}
+
+/**
+ * Finds the strongly connected components with Tarjan's algorithm. The depth-first search keeps
+ * its own stack, so that long paths cannot overflow the call stack.
+ */
+pub fn strong_components(graph: &Graph) -> Components {
+    let node_count = graph.node_count();
+    let mut order = vec![UNVISITED; node_count];
+    let mut lowlink = vec![0u32; node_count];
+    let mut on_stack = vec![false; node_count];
+    let mut stack: Vec<NodeIndex> = Vec::new();
+    // nodes of the current search path, with the position of the next neighbor to visit
+    let mut path: Vec<(NodeIndex, usize)> = Vec::new();
+
+    let mut labels = vec![UNVISITED; node_count];
+    let mut visit_count = 0;
+    let mut component_count = 0;
+
+    for root in graph.indices() {
+        if order[root as usize] != UNVISITED {
+            continue;
+        }
+
+        order[root as usize] = visit_count;
+        lowlink[root as usize] = visit_count;
+        visit_count += 1;
+        stack.push(root);
+        on_stack[root as usize] = true;
+        path.push((root, 0));
+
+        while let Some((v, position)) = path.last_mut() {
+            let v = *v;
+            let neighbors = graph.neighbors(v);
+            if *position < neighbors.len() {
+                let w = neighbors[*position];
+                *position += 1;
+
+                if order[w as usize] == UNVISITED {
+                    order[w as usize] = visit_count;
+                    lowlink[w as usize] = visit_count;
+                    visit_count += 1;
+                    stack.push(w);
+                    on_stack[w as usize] = true;
+                    path.push((w, 0));
+                } else if on_stack[w as usize] {
+                    lowlink[v as usize] = lowlink[v as usize].min(order[w as usize]);
+                }
+                continue;
+            }
+
+            path.pop();
+            if let Some(&(parent, _)) = path.last() {
+                lowlink[parent as usize] = lowlink[parent as usize].min(lowlink[v as usize]);
+            }
+
+            if lowlink[v as usize] == order[v as usize] {
+                while let Some(w) = stack.pop() {
+                    on_stack[w as usize] = false;
+                    labels[w as usize] = component_count;
+                    if w == v {
+                        break;
+                    }
+                }
+                component_count += 1;
+            }
+        }
+    }
+
+    Components::from_labels(labels, component_count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels_by_node_id(graph: &Graph, components: &Components) -> Vec<(i64, u32)> {
+        graph.indices().map(|v| (graph.node_id(v), components.labels[v as usize])).collect()
+    }
+
+    #[test]
+    fn strong_components_of_a_small_cyclic_graph() {
+        // cycle 1 -> 2 -> 3 -> 1, cycle 4 <-> 5 reached from it, 6 only reached
+        let graph = Graph::from_edges(vec![(1, 2), (2, 3), (3, 1), (3, 4), (4, 5), (5, 4), (5, 6)], false);
+
+        let strong = strong_components(&graph);
+        assert_eq!(strong.sizes, vec![3, 2, 1]);
+        assert_eq!(labels_by_node_id(&graph, &strong), vec![(1, 0), (2, 0), (3, 0), (4, 1), (5, 1), (6, 2)]);
+
+        let weak = weak_components(&graph);
+        assert_eq!(weak.sizes, vec![6]);
+    }
+
+    #[test]
+    fn components_of_equal_size_are_ordered_by_their_first_node() {
+        let graph = Graph::from_edges(vec![(5, 6), (6, 5), (1, 2), (2, 1), (3, 4)], false);
+
+        let strong = strong_components(&graph);
+        assert_eq!(strong.sizes, vec![2, 2, 1, 1]);
+        assert_eq!(labels_by_node_id(&graph, &strong), vec![(1, 0), (2, 0), (3, 2), (4, 3), (5, 1), (6, 1)]);
+
+        let weak = weak_components(&graph);
+        assert_eq!(weak.sizes, vec![2, 2, 2]);
+        assert_eq!(weak.giant_component(), vec![true, true, false, false, false, false]);
+    }
+
+    #[test]
+    fn long_cycle_does_not_overflow_the_stack() {
+        let node_count = 200_000;
+        let edges = (0..node_count).map(|v| (v, (v + 1) % node_count)).collect();
+        let graph = Graph::from_edges(edges, false);
+
+        assert_eq!(strong_components(&graph).sizes, vec![node_count as usize]);
+    }
+}
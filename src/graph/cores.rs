@@ -0,0 +1,2 @@
+pub mod core_counter;
+mod core_decomposition;
@@ -0,0 +1,125 @@
+use std::fs::File;
+
+use csv::Writer;
+use log::info;
+
+use crate::{CoreKind, CoreParameters, OutputPaths};
+use crate::graph::common::edge_filter::write_edges_within;
+use crate::graph::common::graph::Graph;
+use crate::graph::cores::core_decomposition::core_numbers;
+
+pub struct CoreCounter {
+    graph: Graph,
+    params: CoreParameters,
+    writer: Writer<File>,
+}
+
+impl CoreCounter {
+    pub fn new(graph: Graph, params: CoreParameters, writer: Writer<File>) -> CoreCounter {
+        CoreCounter {
+            graph,
+            params,
+            writer,
+        }
+    }
+
+    /**
+     * Calculates the core numbers and writes
+     *     - the core number of every node to the coreness CSV file
+     *     - the size of every k-core to core_sizes.csv
+     *     - if export_k is set, the edges within the k-core to k_core_edges.csv
+     * On directed graphs, the cores are based on the in, out and total (in + out) degree. On
+     * undirected graphs, these are the same, so only one core number is written.
+     */
+    pub fn calculate_and_persist(&mut self, output_paths: &OutputPaths) {
+        let kinds = if self.graph.is_undirected() {
+            vec![CoreKind::Total]
+        } else {
+            vec![CoreKind::In, CoreKind::Out, CoreKind::Total]
+        };
+
+        let cores: Vec<Vec<u32>> = kinds.iter()
+            .map(|kind| {
+                info!("Calculating {:?} CORE NUMBERS", kind);
+                let cores = core_numbers(&self.graph, kind);
+                info!("The innermost {:?} core is the {}-core", kind, cores.iter().max().copied().unwrap_or(0));
+                cores
+            })
+            .collect();
+
+        self.write_node_cores(&cores);
+        Self::write_core_sizes(output_paths, &kinds, &cores);
+
+        if let Some(k) = self.params.export_k {
+            let position = kinds.iter().position(|kind| *kind == self.params.export_kind).unwrap_or(0);
+            let in_core: Vec<bool> = cores[position].iter().map(|&core| core >= k).collect();
+
+            let path = &output_paths.k_core_edges;
+            info!(
+                "Writing the edges of the {:?} {}-core ({} nodes) to {}",
+                kinds[position], k, in_core.iter().filter(|&&selected| selected).count(), path.to_str().unwrap()
+            );
+            write_edges_within(&self.graph, &output_paths.edges_deduplicated, path, &in_core);
+        }
+    }
+
+    fn write_node_cores(&mut self, cores: &[Vec<u32>]) {
+        if self.graph.is_undirected() {
+            self.writer.serialize(("node_id", "coreness")).unwrap();
+            for v in self.graph.indices() {
+                self.writer.serialize((self.graph.node_id(v), cores[0][v as usize])).unwrap();
+            }
+        } else {
+            self.writer.serialize(("node_id", "core_in", "core_out", "core_total")).unwrap();
+            for v in self.graph.indices() {
+                let v_index = v as usize;
+                let node_id = self.graph.node_id(v);
+                self.writer.serialize((node_id, cores[0][v_index], cores[1][v_index], cores[2][v_index])).unwrap();
+            }
+        }
+        self.writer.flush().unwrap();
+    }
+
+    /**
+     * Writes the number of nodes in the k-core (i.e. with a core number of at least k) for every k
+     * from 0 up to the largest core number.
+     */
+    #[allow(clippy::expect_fun_call, clippy::needless_range_loop)]
+    fn write_core_sizes(output_paths: &OutputPaths, kinds: &[CoreKind], cores: &[Vec<u32>]) {
+        let path = &output_paths.core_sizes;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing core sizes at {}", path.to_str().unwrap()));
+
+        let max_core = cores.iter().flatten().max().copied().unwrap_or(0) as usize;
+        let core_sizes: Vec<Vec<usize>> = cores.iter()
+            .map(|cores| {
+                // count the nodes per core number, then sum up from the innermost core
+                let mut sizes = vec![0usize; max_core + 2];
+                for &core in cores {
+                    sizes[core as usize] += 1;
+                }
+                for k in (0..=max_core).rev() {
+                    sizes[k] += sizes[k + 1];
+                }
+                sizes
+            })
+            .collect();
+
+        if kinds.len() == 1 {
+            writer.serialize(("k", "core_size")).unwrap();
+            for k in 0..=max_core {
+                writer.serialize((k, core_sizes[0][k])).unwrap();
+            }
+        } else {
+            writer.serialize(("k", "core_in_size", "core_out_size", "core_total_size")).unwrap();
+            for k in 0..=max_core {
+                writer.serialize((k, core_sizes[0][k], core_sizes[1][k], core_sizes[2][k])).unwrap();
+            }
+        }
+        writer.flush().unwrap();
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
@@ -0,0 +1,94 @@
+use crate::CoreKind;
+use crate::graph::common::graph::{Graph, NodeIndex};
+
+/**
+ * The neighbors whose edges to v count towards the degree of the given kind. Self loops are left
+ * out, as they cannot hold a node in a core.
+ */
+fn counted_neighbors<'a>(graph: &'a Graph, v: NodeIndex, kind: &CoreKind) -> impl Iterator<Item = NodeIndex> + 'a {
+    let (predecessors, successors): (&[NodeIndex], &[NodeIndex]) = match kind {
+        CoreKind::In => (graph.reverse_neighbors(v), &[]),
+        CoreKind::Out => (&[], graph.neighbors(v)),
+        CoreKind::Total => (graph.reverse_neighbors(v), graph.neighbors(v)),
+    };
+
+    predecessors.iter().chain(successors).copied().filter(move |&w| w != v)
+}
+
+/**
+ * The neighbors whose degree of the given kind drops when v is removed, i.e. the reverse of the
+ * counted neighbors. A neighbor connected in both directions appears twice for the total degree.
+ */
+fn affected_neighbors<'a>(graph: &'a Graph, v: NodeIndex, kind: &CoreKind) -> impl Iterator<Item = NodeIndex> + 'a {
+    let (successors, predecessors): (&[NodeIndex], &[NodeIndex]) = match kind {
+        CoreKind::In => (graph.neighbors(v), &[]),
+        CoreKind::Out => (&[], graph.reverse_neighbors(v)),
+        CoreKind::Total => (graph.neighbors(v), graph.reverse_neighbors(v)),
+    };
+
+    successors.iter().chain(predecessors).copied().filter(move |&w| w != v)
+}
+
+/**
+ * Calculates the core number of every node with the algorithm of Batagelj and Zaversnik (2003) in
+ * O(n + m). The nodes are kept sorted by their current degree in bins and removed in this order;
+ * the degree of a node at its removal is its core number, i.e. the largest k such that the node
+ * belongs to the k-core, the maximal subgraph in which every node has a degree of at least k.
+ */
+pub fn core_numbers(graph: &Graph, kind: &CoreKind) -> Vec<u32> {
+    // the undirected graph holds every edge in both directions, the successors alone are the neighbors
+    let kind = if graph.is_undirected() { &CoreKind::Out } else { kind };
+    let node_count = graph.node_count();
+    let mut degrees: Vec<usize> = graph.indices()
+        .map(|v| counted_neighbors(graph, v, kind).count())
+        .collect();
+    let max_degree = degrees.iter().copied().max().unwrap_or(0);
+
+    // bin_starts[d] is the position of the first node with degree d in the sorted order
+    let mut bin_starts = vec![0usize; max_degree + 1];
+    for &degree in &degrees {
+        bin_starts[degree] += 1;
+    }
+    let mut start = 0;
+    for bin_start in bin_starts.iter_mut() {
+        let size = *bin_start;
+        *bin_start = start;
+        start += size;
+    }
+
+    let mut order: Vec<NodeIndex> = vec![0; node_count];
+    let mut positions = vec![0usize; node_count];
+    let mut next_positions = bin_starts.clone();
+    for v in graph.indices() {
+        let position = next_positions[degrees[v as usize]];
+        positions[v as usize] = position;
+        order[position] = v;
+        next_positions[degrees[v as usize]] += 1;
+    }
+
+    for i in 0..node_count {
+        let v = order[i];
+        let v_degree = degrees[v as usize];
+        for w in affected_neighbors(graph, v, kind) {
+            let w_degree = degrees[w as usize];
+            if w_degree <= v_degree {
+                continue;
+            }
+
+            // swap w with the first node of its bin and move the bin start behind it, so that w
+            // becomes the last node of the next lower bin
+            let w_position = positions[w as usize];
+            let first_position = bin_starts[w_degree];
+            let first = order[first_position];
+            if first != w {
+                order.swap(w_position, first_position);
+                positions[w as usize] = first_position;
+                positions[first as usize] = w_position;
+            }
+            bin_starts[w_degree] += 1;
+            degrees[w as usize] -= 1;
+        }
+    }
+
+    degrees.into_iter().map(|degree| degree as u32).collect()
+}
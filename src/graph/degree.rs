@@ -1,2 +1,5 @@
 pub mod degree_counter;
+pub mod degree_distribution;
 mod degree_calculator;
+mod power_law_fit;
+mod special_functions;
@@ -1,7 +1,4 @@
-use std::collections::HashSet;
-
-use crate::graph::common::graph::Graph;
-use crate::graph::common::sparse_list::SparseList;
+use crate::graph::common::graph::{Graph, NodeIndex};
 
 pub struct DegreeCalculator {
 }
@@ -11,54 +8,43 @@ impl DegreeCalculator {
         DegreeCalculator {  }
     }
 
-    pub fn collect_values_for_node(&self, node_id: i64, graph: &mut Graph) -> DegreeValues {
-        graph.ensure_reversed_edges_exist();
-
-        let mut results = DegreeValues::new(node_id);
+    pub fn collect_values_for_node(&self, node: NodeIndex, graph: &Graph) -> DegreeValues {
+        let mut results = DegreeValues::new(graph.node_id(node));
 
-        results.d_in = graph.edges_reversed()[node_id].len() as u32;
-        results.d_out = graph.edges()[node_id].len() as u32;
+        results.d_in = graph.in_degree(node) as u32;
+        results.d_out = graph.out_degree(node) as u32;
 
-        results.and_in = self.average_neighbor_degree(node_id, graph, Direction::IN);
-        results.and_out = self.average_neighbor_degree(node_id, graph, Direction::OUT);
-        results.and_total = self.average_neighbor_degree(node_id, graph, Direction::BOTH);
+        results.and_in = self.average_neighbor_degree(node, graph, Direction::IN);
+        results.and_out = self.average_neighbor_degree(node, graph, Direction::OUT);
+        results.and_total = self.average_neighbor_degree(node, graph, Direction::BOTH);
 
-        results.iand_in = self.iterated_average_neighbor_degree(node_id, graph, Direction::IN);
-        results.iand_out = self.iterated_average_neighbor_degree(node_id, graph, Direction::OUT);
-        results.iand_total = self.iterated_average_neighbor_degree(node_id, graph, Direction::BOTH);
+        results.iand_in = self.iterated_average_neighbor_degree(node, graph, Direction::IN);
+        results.iand_out = self.iterated_average_neighbor_degree(node, graph, Direction::OUT);
+        results.iand_total = self.iterated_average_neighbor_degree(node, graph, Direction::BOTH);
 
         results
     }
 
-    fn average_neighbor_degree(
-        &self, node_id: i64, graph: &mut Graph, direction: Direction,
-    ) -> f64 {
-        let edges = graph.edges();
-        let reverse_edges = graph.edges_reversed();
+    fn average_neighbor_degree(&self, node: NodeIndex, graph: &Graph, direction: Direction) -> f64 {
         let mut first_hop_neighbors = Vec::new();
 
         match direction {
             Direction::IN => {
-                first_hop_neighbors.extend(&reverse_edges[node_id]);
+                first_hop_neighbors.extend(graph.reverse_neighbors(node));
             }
             Direction::OUT => {
-                first_hop_neighbors.extend(&edges[node_id]);
+                first_hop_neighbors.extend(graph.neighbors(node));
             }
             Direction::BOTH => {
-                first_hop_neighbors.extend(&edges[node_id]);
-                first_hop_neighbors.extend(&reverse_edges[node_id]);
+                first_hop_neighbors.extend(graph.neighbors(node));
+                first_hop_neighbors.extend(graph.reverse_neighbors(node));
             }
         }
 
-        Self::obtain_average(direction, edges, reverse_edges, &mut first_hop_neighbors)
+        Self::obtain_average(direction, graph, &first_hop_neighbors)
     }
 
-    fn obtain_average(
-        direction: Direction,
-        edges: &SparseList<HashSet<i64>>,
-        reverse_edges: &SparseList<HashSet<i64>>,
-        neighbors: &mut Vec<i64>
-    ) -> f64 {
+    fn obtain_average(direction: Direction, graph: &Graph, neighbors: &[NodeIndex]) -> f64 {
         if neighbors.is_empty() {
             return 0.0
         }
@@ -66,9 +52,9 @@ impl DegreeCalculator {
         let neighbor_sum: usize = neighbors.iter()
             .map(|&neighbor| {
                 match direction {
-                    Direction::IN => reverse_edges[neighbor].len(),
-                    Direction::OUT => edges[neighbor].len(),
-                    Direction::BOTH => edges[neighbor].len() + reverse_edges[neighbor].len()
+                    Direction::IN => graph.in_degree(neighbor),
+                    Direction::OUT => graph.out_degree(neighbor),
+                    Direction::BOTH => graph.out_degree(neighbor) + graph.in_degree(neighbor)
                 }
             })
             .sum();
@@ -76,46 +62,40 @@ impl DegreeCalculator {
         (neighbor_sum as f64) / (neighbors.len() as f64)
     }
 
-    fn iterated_average_neighbor_degree(
-        &self, node_id: i64, graph: &mut Graph, direction: Direction,
-    ) -> f64 {
-        let edges = graph.edges();
-        let reverse_edges = graph.edges_reversed();
-        let mut two_hop_neighbors: Vec<i64> = Vec::new();
+    fn iterated_average_neighbor_degree(&self, node: NodeIndex, graph: &Graph, direction: Direction) -> f64 {
+        let mut two_hop_neighbors: Vec<NodeIndex> = Vec::new();
+
+        let forward = |index: NodeIndex| graph.neighbors(index);
+        let reverse = |index: NodeIndex| graph.reverse_neighbors(index);
 
         match direction {
             Direction::IN => {
-                two_hop_neighbors.extend(&reverse_edges[node_id]);
-                two_hop_neighbors.extend(
-                    Self::obtain_second_degree_neighborhood(node_id, &reverse_edges)
-                );
+                two_hop_neighbors.extend(reverse(node));
+                two_hop_neighbors.extend(Self::obtain_second_degree_neighborhood(node, reverse));
             }
             Direction::OUT => {
-                two_hop_neighbors.extend(&edges[node_id]);
-                two_hop_neighbors.extend(
-                    Self::obtain_second_degree_neighborhood(node_id, &edges)
-                );
+                two_hop_neighbors.extend(forward(node));
+                two_hop_neighbors.extend(Self::obtain_second_degree_neighborhood(node, forward));
             }
             Direction::BOTH => {
-                two_hop_neighbors.extend(&edges[node_id]);
-                two_hop_neighbors.extend(&reverse_edges[node_id]);
-
-                two_hop_neighbors.extend(
-                    Self::obtain_second_degree_neighborhood(node_id, &edges)
-                );
-                two_hop_neighbors.extend(
-                    Self::obtain_second_degree_neighborhood(node_id, &reverse_edges)
-                );
+                two_hop_neighbors.extend(forward(node));
+                two_hop_neighbors.extend(reverse(node));
+
+                two_hop_neighbors.extend(Self::obtain_second_degree_neighborhood(node, forward));
+                two_hop_neighbors.extend(Self::obtain_second_degree_neighborhood(node, reverse));
             }
         }
 
-        Self::obtain_average(direction, edges, reverse_edges, &mut two_hop_neighbors)
+        Self::obtain_average(direction, graph, &two_hop_neighbors)
     }
 
-    fn obtain_second_degree_neighborhood(node_id: i64, edges: &SparseList<HashSet<i64>>) -> Vec<i64> {
-        edges[node_id].iter()
-            .flat_map(|&neighbor| &edges[neighbor])
-            .map(|&n| n)
+    fn obtain_second_degree_neighborhood<'a>(
+        node: NodeIndex,
+        neighbors: impl Fn(NodeIndex) -> &'a [NodeIndex],
+    ) -> Vec<NodeIndex> {
+        neighbors(node).iter()
+            .flat_map(|&neighbor| neighbors(neighbor))
+            .copied()
             .collect()
     }
 }
@@ -161,11 +141,19 @@ impl DegreeValues {
         )
     }
 
+    /**
+     * Only meaningful for undirected graphs, where the in and out values are identical.
+     */
+    pub fn as_undirected_tuple(&self) -> (i64, u32, f64, f64) {
+        (self.node_id, self.d_out, self.and_out, self.iand_out)
+    }
+
     pub fn is_non_zero(&self) -> bool {
         !(self.d_in == 0 && self.d_out == 0)
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 enum Direction {
     IN,
     OUT,
@@ -9,48 +9,67 @@ use crate::graph::degree::degree_calculator::DegreeCalculator;
 
 pub struct DegreeCounter {
     graph: Graph,
-    writer: Writer<File>
+    writer: Writer<File>,
+    node_column: &'static str,
 }
 
 impl DegreeCounter {
     pub fn new(graph: Graph, writer: Writer<File>) -> DegreeCounter {
         DegreeCounter {
-            graph, writer
+            graph, writer, node_column: "node_id"
         }
     }
 
+    /**
+     * Sets the header of the node column, for graphs whose nodes are not router-level node IDs.
+     */
+    pub fn with_node_column(mut self, node_column: &'static str) -> DegreeCounter {
+        self.node_column = node_column;
+        self
+    }
+
     /**
      * Calculates the degree statistics and writes them to the CSV file writer.
      * Stats:
      *     - degree in/out
      *     - average neighbor degree (and) in/out
      *     - iterated average neighbor degree (iand) in/out
+     * On undirected graphs, in and out are the same, so only one value of each is written.
      */
     pub fn calculate_and_persist(&mut self) {
         let calculator = DegreeCalculator::new();
+        let undirected = self.graph.is_undirected();
 
         info!("Counting degrees for all nodes...");
-        let mut progress_bar = ProgressBar::new(self.graph.boundaries().len() as u64);
+        let mut progress_bar = ProgressBar::new(self.graph.node_count() as u64);
         let mut counter = 0;
 
         info!("Calculating values and writing to file...");
-        self.writer.serialize((
-            "node_id",
-            "degree_in",
-            "degree_out",
-            "and_in",
-            "and_out",
-            "and_total",
-            "iand_in",
-            "iand_out",
-            "iand_total",
-        )).unwrap();
+        if undirected {
+            self.writer.serialize((self.node_column, "degree", "and", "iand")).unwrap();
+        } else {
+            self.writer.serialize((
+                self.node_column,
+                "degree_in",
+                "degree_out",
+                "and_in",
+                "and_out",
+                "and_total",
+                "iand_in",
+                "iand_out",
+                "iand_total",
+            )).unwrap();
+        }
 
-        for node in self.graph.boundaries().range_inclusive() {
-            let values = calculator.collect_values_for_node(node, &mut self.graph);
+        for node in self.graph.indices() {
+            let values = calculator.collect_values_for_node(node, &self.graph);
 
             if values.is_non_zero() {
-                self.writer.serialize(values.as_tuple()).unwrap();
+                if undirected {
+                    self.writer.serialize(values.as_undirected_tuple()).unwrap();
+                } else {
+                    self.writer.serialize(values.as_tuple()).unwrap();
+                }
             }
 
             counter += 1;
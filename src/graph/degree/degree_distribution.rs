@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use log::{info, warn};
+
+use crate::{DegreeParameters, OutputPaths};
+use crate::graph::common::graph::Graph;
+use crate::graph::degree::power_law_fit::{
+    bootstrap_p_value, compare_exponential, compare_lognormal, fit_power_law, Histogram,
+};
+
+pub struct DegreeDistribution {
+    graph: Graph,
+    params: DegreeParameters,
+}
+
+impl DegreeDistribution {
+    pub fn new(graph: Graph, params: DegreeParameters) -> DegreeDistribution {
+        DegreeDistribution {
+            graph,
+            params,
+        }
+    }
+
+    /**
+     * Counts the nodes of every degree and writes
+     *     - the histograms and CCDFs (fraction of nodes with at least the degree) to degree_distribution.csv
+     *     - a power law fit of every distribution with its goodness of fit and the comparison with
+     *       log-normal and exponential distributions to degree_fit.csv
+     * On directed graphs, this is done for the in, out and total (in + out) degree. On undirected
+     * graphs, these are the same, so only one distribution is written.
+     */
+    pub fn calculate_and_persist(&self, output_paths: &OutputPaths) {
+        let kinds: Vec<(&str, BTreeMap<u64, u64>)> = if self.graph.is_undirected() {
+            vec![("degree", self.count_nodes(|v| self.graph.out_degree(v)))]
+        } else {
+            vec![
+                ("in", self.count_nodes(|v| self.graph.in_degree(v))),
+                ("out", self.count_nodes(|v| self.graph.out_degree(v))),
+                ("total", self.count_nodes(|v| self.graph.in_degree(v) + self.graph.out_degree(v))),
+            ]
+        };
+
+        self.write_distribution(output_paths, &kinds);
+        self.write_fits(output_paths, &kinds);
+    }
+
+    fn count_nodes<F>(&self, degree: F) -> BTreeMap<u64, u64>
+        where F: Fn(u32) -> usize {
+        let mut counts = BTreeMap::new();
+        for v in self.graph.indices() {
+            *counts.entry(degree(v) as u64).or_default() += 1;
+        }
+        counts
+    }
+
+    /**
+     * Writes one row per degree that occurs in any of the distributions.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn write_distribution(&self, output_paths: &OutputPaths, kinds: &[(&str, BTreeMap<u64, u64>)]) {
+        let path = &output_paths.degree_distribution;
+        info!("Writing the degree distribution to {}", path.to_str().unwrap());
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!(
+                "Could not create file for storing the degree distribution at {}",
+                path.to_str().unwrap()
+            ));
+
+        let mut header = vec!["degree".to_string()];
+        if kinds.len() == 1 {
+            header.extend(["nodes".to_string(), "ccdf".to_string()]);
+        } else {
+            header.extend(kinds.iter().map(|(kind, _)| format!("nodes_{}", kind)));
+            header.extend(kinds.iter().map(|(kind, _)| format!("ccdf_{}", kind)));
+        }
+        writer.write_record(&header).unwrap();
+
+        let node_count = self.graph.node_count() as f64;
+        let mut degrees: Vec<u64> = kinds.iter().flat_map(|(_, counts)| counts.keys()).copied().collect();
+        degrees.sort_unstable();
+        degrees.dedup();
+
+        // the number of nodes with at least the current degree, per kind
+        let mut remaining: Vec<u64> = vec![self.graph.node_count() as u64; kinds.len()];
+        for degree in degrees {
+            let counts: Vec<u64> = kinds.iter()
+                .map(|(_, counts)| counts.get(&degree).copied().unwrap_or(0))
+                .collect();
+
+            let mut record = vec![degree.to_string()];
+            record.extend(counts.iter().map(u64::to_string));
+            record.extend(remaining.iter().map(|&count| (count as f64 / node_count).to_string()));
+            writer.write_record(&record).unwrap();
+
+            for (remaining, count) in remaining.iter_mut().zip(counts) {
+                *remaining -= count;
+            }
+        }
+        writer.flush().unwrap();
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn write_fits(&self, output_paths: &OutputPaths, kinds: &[(&str, BTreeMap<u64, u64>)]) {
+        let path = &output_paths.degree_fit;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing the degree fit at {}", path.to_str().unwrap()));
+        writer.serialize((
+            "degree", "xmin", "alpha", "alpha_error", "tail_size", "ks_distance", "p_value",
+            "lognormal_mu", "lognormal_sigma", "lognormal_ratio", "lognormal_p",
+            "exponential_lambda", "exponential_ratio", "exponential_p",
+        )).unwrap();
+
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.params.max_thread_count as usize)
+            .build()
+            .expect("Could not create thread pool for fitting the degree distribution");
+
+        for (kind, counts) in kinds {
+            info!("Fitting a POWER LAW to the {} degree distribution", kind);
+            let histogram = Histogram::new(counts);
+            let fit = match fit_power_law(&histogram) {
+                Some(fit) => fit,
+                None => {
+                    warn!("The {} degree distribution has less than two distinct positive degrees, no fit", kind);
+                    continue;
+                }
+            };
+
+            let p_value = if self.params.bootstrap_samples > 0 {
+                info!("Testing the goodness of fit with {} bootstrap samples", self.params.bootstrap_samples);
+                let samples = self.params.bootstrap_samples;
+                thread_pool.install(|| bootstrap_p_value(&histogram, &fit, samples, self.params.seed))
+            } else {
+                f64::NAN
+            };
+            let lognormal = compare_lognormal(&histogram, &fit);
+            let exponential = compare_exponential(&histogram, &fit);
+
+            info!(
+                "{} degree: xmin = {}, alpha = {:.4} +- {:.4} ({} of {} nodes in the tail), KS distance {:.4}, p = {}",
+                kind, fit.xmin, fit.alpha, fit.alpha_error(), fit.tail_size, histogram.size(), fit.ks_distance, p_value
+            );
+            info!(
+                "{} degree: log-likelihood ratio to log-normal {:.4} (p = {:.4}), to exponential {:.4} (p = {:.4})",
+                kind, lognormal.ratio, lognormal.p_value, exponential.ratio, exponential.p_value
+            );
+
+            writer.serialize((
+                kind, fit.xmin, fit.alpha, fit.alpha_error(), fit.tail_size, fit.ks_distance, p_value,
+                lognormal.parameters.0, lognormal.parameters.1, lognormal.ratio, lognormal.p_value,
+                exponential.parameters.0, exponential.ratio, exponential.p_value,
+            )).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
@@ -0,0 +1,423 @@
+/*!
+ * Fitting of discrete power laws to degree distributions after Clauset, Shalizi and Newman (2009).
+ * For every candidate xmin, the exponent alpha of p(x) = x^-alpha / zeta(alpha, xmin), x >= xmin, is
+ * estimated by maximum likelihood. The xmin whose fit has the smallest Kolmogorov-Smirnov distance
+ * to the data is chosen. The goodness of fit is the fraction of semi-parametric bootstrap samples
+ * whose own best fit is further from them than the fit is from the data.
+ *
+ * Log-normal and exponential distributions are fitted to the same tail and compared to the power
+ * law with the normalized log-likelihood ratio test of Vuong.
+ */
+
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+
+use crate::common::random::Random;
+use crate::graph::degree::special_functions::{erfc, log_hurwitz_zeta, log_normal_interval, log_normal_upper_tail};
+
+const MIN_ALPHA: f64 = 1.000_001;
+
+/**
+ * A sample of positive integers, stored as its distinct values in ascending order with their counts.
+ */
+pub struct Histogram {
+    values: Vec<u64>,
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    /**
+     * Builds the histogram from value counts, leaving out the value 0.
+     */
+    pub fn new(counts: &BTreeMap<u64, u64>) -> Histogram {
+        let (values, counts) = counts.iter()
+            .filter(|&(&value, &count)| value > 0 && count > 0)
+            .map(|(&value, &count)| (value, count))
+            .unzip();
+
+        Histogram {
+            values,
+            counts,
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /**
+     * The values and counts from the given position in the distinct values on.
+     */
+    fn tail(&self, start: usize) -> (&[u64], &[u64]) {
+        (&self.values[start..], &self.counts[start..])
+    }
+}
+
+pub struct PowerLawFit {
+    pub xmin: u64,
+    pub alpha: f64,
+    pub tail_size: u64,
+    pub ks_distance: f64,
+}
+
+impl PowerLawFit {
+    /**
+     * The standard error of alpha, (alpha - 1) / sqrt(n).
+     */
+    pub fn alpha_error(&self) -> f64 {
+        (self.alpha - 1.0) / (self.tail_size as f64).sqrt()
+    }
+
+    fn log_probability(&self, x: u64, log_normalizer: f64) -> f64 {
+        -self.alpha * (x as f64).ln() - log_normalizer
+    }
+}
+
+/**
+ * The result of a log-likelihood ratio test. A positive ratio favours the power law, a negative one
+ * the alternative. The p-value states whether the sign of the ratio is significant.
+ */
+pub struct Comparison {
+    pub parameters: (f64, f64),
+    pub ratio: f64,
+    pub p_value: f64,
+}
+
+/**
+ * Fits a power law to every tail of the histogram, starting at each distinct value except the
+ * largest, and returns the fit with the smallest KS distance. Returns None if the histogram has
+ * less than two distinct values.
+ */
+pub fn fit_power_law(histogram: &Histogram) -> Option<PowerLawFit> {
+    let candidates = histogram.values.len().saturating_sub(1);
+
+    (0..candidates)
+        .map(|start| fit_tail(histogram, start))
+        .min_by(|a, b| a.ks_distance.total_cmp(&b.ks_distance))
+}
+
+/**
+ * Fits a power law with xmin at the given position of the distinct values.
+ */
+fn fit_tail(histogram: &Histogram, start: usize) -> PowerLawFit {
+    let (values, counts) = histogram.tail(start);
+    let xmin = values[0];
+    let tail_size: u64 = counts.iter().sum();
+    let log_sum: f64 = values.iter().zip(counts).map(|(&x, &count)| count as f64 * (x as f64).ln()).sum();
+
+    // the log-likelihood is concave in alpha, so its maximum lies below the first doubling of alpha
+    // that decreases it, and a golden section search finds it
+    let log_likelihood = |alpha: f64| -alpha * log_sum - tail_size as f64 * log_hurwitz_zeta(alpha, xmin as f64);
+    let mut upper = 2.0;
+    while upper < 1e6 && log_likelihood(2.0 * upper) > log_likelihood(upper) {
+        upper *= 2.0;
+    }
+    let alpha = maximize_golden_section(log_likelihood, MIN_ALPHA, 2.0 * upper, 1e-9 * upper);
+
+    let mut fit = PowerLawFit {
+        xmin,
+        alpha,
+        tail_size,
+        ks_distance: 0.0,
+    };
+    fit.ks_distance = ks_distance(&fit, values, counts);
+    fit
+}
+
+/**
+ * The largest difference between the empirical and the fitted CDF, at the observed values.
+ */
+fn ks_distance(fit: &PowerLawFit, values: &[u64], counts: &[u64]) -> f64 {
+    let log_normalizer = log_hurwitz_zeta(fit.alpha, fit.xmin as f64);
+    let mut cumulative_count = 0;
+
+    values.iter()
+        .zip(counts)
+        .map(|(&x, &count)| {
+            cumulative_count += count;
+            let empirical = cumulative_count as f64 / fit.tail_size as f64;
+            let fitted = 1.0 - (log_hurwitz_zeta(fit.alpha, (x + 1) as f64) - log_normalizer).exp();
+            (empirical - fitted).abs()
+        })
+        .fold(0.0, f64::max)
+}
+
+/**
+ * The goodness of fit: draws synthetic samples of the size of the histogram that follow the fitted
+ * power law above xmin and the data below, fits a power law to each of them and returns the fraction
+ * whose KS distance is at least the one of the data. Values above 0.1 mean that a power law is a
+ * plausible model. The bootstrap sample with a given index is the same in every run with the seed.
+ */
+pub fn bootstrap_p_value(histogram: &Histogram, fit: &PowerLawFit, sample_count: u32, seed: u64) -> f64 {
+    let size = histogram.size();
+    let tail_probability = fit.tail_size as f64 / size as f64;
+    let body_size = size - fit.tail_size;
+    let body_end = histogram.values.partition_point(|&value| value < fit.xmin);
+    let body_cumulative_counts: Vec<u64> = histogram.counts[..body_end].iter()
+        .scan(0, |sum, &count| {
+            *sum += count;
+            Some(*sum)
+        })
+        .collect();
+
+    let worse_fits = (0..sample_count).into_par_iter()
+        .filter(|&index| {
+            let mut random = Random::for_stream(seed, index as u64);
+            let mut counts: BTreeMap<u64, u64> = BTreeMap::new();
+            for _ in 0..size {
+                let value = if body_size == 0 || random.next_f64() < tail_probability {
+                    sample_power_law(fit, &mut random)
+                } else {
+                    let target = random.below(body_size as usize) as u64;
+                    let position = body_cumulative_counts.partition_point(|&sum| sum <= target);
+                    histogram.values[position]
+                };
+                *counts.entry(value).or_default() += 1;
+            }
+
+            fit_power_law(&Histogram::new(&counts))
+                .is_none_or(|synthetic_fit| synthetic_fit.ks_distance >= fit.ks_distance)
+        })
+        .count();
+
+    worse_fits as f64 / sample_count.max(1) as f64
+}
+
+/**
+ * Draws from the discrete power law by rounding a continuous power law with xmin - 1/2, the
+ * approximation of Clauset, Shalizi and Newman.
+ */
+fn sample_power_law(fit: &PowerLawFit, random: &mut Random) -> u64 {
+    let r = random.next_f64();
+    let value = (fit.xmin as f64 - 0.5) * (1.0 - r).powf(-1.0 / (fit.alpha - 1.0)) + 0.5;
+    value.floor() as u64
+}
+
+/**
+ * Compares the power law with an exponential distribution p(x) = (1 - e^-lambda) e^(-lambda (x - xmin))
+ * fitted to the same tail. The parameters are (lambda, NaN).
+ */
+pub fn compare_exponential(histogram: &Histogram, fit: &PowerLawFit) -> Comparison {
+    let (values, counts) = tail_of(histogram, fit);
+    let mean = values.iter().zip(counts).map(|(&x, &count)| (x * count) as f64).sum::<f64>() / fit.tail_size as f64;
+    let lambda = (1.0 + 1.0 / (mean - fit.xmin as f64)).ln();
+
+    let log_probability = |x: u64| (-(-lambda).exp_m1()).ln() - lambda * (x - fit.xmin) as f64;
+    likelihood_ratio_test(histogram, fit, (lambda, f64::NAN), log_probability)
+}
+
+/**
+ * Compares the power law with a discretized log-normal distribution, i.e. the probability of x is the
+ * one of the interval [x, x + 1), fitted to the same tail by Nelder-Mead. The parameters are
+ * (mu, sigma) of the underlying normal distribution.
+ */
+pub fn compare_lognormal(histogram: &Histogram, fit: &PowerLawFit) -> Comparison {
+    let (values, counts) = tail_of(histogram, fit);
+    let log_probability = |mu: f64, sigma: f64, x: u64| {
+        let z = |y: f64| (y.ln() - mu) / sigma;
+        log_normal_interval(z(x as f64), z((x + 1) as f64)) - log_normal_upper_tail(z(fit.xmin as f64))
+    };
+    let negative_log_likelihood = |parameters: [f64; 2]| {
+        let (mu, sigma) = (parameters[0], parameters[1].exp());
+        let log_likelihood: f64 = values.iter()
+            .zip(counts)
+            .map(|(&x, &count)| count as f64 * log_probability(mu, sigma, x))
+            .sum();
+        if log_likelihood.is_finite() { -log_likelihood } else { f64::INFINITY }
+    };
+
+    // start from the moments of the logarithms of the tail
+    let tail_size = fit.tail_size as f64;
+    let log_mean = values.iter()
+        .zip(counts)
+        .map(|(&x, &count)| count as f64 * (x as f64).ln())
+        .sum::<f64>() / tail_size;
+    let log_variance = values.iter()
+        .zip(counts)
+        .map(|(&x, &count)| count as f64 * ((x as f64).ln() - log_mean).powi(2))
+        .sum::<f64>() / tail_size;
+    let log_sigma = if log_variance > 0.0 { 0.5 * log_variance.ln() } else { 0.0 };
+
+    let [mu, log_sigma] = minimize_nelder_mead(negative_log_likelihood, [log_mean, log_sigma]);
+    let sigma = log_sigma.exp();
+    likelihood_ratio_test(histogram, fit, (mu, sigma), |x| log_probability(mu, sigma, x))
+}
+
+fn tail_of<'a>(histogram: &'a Histogram, fit: &PowerLawFit) -> (&'a [u64], &'a [u64]) {
+    histogram.tail(histogram.values.partition_point(|&value| value < fit.xmin))
+}
+
+/**
+ * The log-likelihood ratio R of the power law and the alternative on the tail, and the p-value of
+ * the normalized ratio R / (sigma sqrt(n)) of Vuong, where sigma is the standard deviation of the
+ * pointwise log-likelihood ratios.
+ */
+fn likelihood_ratio_test<F>(
+    histogram: &Histogram,
+    fit: &PowerLawFit,
+    parameters: (f64, f64),
+    alternative: F,
+) -> Comparison
+    where F: Fn(u64) -> f64 {
+    let (values, counts) = tail_of(histogram, fit);
+    let log_normalizer = log_hurwitz_zeta(fit.alpha, fit.xmin as f64);
+    let ratios: Vec<(f64, u64)> = values.iter()
+        .zip(counts)
+        .map(|(&x, &count)| (fit.log_probability(x, log_normalizer) - alternative(x), count))
+        .collect();
+
+    let n = fit.tail_size as f64;
+    let ratio: f64 = ratios.iter().map(|&(r, count)| count as f64 * r).sum();
+    let mean = ratio / n;
+    let variance = ratios.iter().map(|&(r, count)| count as f64 * (r - mean).powi(2)).sum::<f64>() / n;
+    let p_value = erfc(ratio.abs() / (2.0 * n * variance).sqrt());
+
+    Comparison {
+        parameters,
+        ratio,
+        p_value,
+    }
+}
+
+fn maximize_golden_section<F>(function: F, mut lower: f64, mut upper: f64, tolerance: f64) -> f64
+    where F: Fn(f64) -> f64 {
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let mut left = upper - ratio * (upper - lower);
+    let mut right = lower + ratio * (upper - lower);
+    let mut left_value = function(left);
+    let mut right_value = function(right);
+
+    while upper - lower > tolerance {
+        if left_value > right_value {
+            upper = right;
+            right = left;
+            right_value = left_value;
+            left = upper - ratio * (upper - lower);
+            left_value = function(left);
+        } else {
+            lower = left;
+            left = right;
+            left_value = right_value;
+            right = lower + ratio * (upper - lower);
+            right_value = function(right);
+        }
+    }
+
+    (lower + upper) / 2.0
+}
+
+/**
+ * Minimizes a function of two variables with the simplex method of Nelder and Mead, starting with a
+ * simplex of unit size around the start.
+ */
+fn minimize_nelder_mead<F>(function: F, start: [f64; 2]) -> [f64; 2]
+    where F: Fn([f64; 2]) -> f64 {
+    let mut simplex: Vec<([f64; 2], f64)> = [start, [start[0] + 1.0, start[1]], [start[0], start[1] + 1.0]]
+        .into_iter()
+        .map(|point| (point, function(point)))
+        .collect();
+    let along = |from: [f64; 2], to: [f64; 2], factor: f64| {
+        [from[0] + factor * (to[0] - from[0]), from[1] + factor * (to[1] - from[1])]
+    };
+
+    for _ in 0..1000 {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0].1, simplex[2].1);
+        if (worst - best).abs() <= 1e-12 * (1.0 + best.abs()) {
+            break;
+        }
+
+        let centroid = along(simplex[0].0, simplex[1].0, 0.5);
+        let worst_point = simplex[2].0;
+        let reflected = along(centroid, worst_point, -1.0);
+        let reflected_value = function(reflected);
+
+        if reflected_value < best {
+            let expanded = along(centroid, worst_point, -2.0);
+            let expanded_value = function(expanded);
+            simplex[2] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+        } else if reflected_value < simplex[1].1 {
+            simplex[2] = (reflected, reflected_value);
+        } else {
+            let contracted = along(centroid, worst_point, 0.5);
+            let contracted_value = function(contracted);
+            if contracted_value < worst {
+                simplex[2] = (contracted, contracted_value);
+            } else {
+                let best_point = simplex[0].0;
+                for vertex in simplex.iter_mut().skip(1) {
+                    let shrunk = along(best_point, vertex.0, 0.5);
+                    *vertex = (shrunk, function(shrunk));
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex[0].0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Draws a sample of the discrete power law with the given exponent and xmin by inverting its
+     * CCDF zeta(alpha, x) / zeta(alpha, xmin) exactly.
+     */
+    fn power_law_sample(alpha: f64, xmin: u64, size: usize, seed: u64) -> BTreeMap<u64, u64> {
+        let log_normalizer = log_hurwitz_zeta(alpha, xmin as f64);
+        let mut random = Random::new(seed);
+        let mut counts = BTreeMap::new();
+        for _ in 0..size {
+            let r = random.next_f64();
+            let mut x = xmin;
+            while (log_hurwitz_zeta(alpha, (x + 1) as f64) - log_normalizer).exp() > r {
+                x += 1;
+            }
+            *counts.entry(x).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn maximum_likelihood_recovers_the_exponent() {
+        let histogram = Histogram::new(&power_law_sample(2.5, 1, 20_000, 42));
+        let fit = fit_tail(&histogram, 0);
+
+        assert_eq!(fit.xmin, 1);
+        assert_eq!(fit.tail_size, 20_000);
+        assert!((fit.alpha - 2.5).abs() < 3.0 * fit.alpha_error(), "alpha {}", fit.alpha);
+        assert!(fit.ks_distance < 0.02, "KS distance {}", fit.ks_distance);
+    }
+
+    #[test]
+    fn best_fit_finds_the_start_of_the_power_law() {
+        let mut counts = power_law_sample(2.2, 10, 20_000, 7);
+        // values below xmin that do not follow the power law
+        for x in 1..10 {
+            counts.insert(x, 500);
+        }
+
+        let fit = fit_power_law(&Histogram::new(&counts)).unwrap();
+        assert!((8..=12).contains(&fit.xmin), "xmin {}", fit.xmin);
+        assert!((fit.alpha - 2.2).abs() < 0.1, "alpha {}", fit.alpha);
+    }
+
+    #[test]
+    fn exponential_tail_is_not_mistaken_for_a_power_law() {
+        let counts: BTreeMap<u64, u64> = (1..=30).map(|x| (x, (100_000.0 * (-0.5 * x as f64).exp()) as u64)).collect();
+        let histogram = Histogram::new(&counts);
+        let fit = fit_tail(&histogram, 0);
+
+        let comparison = compare_exponential(&histogram, &fit);
+        assert!(comparison.ratio < 0.0);
+        assert!(comparison.p_value < 0.01);
+        assert!((comparison.parameters.0 - 0.5).abs() < 0.01, "lambda {}", comparison.parameters.0);
+    }
+}
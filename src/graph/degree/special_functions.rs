@@ -0,0 +1,153 @@
+use std::f64::consts::{PI, SQRT_2};
+
+// Bernoulli numbers B_2, B_4, ..., B_14 divided by (2j)!, for the Euler-Maclaurin correction terms
+const BERNOULLI_TERMS: [f64; 7] = [
+    1.0 / 12.0,
+    -1.0 / 720.0,
+    1.0 / 30_240.0,
+    -1.0 / 1_209_600.0,
+    1.0 / 47_900_160.0,
+    -691.0 / 1_307_674_368_000.0,
+    1.0 / 74_724_249_600.0,
+];
+
+/**
+ * The logarithm of the Hurwitz zeta function zeta(s, q) = sum over k >= 0 of (q + k)^-s, for s > 1
+ * and q > 0. The first terms are summed directly until q + k >= max(10, s), the rest is approximated
+ * by Euler-Maclaurin summation, which is accurate to double precision from there on. All terms are
+ * scaled by q^s, so that large exponents do not underflow.
+ */
+pub fn log_hurwitz_zeta(s: f64, q: f64) -> f64 {
+    let scaled_power = |x: f64| (-s * (x / q).ln()).exp();
+    let mut sum = 0.0;
+    let mut a = q;
+    while a < s.max(10.0) {
+        let term = scaled_power(a);
+        sum += term;
+        a += 1.0;
+        // for large s, the terms vanish long before
+        if term < sum * 1e-17 {
+            return sum.ln() - s * q.ln();
+        }
+    }
+
+    let a_power = scaled_power(a);
+    sum += a_power * a / (s - 1.0) + 0.5 * a_power;
+
+    // the j-th term is B_2j / (2j)! * s (s + 1) ... (s + 2j - 2) * a^(-s - 2j + 1)
+    let mut factor = s * a_power / a;
+    for (j, bernoulli_term) in BERNOULLI_TERMS.iter().enumerate() {
+        sum += bernoulli_term * factor;
+        let next = 2.0 * (j + 1) as f64;
+        factor *= (s + next - 1.0) * (s + next) / (a * a);
+    }
+
+    sum.ln() - s * q.ln()
+}
+
+/**
+ * The complementary error function, to about double precision. Uses the series of erf below 3 and
+ * the continued fraction of erfc above, so that the small values in the upper tail keep their
+ * relative precision.
+ */
+pub fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        2.0 - erfc(-x)
+    } else if x < 3.0 {
+        // erf(x) = 2 / sqrt(pi) * exp(-x^2) * sum of 2^n x^(2n+1) / (1 * 3 * ... * (2n+1))
+        let mut term = x;
+        let mut sum = x;
+        let mut n = 0.0;
+        while term > sum * 1e-17 {
+            n += 1.0;
+            term *= 2.0 * x * x / (2.0 * n + 1.0);
+            sum += term;
+        }
+        1.0 - 2.0 / PI.sqrt() * (-x * x).exp() * sum
+    } else {
+        (-x * x).exp() * scaled_erfc(x)
+    }
+}
+
+/**
+ * exp(x^2) erfc(x) for x >= 3, which does not underflow for large x.
+ */
+fn scaled_erfc(x: f64) -> f64 {
+    // erfc(x) = exp(-x^2) / sqrt(pi) * 1 / (x + (1/2) / (x + 1 / (x + (3/2) / (x + 2 / (x + ...)))))
+    // evaluated from the back, 60 levels are far more than needed for x >= 3
+    let mut fraction = x;
+    for k in (1..=60).rev() {
+        fraction = x + (k as f64 / 2.0) / fraction;
+    }
+    1.0 / PI.sqrt() / fraction
+}
+
+/**
+ * The logarithm of the probability that a standard normal variable is larger than z. Far out in
+ * the upper tail, the probability itself would underflow.
+ */
+pub fn log_normal_upper_tail(z: f64) -> f64 {
+    let x = z / SQRT_2;
+    if x < 3.0 {
+        (0.5 * erfc(x)).ln()
+    } else {
+        (0.5 * scaled_erfc(x)).ln() - x * x
+    }
+}
+
+/**
+ * The logarithm of the probability that a standard normal variable lies between a and b (a < b).
+ * Intervals within a tail are computed from that tail, so that they keep their relative precision.
+ */
+pub fn log_normal_interval(a: f64, b: f64) -> f64 {
+    let log_difference = |upper: f64, lower: f64| {
+        let log_upper = log_normal_upper_tail(upper);
+        log_upper + (-(log_normal_upper_tail(lower) - log_upper).exp()).ln_1p()
+    };
+
+    if a >= 0.0 {
+        log_difference(a, b)
+    } else if b <= 0.0 {
+        log_difference(-b, -a)
+    } else {
+        (1.0 - 0.5 * erfc(-a / SQRT_2) - 0.5 * erfc(b / SQRT_2)).ln()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, relative_tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= relative_tolerance * expected.abs(),
+            "expected {}, got {}", expected, actual
+        );
+    }
+
+    #[test]
+    fn hurwitz_zeta_matches_known_values() {
+        assert_close(log_hurwitz_zeta(2.0, 1.0).exp(), PI * PI / 6.0, 1e-14);
+        assert_close(log_hurwitz_zeta(2.0, 2.0).exp(), PI * PI / 6.0 - 1.0, 1e-14);
+        assert_close(log_hurwitz_zeta(3.0, 1.0).exp(), 1.202_056_903_159_594_3, 1e-14);
+        assert_close(log_hurwitz_zeta(4.0, 1.0).exp(), PI.powi(4) / 90.0, 1e-14);
+        // the sum is dominated by its first term without underflowing
+        assert_close(log_hurwitz_zeta(500.0, 3.0), -500.0 * 3f64.ln(), 1e-14);
+    }
+
+    #[test]
+    fn erfc_matches_known_values() {
+        assert_eq!(erfc(0.0), 1.0);
+        assert_close(erfc(1.0), 0.157_299_207_050_285_13, 1e-14);
+        assert_close(erfc(-1.0), 1.842_700_792_949_715, 1e-14);
+        assert_close(erfc(5.0), 1.537_459_794_428_034_8e-12, 1e-12);
+    }
+
+    #[test]
+    fn normal_tails_keep_their_precision() {
+        assert_close(log_normal_upper_tail(0.0), 0.5f64.ln(), 1e-15);
+        // P(Z > 40) is about 3.66e-350, far below the smallest double
+        assert_close(log_normal_upper_tail(40.0), -804.608_442_013_753_8, 1e-12);
+        assert_close(log_normal_interval(-1.0, 1.0).exp(), 0.682_689_492_137_085_9, 1e-13);
+    }
+}
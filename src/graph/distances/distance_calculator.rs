@@ -0,0 +1,250 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{DistanceParameters, OutputPaths};
+use crate::common::random::Random;
+use crate::graph::betweenness::brandes_memory::BrandesMemory;
+use crate::graph::common::graph::{Graph, NodeIndex};
+use crate::graph::common::source_runner::{SourceRunner, SourceVisitor};
+
+/**
+ * The number of (source, target) pairs per hop distance (index 0 stays empty) and the eccentricity
+ * and number of reached nodes of every source visited so far.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct DistanceState {
+    pair_counts: Vec<u64>,
+    eccentricities: Vec<(NodeIndex, u32, u32)>,
+}
+
+pub struct DistanceCalculator {
+    graph: Graph,
+    intermediate_folder_path: PathBuf,
+    params: DistanceParameters,
+}
+
+impl DistanceCalculator {
+    pub fn new(graph: Graph, intermediate_folder_path: &Path, params: DistanceParameters) -> DistanceCalculator {
+        DistanceCalculator {
+            graph,
+            intermediate_folder_path: intermediate_folder_path.to_path_buf(),
+            params,
+        }
+    }
+
+    /**
+     * Runs one BFS per source node (the same BFS as the Brandes algorithm) and writes
+     *     - the number and fraction of node pairs per hop distance to distance_distribution.csv
+     *     - the average shortest path length, the effective diameter and bounds on the diameter to
+     *       distance_summary.csv
+     *     - the eccentricity of every source node to eccentricity.csv
+     * Only pairs where the target is reachable from the source count. With sampling, the
+     * distribution and the values derived from it are estimates from the sampled sources.
+     */
+    pub fn calculate_and_persist(&self, output_paths: &OutputPaths) {
+        let candidate_count = self.candidate_sources().len();
+        let sources = self.sources();
+
+        let directory_name = if self.graph.is_undirected() { "distances_undirected" } else { "distances" };
+        let runner = SourceRunner::new(
+            &self.intermediate_folder_path.join(directory_name),
+            self.params.save_intermediate_results_periodically,
+            self.params.result_batch_size,
+            self.params.max_thread_count,
+        );
+        let mut partial_results = runner.run(self, self.graph.content_hash(), &sources).into_iter();
+        let mut result = partial_results.next().unwrap_or_else(|| self.fresh_state());
+        for partial_result in partial_results {
+            Self::merge(&mut result, partial_result);
+        }
+        result.eccentricities.sort_unstable();
+
+        self.write_distribution(output_paths, &result.pair_counts);
+        self.write_summary(output_paths, &result, sources.len(), candidate_count);
+        self.write_eccentricities(output_paths, &result.eccentricities);
+    }
+
+    /**
+     * Nodes without outgoing edges do not reach any other node, so they are never used as sources.
+     */
+    fn candidate_sources(&self) -> Vec<NodeIndex> {
+        self.graph.indices()
+            .filter(|&s| self.graph.out_degree(s) > 0)
+            .collect()
+    }
+
+    fn sources(&self) -> Vec<NodeIndex> {
+        let candidates = self.candidate_sources();
+        let sample_size = self.params.sample_size as usize;
+        if sample_size == 0 || sample_size >= candidates.len() {
+            info!("Calculating DISTANCES from all {} source nodes", candidates.len());
+            return candidates;
+        }
+
+        info!(
+            "Calculating DISTANCES from {} of {} source nodes sampled with seed {}",
+            sample_size, candidates.len(), self.params.seed
+        );
+        Random::new(self.params.seed).sample_nodes(&candidates, sample_size)
+    }
+
+    fn merge(result: &mut DistanceState, other: DistanceState) {
+        if result.pair_counts.len() < other.pair_counts.len() {
+            result.pair_counts.resize(other.pair_counts.len(), 0);
+        }
+        for (value, other_value) in result.pair_counts.iter_mut().zip(other.pair_counts) {
+            *value += other_value;
+        }
+        result.eccentricities.extend(other.eccentricities);
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn write_distribution(&self, output_paths: &OutputPaths, pair_counts: &[u64]) {
+        let path = &output_paths.distance_distribution;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing distances at {}", path.to_str().unwrap()));
+        writer.serialize(("distance", "pairs", "fraction", "cdf")).unwrap();
+
+        let pair_count: u64 = pair_counts.iter().sum();
+        let mut cumulative = 0;
+        for (distance, &count) in pair_counts.iter().enumerate().skip(1) {
+            cumulative += count;
+            let fraction = count as f64 / pair_count as f64;
+            writer.serialize((distance, count, fraction, cumulative as f64 / pair_count as f64)).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    #[allow(clippy::expect_fun_call)]
+    fn write_summary(&self, output_paths: &OutputPaths, result: &DistanceState, sources: usize, candidates: usize) {
+        let pair_counts = &result.pair_counts;
+        let pair_count: u64 = pair_counts.iter().sum();
+        let distance_sum: u64 = pair_counts.iter().enumerate().map(|(distance, &count)| distance as u64 * count).sum();
+        let average_distance = distance_sum as f64 / pair_count as f64;
+        let percentile = self.params.effective_diameter_percentile;
+        let effective_diameter = effective_diameter(pair_counts, percentile);
+        let max_distance = pair_counts.len().saturating_sub(1) as u32;
+        let lower_bound = max_distance.max(self.double_sweep_bound());
+
+        info!(
+            "Average shortest path length {:.4} over {} reachable pairs, effective diameter ({}) {:.4}",
+            average_distance, pair_count, percentile, effective_diameter
+        );
+        info!("Longest distance from the sources {}, lower bound on the diameter {}", max_distance, lower_bound);
+
+        let path = &output_paths.distance_summary;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing distances at {}", path.to_str().unwrap()));
+        writer.serialize((
+            "sources", "candidate_sources", "reachable_pairs", "average_distance", "effective_diameter_percentile",
+            "effective_diameter", "max_distance", "diameter_lower_bound",
+        )).unwrap();
+        writer.serialize((
+            sources, candidates, pair_count, average_distance, percentile,
+            effective_diameter, max_distance, lower_bound,
+        )).unwrap();
+        writer.flush().unwrap();
+    }
+
+    /**
+     * The eccentricity of a source is its largest distance to a node it reaches, i.e. other
+     * components are ignored. Sources that were not visited are left out.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn write_eccentricities(&self, output_paths: &OutputPaths, eccentricities: &[(NodeIndex, u32, u32)]) {
+        let path = &output_paths.eccentricity;
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create file for storing eccentricities at {}", path.to_str().unwrap()));
+        writer.serialize(("node_id", "eccentricity", "reachable_nodes")).unwrap();
+        for &(s, eccentricity, reached) in eccentricities {
+            writer.serialize((self.graph.node_id(s), eccentricity, reached)).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    /**
+     * Double sweeps from the diameter_sweeps nodes with the highest out degree: a BFS from the start
+     * node finds the farthest node from it, whose eccentricity (from a second BFS) is a lower bound
+     * on the diameter. It is usually close to the diameter, at the cost of only two BFS runs.
+     */
+    fn double_sweep_bound(&self) -> u32 {
+        let mut starts = self.candidate_sources();
+        starts.sort_by_key(|&s| std::cmp::Reverse(self.graph.out_degree(s)));
+        starts.truncate(self.params.diameter_sweeps as usize);
+
+        let mut memory = BrandesMemory::new(self.graph.node_count());
+        let mut sweep = |s: NodeIndex| {
+            memory.search_from(&self.graph, s);
+            let farthest = *memory.s_stack.last().unwrap();
+            let eccentricity = memory.d[farthest as usize] as u32;
+            memory.reset();
+            (farthest, eccentricity)
+        };
+
+        let mut bound = 0;
+        for start in starts {
+            let (farthest, start_eccentricity) = sweep(start);
+            let (_, eccentricity) = sweep(farthest);
+            bound = bound.max(start_eccentricity).max(eccentricity);
+        }
+        bound
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
+
+/**
+ * The smallest distance within which the given fraction of the reachable pairs lies, linearly
+ * interpolated between the integer distances (as in SNAP).
+ */
+fn effective_diameter(pair_counts: &[u64], percentile: f64) -> f64 {
+    let pair_count: u64 = pair_counts.iter().sum();
+    let mut previous_fraction = 0.0;
+    let mut cumulative = 0;
+    for (distance, &count) in pair_counts.iter().enumerate().skip(1) {
+        cumulative += count;
+        let fraction = cumulative as f64 / pair_count as f64;
+        if fraction >= percentile && count > 0 {
+            return (distance - 1) as f64 + (percentile - previous_fraction) / (fraction - previous_fraction);
+        }
+        previous_fraction = fraction;
+    }
+    f64::NAN
+}
+
+impl SourceVisitor for DistanceCalculator {
+    type State = DistanceState;
+    type Memory = BrandesMemory;
+
+    fn fresh_state(&self) -> DistanceState {
+        DistanceState {
+            pair_counts: Vec::new(),
+            eccentricities: Vec::new(),
+        }
+    }
+
+    fn new_memory(&self) -> BrandesMemory {
+        BrandesMemory::new(self.graph.node_count())
+    }
+
+    fn visit(&self, memory: &mut BrandesMemory, state: &mut DistanceState, s: NodeIndex) {
+        memory.search_from(&self.graph, s);
+
+        // the stack holds the reached nodes by distance, so the last one is the farthest
+        let eccentricity = memory.d[*memory.s_stack.last().unwrap() as usize] as usize;
+        if state.pair_counts.len() <= eccentricity {
+            state.pair_counts.resize(eccentricity + 1, 0);
+        }
+        for &w in &memory.s_stack[1..] {
+            state.pair_counts[memory.d[w as usize] as usize] += 1;
+        }
+        let reached = memory.s_stack.len() as u32 - 1;
+        state.eccentricities.push((s, eccentricity as u32, reached));
+
+        memory.reset();
+    }
+}
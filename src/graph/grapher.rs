@@ -1,8 +1,20 @@
+use std::path::Path;
+
 use log::info;
+use crate::graph::assortativity::assortativity_calculator::AssortativityCalculator;
 use crate::graph::betweenness::brandes_calculator::BrandesCalculator;
+use crate::graph::closeness::closeness_calculator::ClosenessCalculator;
+use crate::graph::clustering::triangle_counter::TriangleCounter;
 
 use crate::graph::common::graph::Graph;
+use crate::graph::common::graph_snapshot::GraphSnapshot;
+use crate::graph::communities::community_detector::CommunityDetector;
+use crate::graph::components::component_counter::ComponentCounter;
+use crate::graph::cores::core_counter::CoreCounter;
 use crate::graph::degree::degree_counter::DegreeCounter;
+use crate::graph::degree::degree_distribution::DegreeDistribution;
+use crate::graph::distances::distance_calculator::DistanceCalculator;
+use crate::graph::spectral::spectral_calculator::SpectralCalculator;
 use crate::{DatasetConfig, GraphParametersToCompute, OutputPaths};
 
 pub struct Grapher {
@@ -17,9 +29,15 @@ impl Grapher {
         output_paths: &OutputPaths,
         parameters: &GraphParametersToCompute
     ) -> Grapher {
+        let output_paths = if parameters.undirected {
+            output_paths.for_undirected_graph()
+        } else {
+            output_paths.clone()
+        };
+
         Grapher {
             config: config.clone(),
-            output_paths: output_paths.clone(),
+            output_paths,
             parameters: parameters.clone(),
         }
     }
@@ -39,59 +57,304 @@ impl Grapher {
         info!("Building in-memory graph for calculating graph values. This may take a while \
         but only has to be done once per run.");
 
-        Graph::new(&self.output_paths, /* from_deduplicated = */ true)
+        if self.parameters.undirected {
+            info!("Graph is treated as UNDIRECTED - all statistics are computed on the symmetrised graph.");
+        }
+
+        let undirected = self.parameters.undirected;
+        let build = || Graph::new(&self.output_paths, /* from_deduplicated = */ true, undirected);
+
+        if self.parameters.snapshot {
+            GraphSnapshot::new(&self.config.intermediate_path, undirected)
+                .load_or_build(&self.output_paths.edges_deduplicated, undirected, build)
+        } else {
+            build()
+        }
     }
 
     fn calculate_graph_parameters(&self, graph: Graph) {
         let mut graph: Graph = graph;
 
+        if self.parameters.components.enabled {
+            graph = self.calculate_components(graph);
+        }
+        if self.parameters.restricted_to_giant_component() {
+            self.for_giant_component().calculate_statistics(graph);
+        } else {
+            self.calculate_statistics(graph);
+        }
+    }
+
+    /**
+     * Returns a grapher for the statistics of the giant component. Its output files are labelled
+     * with _giant (e.g. degree_giant.csv) and its checkpoints are kept in a separate intermediate
+     * folder, so that neither is mixed up with the ones of the whole graph.
+     */
+    fn for_giant_component(&self) -> Grapher {
+        let mut config = self.config.clone();
+        config.intermediate_path = self.config.intermediate_path.join("giant_component");
+
+        Grapher {
+            config,
+            output_paths: self.output_paths.for_giant_component(),
+            parameters: self.parameters.clone(),
+        }
+    }
+
+    /**
+     * Calculates all enabled statistics except for the components.
+     */
+    fn calculate_statistics(&self, graph: Graph) {
+        let mut graph: Graph = graph;
+
         if self.parameters.degree.enabled {
-            graph = self.calculate_degree(graph);
+            graph = self.calculate_degree(graph, &self.output_paths.degree, "node_id");
+        }
+        if self.parameters.degree.distribution {
+            graph = self.calculate_degree_distribution(graph);
+        }
+        if self.parameters.cores.enabled {
+            graph = self.calculate_cores(graph);
+        }
+        if self.parameters.assortativity.enabled {
+            graph = self.calculate_assortativity(graph);
         }
         if self.parameters.betweenness.enabled {
-            self.calculate_betweenness(graph);
+            let edge_betweenness_path = Some(self.output_paths.edge_betweenness.as_path())
+                .filter(|_| self.parameters.betweenness.edges);
+            graph = self.calculate_betweenness(
+                graph,
+                &self.config.intermediate_path,
+                &self.output_paths.betweenness,
+                edge_betweenness_path,
+                "node_id"
+            );
+        }
+        if self.parameters.closeness.enabled {
+            graph = self.calculate_closeness(graph);
+        }
+        if self.parameters.distances.enabled {
+            graph = self.calculate_distances(graph);
+        }
+        if self.parameters.communities.louvain || self.parameters.communities.label_propagation {
+            graph = self.detect_communities(graph);
+        }
+        let spectral = &self.parameters.spectral;
+        if spectral.pagerank || spectral.eigenvector || spectral.katz {
+            graph = self.calculate_spectral_centralities(graph);
+        }
+        if self.parameters.clustering.enabled {
+            self.calculate_clustering(graph);
         }
     }
 
     /**
-     * Calculates the degree statistics and writes them to the degree CSV file (degree.csv)
+     * Calculates the degree statistics and writes them to the given degree CSV file (e.g. degree.csv)
      */
-    fn calculate_degree(&self, graph: Graph) -> Graph {
+    #[allow(clippy::expect_fun_call)]
+    pub fn calculate_degree(&self, graph: Graph, degree_path: &Path, node_column: &'static str) -> Graph {
         info!("Calculating IN and OUT degree");
 
-        let degree_writer = csv::Writer::from_path(&self.output_paths.degree)
+        let degree_writer = csv::Writer::from_path(degree_path)
             .expect(&format!(
                 "Could not create file for storing degree at {}",
-                &self.output_paths.degree.to_str().unwrap()
+                degree_path.to_str().unwrap()
             ));
 
-        let mut calculator = DegreeCounter::new(graph, degree_writer);
+        let mut calculator = DegreeCounter::new(graph, degree_writer).with_node_column(node_column);
         calculator.calculate_and_persist();
         calculator.graph()
     }
 
     /**
-     * Calculates the degree statistics and writes them to the betweenness CSV file (betweenness.csv)
+     * Writes the degree histograms and CCDFs to degree_distribution.csv and the power law fits to degree_fit.csv.
+     */
+    fn calculate_degree_distribution(&self, graph: Graph) -> Graph {
+        let distribution = DegreeDistribution::new(graph, self.parameters.degree.clone());
+        distribution.calculate_and_persist(&self.output_paths);
+        distribution.graph()
+    }
+
+    /**
+     * Calculates the core numbers and writes them to coreness.csv, the core sizes to core_sizes.csv
+     * and, if requested, the edges of the chosen k-core to k_core_edges.csv.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn calculate_cores(&self, graph: Graph) -> Graph {
+        let coreness_writer = csv::Writer::from_path(&self.output_paths.coreness)
+            .expect(&format!(
+                "Could not create file for storing core numbers at {}",
+                self.output_paths.coreness.to_str().unwrap()
+            ));
+
+        let mut counter = CoreCounter::new(graph, self.parameters.cores.clone(), coreness_writer);
+        counter.calculate_and_persist(&self.output_paths);
+        counter.graph()
+    }
+
+    /**
+     * Calculates the degree assortativity and writes it to assortativity.csv and, if enabled, the
+     * rich-club coefficients to rich_club.csv.
+     */
+    fn calculate_assortativity(&self, graph: Graph) -> Graph {
+        let calculator = AssortativityCalculator::new(graph, self.parameters.assortativity.clone());
+        calculator.calculate_and_persist(&self.output_paths);
+        calculator.graph()
+    }
+
+    /**
+     * Calculates the betweenness centrality and writes it to the given betweenness CSV file
+     * (e.g. betweenness.csv). If an edge betweenness path is given, the edge betweenness is
+     * written there as well. The checkpoints are stored below the given intermediate path.
      *
      * IMPORTANT: If intermediate files are present, it will read them in and continue computation
      * from there. If you want to start a new calculation, DELETE THE INTERMEDIATE FILES!
      */
-    fn calculate_betweenness(&self, graph: Graph) -> Graph {
+    #[allow(clippy::expect_fun_call)]
+    pub fn calculate_betweenness(
+        &self,
+        graph: Graph,
+        intermediate_path: &Path,
+        betweenness_path: &Path,
+        edge_betweenness_path: Option<&Path>,
+        node_column: &'static str,
+    ) -> Graph {
         info!("Calculating BETWEENNESS CENTRALITY using BRANDES algorithm");
 
-        let betweenness_writer = csv::Writer::from_path(&self.output_paths.betweenness)
+        let betweenness_writer = csv::Writer::from_path(betweenness_path)
             .expect(&format!(
                 "Could not create file for storing betweenness at {}",
-                &self.output_paths.betweenness.to_str().unwrap()
+                betweenness_path.to_str().unwrap()
             ));
 
         let mut calculator = BrandesCalculator::new(
             graph,
-            &self.config.intermediate_path,
+            intermediate_path,
             self.parameters.betweenness.clone(),
             betweenness_writer
+        ).with_node_column(node_column);
+        if let Some(edge_betweenness_path) = edge_betweenness_path {
+            let edge_writer = csv::Writer::from_path(edge_betweenness_path)
+                .expect(&format!(
+                    "Could not create file for storing edge betweenness at {}",
+                    edge_betweenness_path.to_str().unwrap()
+                ));
+            calculator = calculator.with_edge_writer(edge_writer);
+        }
+        calculator.calculate_and_persist();
+        calculator.graph()
+    }
+
+    /**
+     * Calculates the closeness and harmonic centrality and writes them to closeness.csv.
+     *
+     * IMPORTANT: As for the betweenness, present intermediate files are read in and the
+     * computation continues from there.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn calculate_closeness(&self, graph: Graph) -> Graph {
+        info!("Calculating CLOSENESS and HARMONIC CENTRALITY");
+
+        let closeness_writer = csv::Writer::from_path(&self.output_paths.closeness)
+            .expect(&format!(
+                "Could not create file for storing closeness at {}",
+                self.output_paths.closeness.to_str().unwrap()
+            ));
+
+        let mut calculator = ClosenessCalculator::new(
+            graph,
+            &self.config.intermediate_path,
+            self.parameters.closeness.clone(),
+            closeness_writer
         );
         calculator.calculate_and_persist();
         calculator.graph()
     }
+
+    /**
+     * Calculates the distance distribution, average shortest path length, effective diameter and a
+     * lower bound on the diameter, and writes them to distance_distribution.csv and
+     * distance_summary.csv, together with the eccentricities of the source nodes (eccentricity.csv).
+     *
+     * IMPORTANT: As for the betweenness, present intermediate files are read in and the
+     * computation continues from there.
+     */
+    fn calculate_distances(&self, graph: Graph) -> Graph {
+        info!("Calculating DISTANCE statistics");
+        let calculator = DistanceCalculator::new(
+            graph,
+            &self.config.intermediate_path,
+            self.parameters.distances.clone()
+        );
+        calculator.calculate_and_persist(&self.output_paths);
+        calculator.graph()
+    }
+
+    /**
+     * Detects communities with the enabled algorithms (Louvain, label propagation) and writes them
+     * to communities.csv, community_sizes.csv and community_summary.csv.
+     */
+    fn detect_communities(&self, graph: Graph) -> Graph {
+        let detector = CommunityDetector::new(graph, self.parameters.communities.clone());
+        detector.calculate_and_persist(&self.output_paths);
+        detector.graph()
+    }
+
+    /**
+     * Calculates the enabled spectral centralities (PageRank, eigenvector, Katz) and writes them to spectral.csv.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn calculate_spectral_centralities(&self, graph: Graph) -> Graph {
+        let spectral_writer = csv::Writer::from_path(&self.output_paths.spectral)
+            .expect(&format!(
+                "Could not create file for storing spectral centralities at {}",
+                self.output_paths.spectral.to_str().unwrap()
+            ));
+
+        let mut calculator = SpectralCalculator::new(graph, self.parameters.spectral.clone(), spectral_writer);
+        calculator.calculate_and_persist();
+        calculator.graph()
+    }
+
+    /**
+     * Counts the triangles and writes the clustering coefficients to clustering.csv and the graph-wide
+     * values to clustering_summary.csv.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn calculate_clustering(&self, graph: Graph) -> Graph {
+        let clustering_writer = csv::Writer::from_path(&self.output_paths.clustering)
+            .expect(&format!(
+                "Could not create file for storing clustering at {}",
+                self.output_paths.clustering.to_str().unwrap()
+            ));
+
+        let mut counter = TriangleCounter::new(graph, self.parameters.clustering.clone(), clustering_writer);
+        counter.calculate_and_persist(&self.output_paths.clustering_summary);
+        counter.graph()
+    }
+
+    /**
+     * Finds the connected components and writes them to components.csv, component_sizes.csv and
+     * giant_component_edges.csv. If requested, returns the giant component only, so that all
+     * following statistics are computed on it.
+     */
+    #[allow(clippy::expect_fun_call)]
+    fn calculate_components(&self, graph: Graph) -> Graph {
+        let components_writer = csv::Writer::from_path(&self.output_paths.components)
+            .expect(&format!(
+                "Could not create file for storing components at {}",
+                self.output_paths.components.to_str().unwrap()
+            ));
+
+        let mut counter = ComponentCounter::new(graph, self.parameters.components.clone(), components_writer);
+        let giant_component = counter.calculate_and_persist(&self.output_paths);
+        let graph = counter.graph();
+
+        if self.parameters.components.restrict_to_giant_component {
+            info!("Restricting all further statistics to the giant component (output files suffixed with _giant)");
+            graph.subgraph(&giant_component)
+        } else {
+            graph
+        }
+    }
 }
@@ -0,0 +1,2 @@
+pub mod spectral_calculator;
+mod power_iteration;
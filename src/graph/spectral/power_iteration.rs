@@ -0,0 +1,57 @@
+use log::{info, warn};
+
+/**
+ * Repeats an iteration step until the values converge, i.e. until the summed absolute change of
+ * all values (the residual) drops below node count * tolerance, or the iteration cap is reached.
+ */
+pub struct PowerIteration {
+    name: &'static str,
+    tolerance: f64,
+    max_iterations: u32,
+}
+
+impl PowerIteration {
+    pub fn new(name: &'static str, tolerance: f64, max_iterations: u32) -> PowerIteration {
+        PowerIteration {
+            name,
+            tolerance,
+            max_iterations,
+        }
+    }
+
+    /**
+     * Runs the iteration from the initial values. The step calculates the next values from the
+     * current ones. If the values do not converge, the last values are returned nonetheless.
+     */
+    pub fn run<F>(&self, initial: Vec<f64>, step: F) -> Vec<f64>
+        where F: Fn(&[f64]) -> Vec<f64> {
+        let threshold = initial.len() as f64 * self.tolerance;
+        let mut values = initial;
+        let mut residual = f64::INFINITY;
+
+        for iteration in 1..=self.max_iterations {
+            let next_values = step(&values);
+            residual = next_values.iter()
+                .zip(&values)
+                .map(|(next_value, value)| (next_value - value).abs())
+                .sum();
+            values = next_values;
+
+            info!("{}: iteration {}, residual {:e}", self.name, iteration, residual);
+            if residual < threshold {
+                info!("{}: converged after {} iterations", self.name, iteration);
+                return values;
+            }
+            if !residual.is_finite() {
+                warn!("{}: values diverged after {} iterations", self.name, iteration);
+                return values;
+            }
+        }
+
+        warn!(
+            "{}: did not converge within {} iterations (residual {:e}), writing the values of the last iteration",
+            self.name, self.max_iterations, residual
+        );
+        values
+    }
+}
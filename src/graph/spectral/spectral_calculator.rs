@@ -0,0 +1,168 @@
+use std::fs::File;
+
+use csv::Writer;
+use log::info;
+use rayon::prelude::*;
+
+use crate::{DanglingNodeRule, SpectralParameters};
+use crate::graph::common::graph::Graph;
+use crate::graph::spectral::power_iteration::PowerIteration;
+
+/**
+ * Calculates PageRank, eigenvector and Katz centrality by power iteration. All three follow the
+ * conventions of networkx, i.e. a node is central if it is pointed to by central nodes, so the
+ * values of a node are pulled from its predecessors. Every iteration is parallelized over the nodes.
+ */
+pub struct SpectralCalculator {
+    graph: Graph,
+    params: SpectralParameters,
+    writer: Writer<File>,
+}
+
+impl SpectralCalculator {
+    pub fn new(graph: Graph, params: SpectralParameters, writer: Writer<File>) -> SpectralCalculator {
+        SpectralCalculator {
+            graph,
+            params,
+            writer,
+        }
+    }
+
+    /**
+     * Calculates the enabled centralities and writes them to one CSV file with a column per centrality.
+     */
+    pub fn calculate_and_persist(&mut self) {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.params.max_thread_count as usize)
+            .build()
+            .expect("Could not create thread pool for the spectral centralities");
+
+        let mut columns: Vec<(&str, Vec<f64>)> = Vec::new();
+        if self.params.pagerank {
+            columns.push(("pagerank", thread_pool.install(|| self.pagerank())));
+        }
+        if self.params.eigenvector {
+            columns.push(("eigenvector", thread_pool.install(|| self.eigenvector())));
+        }
+        if self.params.katz {
+            columns.push(("katz", thread_pool.install(|| self.katz())));
+        }
+
+        let mut header = vec!["node_id"];
+        header.extend(columns.iter().map(|(name, _)| *name));
+        self.writer.write_record(&header).unwrap();
+
+        for v in self.graph.indices() {
+            let mut record = vec![self.graph.node_id(v).to_string()];
+            record.extend(columns.iter().map(|(_, values)| values[v as usize].to_string()));
+            self.writer.write_record(&record).unwrap();
+        }
+    }
+
+    /**
+     * Every node passes its rank on evenly to its successors. The rank of nodes without successors
+     * (dangling nodes) is either distributed evenly among all nodes or dropped, in which case the
+     * final ranks are normalized to sum up to 1.
+     */
+    fn pagerank(&self) -> Vec<f64> {
+        info!("Calculating PAGERANK with damping factor {}", self.params.damping_factor);
+
+        let graph = &self.graph;
+        let node_count = graph.node_count() as f64;
+        let damping_factor = self.params.damping_factor;
+        let iteration = PowerIteration::new("PageRank", self.params.tolerance, self.params.max_iterations);
+
+        let mut ranks = iteration.run(vec![1.0 / node_count; graph.node_count()], |ranks| {
+            let dangling_rank: f64 = match self.params.dangling_nodes {
+                DanglingNodeRule::Uniform => graph.indices()
+                    .filter(|&v| graph.out_degree(v) == 0)
+                    .map(|v| ranks[v as usize])
+                    .sum(),
+                DanglingNodeRule::Drop => 0.0,
+            };
+            let base_rank = (1.0 - damping_factor) / node_count + damping_factor * dangling_rank / node_count;
+
+            graph.indices()
+                .into_par_iter()
+                .map(|v| {
+                    let incoming_rank: f64 = graph.reverse_neighbors(v).iter()
+                        .map(|&u| ranks[u as usize] / graph.out_degree(u) as f64)
+                        .sum();
+                    base_rank + damping_factor * incoming_rank
+                })
+                .collect()
+        });
+
+        if let DanglingNodeRule::Drop = self.params.dangling_nodes {
+            let rank_sum = ranks.iter().sum();
+            normalize(&mut ranks, rank_sum);
+        }
+        ranks
+    }
+
+    /**
+     * Iterates x = (A + I)^T x, normalized to unit length. The identity shift does not change the
+     * eigenvector but prevents the iteration from oscillating on bipartite structures.
+     */
+    fn eigenvector(&self) -> Vec<f64> {
+        info!("Calculating EIGENVECTOR CENTRALITY");
+
+        let graph = &self.graph;
+        let iteration = PowerIteration::new("Eigenvector", self.params.tolerance, self.params.max_iterations);
+
+        iteration.run(vec![1.0 / graph.node_count() as f64; graph.node_count()], |values| {
+            let mut next_values: Vec<f64> = graph.indices()
+                .into_par_iter()
+                .map(|v| {
+                    let incoming_value: f64 = graph.reverse_neighbors(v).iter()
+                        .map(|&u| values[u as usize])
+                        .sum();
+                    values[v as usize] + incoming_value
+                })
+                .collect();
+
+            let norm = next_values.iter().map(|value| value * value).sum::<f64>().sqrt();
+            normalize(&mut next_values, norm);
+            next_values
+        })
+    }
+
+    /**
+     * Iterates x = alpha * A^T x + beta and normalizes the result to unit length. The iteration only
+     * converges if alpha is smaller than the reciprocal of the largest eigenvalue of the graph.
+     */
+    fn katz(&self) -> Vec<f64> {
+        info!("Calculating KATZ CENTRALITY with alpha {} and beta {}", self.params.katz_alpha, self.params.katz_beta);
+
+        let graph = &self.graph;
+        let alpha = self.params.katz_alpha;
+        let beta = self.params.katz_beta;
+        let iteration = PowerIteration::new("Katz", self.params.tolerance, self.params.max_iterations);
+
+        let mut values = iteration.run(vec![0.0; graph.node_count()], |values| {
+            graph.indices()
+                .into_par_iter()
+                .map(|v| {
+                    let incoming_value: f64 = graph.reverse_neighbors(v).iter()
+                        .map(|&u| values[u as usize])
+                        .sum();
+                    alpha * incoming_value + beta
+                })
+                .collect()
+        });
+
+        let norm = values.iter().map(|value| value * value).sum::<f64>().sqrt();
+        normalize(&mut values, norm);
+        values
+    }
+
+    pub fn graph(self) -> Graph {
+        self.graph
+    }
+}
+
+fn normalize(values: &mut [f64], norm: f64) {
+    if norm > 0.0 {
+        values.iter_mut().for_each(|value| *value /= norm);
+    }
+}
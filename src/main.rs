@@ -4,9 +4,16 @@ use std::fs;
 use std::process::exit;
 use env_logger::Env;
 use log::{error, info, LevelFilter};
-use crate::common::parameters::{BetweennessParameters, compute_output_paths, Config, DatasetConfig, FeatureToggle, GraphParametersToCompute, OutputPaths};
+use crate::common::parameters::{AsLevelParameters, AssortativityParameters, BetweennessMode, BetweennessParameters, ClosenessParameters, ClusteringParameters, CommunityParameters, ComponentKind, ComponentParameters, compute_output_paths, Config, CoreKind, CoreParameters, DanglingNodeRule, DatasetConfig, DeduplicationParameters, DegreeParameters, DistanceParameters, ExportFormat, ExportParameters, FeatureToggle, GeoParameters, GraphParametersToCompute, NodeIdParameters, OutputPaths, PrefixOutputPaths, PrefixParameters, SpectralParameters, UnknownNodeRule};
 use crate::common::structs::util::IpType;
+use crate::alias::alias_resolver::AliasResolver;
+use crate::buckets::bucket_manager::BucketFormat;
+use crate::annotate::as_annotator::AsAnnotator;
+use crate::annotate::geo_annotator::GeoAnnotator;
+use crate::contract::as_grapher::AsGrapher;
+use crate::contract::prefix_grapher::PrefixGrapher;
 use crate::deduplicator::deduplicator::Deduplicator;
+use crate::export::graph_exporter::GraphExporter;
 use crate::graph::grapher::Grapher;
 use crate::merge::merger::Merger;
 use crate::preprocess::warts_data_preprocessor::WartsDataPreprocessor;
@@ -18,6 +25,10 @@ mod preprocess;
 mod common;
 mod buckets;
 mod deduplicator;
+mod export;
+mod annotate;
+mod contract;
+mod alias;
 
 fn main() {
     let mut env_builder = env_logger::builder();
@@ -70,7 +81,7 @@ fn run_on_yarrp_scans(config: DatasetConfig, toggle: &FeatureToggle) {
     }
 
     if toggle.should_merge {
-        let merger = Merger::new(&config, &output_paths);
+        let merger = Merger::new(&config, BucketFormat::Yarrp, &output_paths, &toggle.node_ids);
         merger.merge_data();
     } else {
         info!("Merging flag is FALSE - skipping merging.");
@@ -88,13 +99,18 @@ fn run_on_warts_scans(config: DatasetConfig, toggle: &FeatureToggle) {
     let output_paths = compute_output_paths(&config);
 
     if toggle.should_preprocess {
-        let preprocessor = WartsDataPreprocessor::new(&config, &output_paths);
+        let preprocessor = WartsDataPreprocessor::new(&config);
         preprocessor.preprocess_files();
     } else {
         info!("Preprocessing flag is FALSE - skipping preprocessing.");
     }
 
-    info!("No merging step necessary for WARTS scans.");
+    if toggle.should_merge {
+        let merger = Merger::new(&config, BucketFormat::Warts, &output_paths, &toggle.node_ids);
+        merger.merge_data();
+    } else {
+        info!("Merging flag is FALSE - skipping merging.");
+    }
 
     run(config, toggle, output_paths);
 }
@@ -103,8 +119,15 @@ fn run_on_warts_scans(config: DatasetConfig, toggle: &FeatureToggle) {
  * Runs the common parts of the pipeline for both data sources.
  */
 fn run(config: DatasetConfig, toggle: &FeatureToggle, output_paths: OutputPaths) {
+    if toggle.should_resolve_aliases {
+        let resolver = AliasResolver::new(&config, &output_paths);
+        resolver.resolve_aliases();
+    } else {
+        info!("Alias resolution flag is FALSE - skipping alias resolution.");
+    }
+
     if toggle.should_deduplicate_edges {
-        let deduplicator = Deduplicator::new(&output_paths);
+        let deduplicator = Deduplicator::new(&config, &output_paths, &toggle.deduplication);
         deduplicator.deduplicate_edges();
     } else {
         info!("Deduplication flag is FALSE - skipping deduplication.");
@@ -117,5 +140,35 @@ fn run(config: DatasetConfig, toggle: &FeatureToggle, output_paths: OutputPaths)
         info!("Graph computation flag is FALSE - skipping graph computation.");
     }
 
+    if toggle.should_annotate {
+        let as_annotator = AsAnnotator::new(&config, &output_paths);
+        as_annotator.annotate();
+        let geo_annotator = GeoAnnotator::new(&config, &output_paths, &toggle.geo);
+        geo_annotator.annotate();
+    } else {
+        info!("Annotation flag is FALSE - skipping annotation.");
+    }
+
+    if toggle.should_contract_to_as_level {
+        let as_grapher = AsGrapher::new(&config, &output_paths, &toggle.as_level, &toggle.parameters);
+        as_grapher.collect_as_level_stats();
+    } else {
+        info!("AS contraction flag is FALSE - skipping AS-level graph.");
+    }
+
+    if toggle.should_aggregate_prefixes {
+        let prefix_grapher = PrefixGrapher::new(&config, &output_paths, &toggle.prefixes, &toggle.parameters);
+        prefix_grapher.collect_prefix_level_stats();
+    } else {
+        info!("Prefix aggregation flag is FALSE - skipping prefix-level graphs.");
+    }
+
+    if toggle.should_export_graph {
+        let exporter = GraphExporter::new(&output_paths, &toggle.export, &toggle.parameters);
+        exporter.export_graph();
+    } else {
+        info!("Graph export flag is FALSE - skipping graph export.");
+    }
+
     info!("############## Finished ##############");
 }
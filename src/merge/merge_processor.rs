@@ -2,18 +2,24 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::path::PathBuf;
 use csv::Writer;
-use crate::buckets::bucket::GraphBucket;
+use crate::buckets::bucket::{GraphBucket, HopList, PathKey};
 
 pub struct MergeProcessor<'a> {
     edge_writer: &'a mut Writer<File>,
+    id_mapping: Option<&'a [u64]>,
     missing_node_counter: i64,
     missing_node_memory: HashMap<i64, i64>,
 }
 
 impl<'a> MergeProcessor<'a> {
-    pub fn new(edge_writer: &'a mut Writer<File>) -> MergeProcessor {
+    /**
+     * The optional ID mapping (indexed by the ID assigned during preprocessing) is applied to all
+     * known nodes before writing them.
+     */
+    pub fn new(edge_writer: &'a mut Writer<File>, id_mapping: Option<&'a [u64]>) -> MergeProcessor<'a> {
         MergeProcessor {
             edge_writer,
+            id_mapping,
             missing_node_counter: -1,
             missing_node_memory: HashMap::new(),
         }
@@ -23,35 +29,63 @@ impl<'a> MergeProcessor<'a> {
      * Writes the path data from the bucket into the output edge list CSV.
      * For missing hops, a negative ID is assigned. The ID is pinned to the starting point -
      * for any edge A-B with a known A and an unknown B, the same negative ID is used for B.
+     *
+     * Every edge is written with the RTT of its target hop and the RTT delta to the previous hop.
+     * The delta is only written if both hops are known and consecutive.
+     *
+     * Every path starts at its source, which is the source IP (node 0) for YARRP and the source
+     * address of the traceroute for WARTS. Edges are only formed between the hops of one path.
+     *
+     * The paths are processed in order of their source and target ID and the hops in order of
+     * their hop count (then node ID), so that the unknown nodes are always numbered in the same way.
      */
     pub fn process_bucket(&mut self, files_to_process: Vec<PathBuf>) -> i64 {
         let merged_edge_map = self.merge_edge_maps(files_to_process);
 
-        for (_, mut edges) in merged_edge_map {
-            edges.sort_by_key(|&i| i.1);
+        let mut paths: Vec<((u64, u64, usize, u32), HopList)> = merged_edge_map.into_iter()
+            .map(|((file_index, (source, target, trace)), edges)| {
+                let mapped_edges: HopList = edges.into_iter()
+                    .map(|(node, hop, rtt)| (self.map_id(node), hop, rtt))
+                    .collect();
+                ((self.map_id(source), self.map_id(target), file_index, trace), mapped_edges)
+            })
+            .collect();
+        paths.sort_unstable_by_key(|&(key, _)| key);
 
-            let mut previous_node: i64 = 0; // 0 == source IP
+        for ((source, _, _, _), mut edges) in paths {
+            edges.sort_unstable_by_key(|&(node, hop, rtt)| (hop, node, rtt));
+
+            let mut previous_node = source as i64;
             let mut previous_hop = 0;
-            for (current_node, current_hop) in edges {
+            let mut previous_rtt: Option<u32> = Some(0); // the source has no delay to itself
+            for (current_node, current_hop, current_rtt) in edges {
                 if current_hop > previous_hop + 1 {
                     let missing_hops = (current_hop - 1) - (previous_hop + 1);
                     for _ in 0..missing_hops {
-                        if !self.missing_node_memory.contains_key(&previous_node) {
-                            self.missing_node_memory.insert(previous_node, self.missing_node_counter);
-                            self.missing_node_counter -= 1;
-                        }
-
-                        let new_node_id = *self.missing_node_memory.get(&previous_node).unwrap();
+                        let new_node_id = *self.missing_node_memory
+                            .entry(previous_node)
+                            .or_insert_with(|| {
+                                let id = self.missing_node_counter;
+                                self.missing_node_counter -= 1;
+                                id
+                            });
 
-                        self.edge_writer.serialize((previous_node, new_node_id)).unwrap();
-                        previous_node = i64::try_from(new_node_id).unwrap();
-                        previous_hop += 1;
+                        self.edge_writer.serialize((previous_node, new_node_id, None::<u32>, None::<i64>)).unwrap();
+                        previous_node = new_node_id;
+                        previous_rtt = None;
                     }
                 }
 
-                self.edge_writer.serialize((previous_node, current_node)).unwrap();
-                previous_node = i64::try_from(current_node).unwrap();
+                let rtt_delta = if current_hop == previous_hop + 1 {
+                    rtt_delta(previous_rtt, current_rtt)
+                } else {
+                    None
+                };
+
+                self.edge_writer.serialize((previous_node, current_node, current_rtt, rtt_delta)).unwrap();
+                previous_node = current_node as i64;
                 previous_hop = current_hop;
+                previous_rtt = current_rtt;
             }
         }
 
@@ -60,29 +94,107 @@ impl<'a> MergeProcessor<'a> {
     }
 
     /**
-     * Merges the requested bucket into paths across all input files.
+     * Merges the requested bucket into paths across all input files. Paths with trace number 0
+     * (YARRP) are joined across the files, all other paths are kept apart by their file index.
      */
-    fn merge_edge_maps(&self, files_to_process: Vec<PathBuf>) -> HashMap<u64, Vec<(u64, u8)>> {
-        let mut edge_map = HashMap::new();
+    fn merge_edge_maps(&self, files_to_process: Vec<PathBuf>) -> HashMap<(usize, PathKey), HopList> {
+        let mut edge_map: HashMap<(usize, PathKey), HopList> = HashMap::new();
 
-        for file in files_to_process {
+        for (file_index, file) in files_to_process.into_iter().enumerate() {
             let partial_map = GraphBucket::new(file).edge_map();
 
             for (key, value) in partial_map {
-                if !edge_map.contains_key(&key) {
-                    edge_map.insert(key, Vec::new());
-                }
-
-                let list = edge_map.get_mut(&key).unwrap();
-                list.extend(value);
+                let (_, _, trace) = key;
+                let file_key = if trace == 0 { 0 } else { file_index };
+                edge_map.entry((file_key, key)).or_default().extend(value);
             }
         }
 
         edge_map
     }
 
+    fn map_id(&self, node_id: u64) -> u64 {
+        match self.id_mapping {
+            Some(mapping) => mapping[node_id as usize],
+            None => node_id,
+        }
+    }
+
     pub fn max_unknown_node(&self) -> usize {
         // +1 because we decrement *after* every node assignment
         (self.missing_node_counter + 1).unsigned_abs() as usize
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Calculates the RTT difference between two hops, if both RTTs are known.
+ */
+pub fn rtt_delta(previous_rtt: Option<u32>, current_rtt: Option<u32>) -> Option<i64> {
+    match (previous_rtt, current_rtt) {
+        (Some(previous), Some(current)) => Some(current as i64 - previous as i64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+    use crate::common::structs::parse_data::InternalNode;
+
+    // (source, target, trace, hop, hop count)
+    type Hop = (u64, u64, u32, u64, u8);
+
+    fn write_bucket(path: PathBuf, hops: &[Hop]) {
+        let mut bucket = GraphBucket::new(path);
+        for &(source_id, target_id, trace, hop_id, hop_count) in hops {
+            bucket.add_node(InternalNode { source_id, target_id, trace, hop_id, hop_count, rtt: None });
+        }
+        bucket.evict_to_disk();
+    }
+
+    fn merge(name: &str, files: &[&[Hop]]) -> Vec<(i64, i64)> {
+        let directory = env::temp_dir().join(format!("merge_processor_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let paths: Vec<PathBuf> = files.iter().enumerate()
+            .map(|(index, hops)| {
+                let path = directory.join(format!("bucket_{}.bin", index));
+                write_bucket(path.clone(), hops);
+                path
+            })
+            .collect();
+
+        let edges_path = directory.join("edges.csv");
+        let mut writer = csv::Writer::from_path(&edges_path).unwrap();
+        MergeProcessor::new(&mut writer, None).process_bucket(paths);
+        drop(writer);
+
+        let mut reader = csv::ReaderBuilder::new().has_headers(false).from_path(&edges_path).unwrap();
+        let edges = reader.deserialize::<(i64, i64, Option<u32>, Option<i64>)>()
+            .map(|edge| edge.map(|(from, to, _, _)| (from, to)).unwrap())
+            .collect();
+        fs::remove_dir_all(directory).unwrap();
+        edges
+    }
+
+    #[test]
+    fn untraced_paths_towards_a_target_are_joined_across_files() {
+        // YARRP: two probes towards target 9, from the vantage point, in different files
+        let edges = merge("yarrp", &[&[(0, 9, 0, 1, 1)], &[(0, 9, 0, 2, 2)]]);
+
+        assert_eq!(edges, vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn traceroutes_are_never_joined() {
+        // WARTS: traceroutes from sources 5 and 6 towards target 9, the first one in both files. Joined,
+        // hop 3 of the second file would follow hop 2 of the first one
+        let first_file: &[Hop] = &[(5, 9, 1, 1, 1), (5, 9, 1, 2, 2), (6, 9, 2, 3, 1)];
+        let second_file: &[Hop] = &[(5, 9, 1, 4, 3)];
+        let edges = merge("warts", &[first_file, second_file]);
+
+        assert_eq!(edges, vec![(5, 1), (1, 2), (5, -1), (-1, 4), (6, 3)]);
+    }
+}
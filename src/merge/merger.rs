@@ -1,59 +1,72 @@
 use std::collections::HashMap;
 use std::fs;
 use std::fs::{DirEntry, File};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
 use csv::Writer;
 
 use log::info;
 use pbr::ProgressBar;
 
-use crate::{DatasetConfig, IpType, OutputPaths};
-use crate::common::parameters;
+use crate::{DatasetConfig, NodeIdParameters, OutputPaths};
+use crate::buckets::bucket_manager::BucketFormat;
 use crate::common::structs::parse_data::MaxNodeIds;
 use crate::merge::merge_processor::MergeProcessor;
+use crate::preprocess::{intermediate_store, node_registry};
 
 pub struct Merger {
     config: DatasetConfig,
+    format: BucketFormat,
     output_paths: OutputPaths,
+    node_id_params: NodeIdParameters,
 }
 
 impl Merger {
-    pub fn new(config: &DatasetConfig, output_paths: &OutputPaths) -> Merger {
+    pub fn new(
+        config: &DatasetConfig,
+        format: BucketFormat,
+        output_paths: &OutputPaths,
+        node_id_params: &NodeIdParameters,
+    ) -> Merger {
         Merger {
             config: config.clone(),
+            format,
             output_paths: output_paths.clone(),
+            node_id_params: node_id_params.clone(),
         }
     }
 
     /**
      * Merges the nodes that were collected into buckets into their path form.
-     * The paths are then generated as an edge list CSV (from,to).
+     * The paths are then generated as an edge list CSV (from,to,rtt,rtt_delta).
      * Additionally, the IP-to-NodeID mapping is also stored to a CSV (IP,ID).
-     * (-> see also warts_data_preprocessor.rs)
      *
-     *  Requires: Intermediate binary files at dataset.yarrp.intermediate_path
+     *  Requires: Intermediate binary files at dataset.<yarrp|warts>.intermediate_path
      * Generates:
      *     - edges.csv (edge list)
      *     - mapping.csv (IP to ID mapping)
      *     - max_node_ids.csv (maximum IDs assigned, both known and unknown)
-     * The edges and mapping are in no particular order.
+     * The mapping is sorted by node ID, the edges are in no particular order.
+     *
+     * If features.node_ids.canonical_order is set, the known nodes are renumbered by their sorted
+     * IP first. Since the buckets and paths are always processed in a fixed order, the unknown
+     * nodes then also receive canonical IDs and identical input yields identical output.
+     * With a node ID registry, registered IPs always get their registered ID, also when the merge
+     * step is rerun on the same intermediate files, and only the final IDs are written back.
      */
+    #[allow(clippy::expect_fun_call)]
     pub fn merge_data(self) {
-        info!("Step: Merge intermediate YARRP binary buckets.");
+        info!("Step: Merge intermediate binary buckets.");
         info!("Expecting to work with IP{:?} addresses.", self.config.address_type);
 
         info!("Creating empty output files...");
 
-        let index_path = self.config.intermediate_path.join(
-            Path::new(parameters::NODE_INDEX_FILENAME)
-        );
+        let index_path = intermediate_store::node_index_path(&self.config.intermediate_path);
 
         let node_mapping_output_path = &self.output_paths.mapping;
         let edge_output_path = &self.output_paths.edges;
         let max_node_id_path = &self.output_paths.max_node_ids;
 
-        let mut index_writer = csv::Writer::from_path(&node_mapping_output_path)
+        let mut index_writer = csv::Writer::from_path(node_mapping_output_path)
             .expect(&format!(
                 "Could not create file for storing node mapping at {}", node_mapping_output_path.to_str().unwrap()
             ));
@@ -61,17 +74,42 @@ impl Merger {
             .expect(&format!(
                 "Could not create file for storing edges at {}", edge_output_path.to_str().unwrap()
             ));
+        // The interface-level edges kept by a previous alias resolution are outdated now
+        if self.output_paths.edges_interface.exists() {
+            fs::remove_file(&self.output_paths.edges_interface).unwrap();
+        }
 
         let raw_files_list = fs::read_dir(&self.config.intermediate_path).unwrap();
-        let dirs_to_process: Vec<DirEntry> = raw_files_list
+        let mut dirs_to_process: Vec<DirEntry> = raw_files_list
             .map(|entry| entry.unwrap())
             .filter(|i| i.path().is_dir())
+            .filter(|i| intermediate_store::is_complete(&i.path()))
             .collect();
+        dirs_to_process.sort_by_key(|entry| entry.file_name());
 
         info!("Reading in intermediate files...");
 
-        let max_known_node_id = self.write_node_mapping(index_path, &mut index_writer);
-        let max_unknown_node_id = self.write_edge_mapping(dirs_to_process, &mut edge_writer);
+        let index = Self::read_node_index(index_path);
+        let registry = node_registry::load_configured_registry(&self.config);
+        let canonical_order = self.node_id_params.canonical_order;
+        let id_mapping = if canonical_order || !registry.is_empty() {
+            if canonical_order {
+                info!("Renumbering nodes in canonical order (sorted by IP)...");
+            }
+            Some(Self::final_id_mapping(&index, &registry, canonical_order))
+        } else {
+            None
+        };
+
+        let nodes = Self::apply_id_mapping(index, id_mapping.as_deref());
+        let max_known_node_id = self.write_node_mapping(&nodes, &mut index_writer);
+        if let Some(registry_path) = &self.config.id_registry_path {
+            let updated_registry = Self::update_registry(registry, &nodes);
+            node_registry::store_registry(registry_path, &updated_registry, &self.config.address_type);
+        }
+        drop(nodes);
+
+        let max_unknown_node_id = self.write_edge_mapping(dirs_to_process, id_mapping.as_deref(), &mut edge_writer);
 
         let mut max_node_ids_writer = csv::Writer::from_path(max_node_id_path)
             .expect(&format!(
@@ -85,39 +123,102 @@ impl Merger {
         max_node_ids_writer.serialize(max_node_ids).unwrap()
     }
 
-    fn write_node_mapping(&self, index_path: PathBuf, index_writer: &mut Writer<File>) -> usize {
+    #[allow(clippy::expect_fun_call)]
+    fn read_node_index(index_path: PathBuf) -> HashMap<u128, u64> {
         let index_file = File::open(&index_path).expect(&format!(
             "File at {} does not exist", index_path.to_str().unwrap()
         ));
-        let index: HashMap<u128, u64> = bincode::deserialize_from(index_file).expect(&format!(
+        bincode::deserialize_from(index_file).expect(&format!(
             "File at {} does not contain or contains invalid node index data",
             index_path.to_str().unwrap()
-        ));
+        ))
+    }
+
+    /**
+     * Calculates a mapping (preprocessing ID -> final ID), indexed by the preprocessing ID.
+     * Registered IPs keep their registered ID. This also holds when the merge step is rerun: the
+     * registry then already contains the final IDs of this run, while the buckets still carry the
+     * preprocessing IDs. The remaining nodes are numbered after the highest registered ID (0 is
+     * reserved for the source IP), by their sorted IP in canonical order or by their preprocessing ID.
+     */
+    fn final_id_mapping(index: &HashMap<u128, u64>, registry: &HashMap<u128, u64>, canonical_order: bool) -> Vec<u64> {
+        let max_node_id = index.values().max().copied().unwrap_or(0);
+        let mut id_mapping: Vec<u64> = (0..=max_node_id).collect();
 
+        let mut unregistered: Vec<(u128, u64)> = Vec::new();
+        for (&ip, &node_id) in index {
+            match registry.get(&ip) {
+                Some(&registered_node_id) => id_mapping[node_id as usize] = registered_node_id,
+                None => unregistered.push((ip, node_id)),
+            }
+        }
+
+        if canonical_order {
+            unregistered.sort_unstable();
+        } else {
+            unregistered.sort_unstable_by_key(|&(_, node_id)| node_id);
+        }
+
+        let max_registered_node_id = registry.values().max().copied().unwrap_or(0);
+        for (position, &(_, node_id)) in unregistered.iter().enumerate() {
+            id_mapping[node_id as usize] = max_registered_node_id + position as u64 + 1;
+        }
+
+        id_mapping
+    }
+
+    /**
+     * Returns the final (node ID, IP) pairs, sorted by node ID.
+     */
+    fn apply_id_mapping(index: HashMap<u128, u64>, id_mapping: Option<&[u64]>) -> Vec<(u64, u128)> {
+        let mut nodes: Vec<(u64, u128)> = index.into_iter()
+            .map(|(ip, node_id)| match id_mapping {
+                Some(mapping) => (mapping[node_id as usize], ip),
+                None => (node_id, ip),
+            })
+            .collect();
+        nodes.sort_unstable();
+
+        nodes
+    }
+
+    /**
+     * Adds the new nodes of this run with their final IDs to the registry, registered IDs are never
+     * changed. Returns the (node ID, IP) pairs, sorted by node ID.
+     */
+    fn update_registry(mut registry: HashMap<u128, u64>, nodes: &[(u64, u128)]) -> Vec<(u64, u128)> {
+        for &(node_id, ip) in nodes {
+            registry.entry(ip).or_insert(node_id);
+        }
+
+        let mut updated_registry: Vec<(u64, u128)> = registry.into_iter()
+            .map(|(ip, node_id)| (node_id, ip))
+            .collect();
+        updated_registry.sort_unstable();
+
+        updated_registry
+    }
+
+    fn write_node_mapping(&self, nodes: &[(u64, u128)], index_writer: &mut Writer<File>) -> usize {
         info!("Writing node mapping to disk...");
 
         index_writer.serialize(("ip", "node_id")).unwrap();
 
-        let mut max_node_id: u64 = 0;
-        index.iter()
-            .map(|(&ip, &node_id)| {
-                let ip_addr = if self.config.address_type == IpType::V4 {
-                    IpAddr::V4(Ipv4Addr::from(u32::try_from(ip).unwrap()))
-                } else {
-                    IpAddr::V6(Ipv6Addr::from(ip))
-                };
-                if node_id > max_node_id {
-                    max_node_id = node_id;
-                }
-                (ip_addr, node_id)
-            })
+        let max_node_id: u64 = nodes.last().map_or(0, |&(node_id, _)| node_id);
+        nodes.iter()
+            .map(|&(node_id, ip)| (node_registry::ip_from_numeric(ip, &self.config.address_type), node_id))
             .for_each(|row| index_writer.serialize(row).unwrap());
 
         index_writer.flush().unwrap();
         max_node_id as usize
     }
 
-    fn write_edge_mapping(&self, dirs_to_process: Vec<DirEntry>, edge_writer: &mut Writer<File>) -> usize {
+    fn write_edge_mapping(
+        &self,
+        dirs_to_process: Vec<DirEntry>,
+        id_mapping: Option<&[u64]>,
+        edge_writer: &mut Writer<File>
+    ) -> usize {
         let bucket_count = crate::buckets::bucket_manager::BUCKET_COUNT;
 
         info!(
@@ -129,11 +230,11 @@ impl Merger {
         let mut progress_bar = ProgressBar::new(bucket_count);
         progress_bar.set(0);
 
-        edge_writer.serialize(("from", "to")).unwrap();
+        edge_writer.serialize(("from", "to", "rtt", "rtt_delta")).unwrap();
 
-        let mut merge_processor = MergeProcessor::new(edge_writer);
+        let mut merge_processor = MergeProcessor::new(edge_writer, id_mapping);
         for bucket_id in 0..bucket_count {
-            let bucket_name = &format!("yarrp.{}.bin", bucket_id);
+            let bucket_name = &self.format.bucket_file_name(bucket_id);
 
             let files_to_process = dirs_to_process.iter()
                 .map(|dir| dir.path().join(Path::new(bucket_name)))
@@ -144,4 +245,64 @@ impl Merger {
         }
         merge_processor.max_unknown_node()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_of(entries: &[(u128, u64)]) -> HashMap<u128, u64> {
+        entries.iter().copied().collect()
+    }
+
+    fn final_ids(index: &HashMap<u128, u64>, id_mapping: &[u64]) -> Vec<(u128, u64)> {
+        let mut ids: Vec<(u128, u64)> = index.iter()
+            .map(|(&ip, &node_id)| (ip, id_mapping[node_id as usize]))
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn canonical_ids_do_not_depend_on_the_input_order() {
+        let first = index_of(&[(30, 1), (10, 2), (20, 3), (40, 4)]);
+        let second = index_of(&[(40, 1), (20, 2), (30, 3), (10, 4)]);
+        let no_registry = HashMap::new();
+
+        let first_ids = final_ids(&first, &Merger::final_id_mapping(&first, &no_registry, true));
+        let second_ids = final_ids(&second, &Merger::final_id_mapping(&second, &no_registry, true));
+
+        assert_eq!(first_ids, vec![(10, 1), (20, 2), (30, 3), (40, 4)]);
+        assert_eq!(first_ids, second_ids);
+    }
+
+    #[test]
+    fn registered_ids_are_kept_and_new_ids_follow_them() {
+        let registry = index_of(&[(20, 7), (99, 9)]);
+        // the registered IP got its ID during preprocessing, the new ones were numbered after it
+        let index = index_of(&[(30, 10), (20, 7), (10, 11)]);
+
+        let id_mapping = Merger::final_id_mapping(&index, &registry, true);
+
+        assert_eq!(final_ids(&index, &id_mapping), vec![(10, 10), (20, 7), (30, 11)]);
+    }
+
+    #[test]
+    fn rerunning_the_merge_keeps_the_ids_of_the_first_run() {
+        let registry = index_of(&[(20, 1)]);
+        let index = index_of(&[(20, 1), (40, 2), (30, 3), (10, 4)]);
+
+        let id_mapping = Merger::final_id_mapping(&index, &registry, true);
+        let nodes = Merger::apply_id_mapping(index.clone(), Some(&id_mapping));
+        let updated_registry: HashMap<u128, u64> = Merger::update_registry(registry, &nodes).into_iter()
+            .map(|(node_id, ip)| (ip, node_id))
+            .collect();
+        assert_eq!(final_ids(&index, &id_mapping), vec![(10, 2), (20, 1), (30, 3), (40, 4)]);
+
+        // the rerun reads the same preprocessing IDs, but the registry written by the first run
+        let rerun_mapping = Merger::final_id_mapping(&index, &updated_registry, true);
+        let rerun_nodes = Merger::apply_id_mapping(index.clone(), Some(&rerun_mapping));
+        assert_eq!(rerun_mapping, id_mapping);
+        assert_eq!(Merger::update_registry(updated_registry, &rerun_nodes), nodes);
+    }
+}
@@ -2,3 +2,5 @@ pub mod parser;
 pub mod yarrp_data_preprocessor;
 pub mod file_util;
 pub mod warts_data_preprocessor;
+pub mod intermediate_store;
+pub mod node_registry;
@@ -42,9 +42,9 @@ pub fn read_bzip2_lines<P>(filename: P) -> Option<String>
 
 pub fn read_warts_from_gzip(path: PathBuf) -> Vec<Object> {
     let compressed_bytes = fs::read(path).unwrap();
-    let file_bytes: Vec<u8> = GzDecoder::new(compressed_bytes.as_slice())
-        .bytes()
-        .map(|i| i.unwrap())
-        .collect();
+    let mut file_bytes: Vec<u8> = Vec::new();
+    GzDecoder::new(compressed_bytes.as_slice())
+        .read_to_end(&mut file_bytes)
+        .unwrap();
     Object::all_from_bytes(file_bytes.as_slice())
 }
\ No newline at end of file
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::common::parameters;
+use crate::preprocess::file_util;
+
+// Marker file that flags an intermediate directory as completely processed.
+const COMPLETION_MARKER_FILENAME: &str = ".complete";
+
+/**
+ * Creates an intermediate directory for the given input file.
+ * Directories of input files that were not processed completely (e.g. because the previous run
+ * crashed) are wiped and created anew.
+ * Returns (intermediate_path: PathBuf, was_newly_created: bool)
+ */
+pub fn create_intermediate_path(intermediate_path: &Path, suffix: &str) -> (PathBuf, bool) {
+    let path: PathBuf = intermediate_path.join(Path::new(suffix));
+
+    if path.exists() && !is_complete(&path) {
+        info!("Found incomplete intermediate data at {:?}, processing the file again.", path);
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    let path_is_new = !path.exists();
+    if path_is_new {
+        fs::create_dir_all(&path).unwrap();
+    }
+    (path, path_is_new)
+}
+
+/**
+ * Flags the given intermediate directory as completely processed.
+ */
+pub fn mark_as_complete(path: &Path) {
+    File::create(path.join(COMPLETION_MARKER_FILENAME)).unwrap();
+}
+
+pub fn is_complete(path: &Path) -> bool {
+    path.join(COMPLETION_MARKER_FILENAME).exists()
+}
+
+/**
+ * Loads the node index of a previous (interrupted) run, so that resumed runs keep assigning the
+ * same IDs. Returns the index and the next free node ID, which also takes the IDs of the given
+ * node ID registry into account.
+ */
+#[allow(clippy::expect_fun_call)]
+pub fn load_node_index(intermediate_path: &Path, registry: &HashMap<u128, u64>) -> (HashMap<u128, u64>, u64) {
+    let node_index_path = node_index_path(intermediate_path);
+
+    let index: HashMap<u128, u64> = match File::open(&node_index_path) {
+        Ok(file) => bincode::deserialize_from(file).expect(&format!(
+            "File at {} does not contain or contains invalid node index data",
+            node_index_path.to_str().unwrap()
+        )),
+        Err(_) => HashMap::new(),
+    };
+
+    // 0 is reserved for the source IP
+    let counter = index.values()
+        .chain(registry.values())
+        .max()
+        .map_or(1, |&max_id| max_id + 1);
+
+    (index, counter)
+}
+
+pub fn store_node_index(intermediate_path: &Path, index: &HashMap<u128, u64>) {
+    file_util::write_binary_to_file(&node_index_path(intermediate_path), index);
+}
+
+pub fn node_index_path(intermediate_path: &Path) -> PathBuf {
+    intermediate_path.join(Path::new(parameters::NODE_INDEX_FILENAME))
+}
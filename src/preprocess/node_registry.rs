@@ -0,0 +1,115 @@
+/*!
+ * Persistent IP-to-NodeID registry that allows keeping node IDs stable across snapshots.
+ * The registry is either a CSV file in the format of mapping.csv (ip,node_id) or a binary node
+ * index as stored in the intermediate folder. The format is chosen by the file extension.
+ */
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::process::exit;
+
+use log::{error, info};
+use serde::Deserialize;
+
+use crate::{DatasetConfig, IpType};
+use crate::preprocess::file_util;
+use crate::preprocess::parser::{ipv4_to_numeric, ipv6_to_numeric};
+
+#[derive(Deserialize)]
+struct RegistryRow {
+    ip: IpAddr,
+    node_id: u64,
+}
+
+/**
+ * Loads the registry configured for the dataset, or returns an empty one if none is configured.
+ */
+pub fn load_configured_registry(config: &DatasetConfig) -> HashMap<u128, u64> {
+    match &config.id_registry_path {
+        Some(registry_path) => load_registry(registry_path, &config.address_type),
+        None => HashMap::new(),
+    }
+}
+
+/**
+ * Loads the registry at the given path. If the file does not exist (yet), an empty registry
+ * is returned, so the first run of a series can already point to the registry.
+ */
+#[allow(clippy::expect_fun_call)]
+pub fn load_registry(path: &Path, address_type: &IpType) -> HashMap<u128, u64> {
+    if !path.exists() {
+        info!("No node ID registry found at {:?}, starting with an empty one.", path);
+        return HashMap::new();
+    }
+
+    info!("Loading node ID registry from {:?}...", path);
+    let registry = if is_csv(path) {
+        load_csv_registry(path, address_type)
+    } else {
+        let file = File::open(path).unwrap();
+        bincode::deserialize_from(file).expect(&format!(
+            "File at {} does not contain or contains invalid node index data",
+            path.to_str().unwrap()
+        ))
+    };
+    info!("Loaded {} registered nodes.", registry.len());
+
+    registry
+}
+
+#[allow(clippy::expect_fun_call)]
+fn load_csv_registry(path: &Path, address_type: &IpType) -> HashMap<u128, u64> {
+    let mut reader = csv::Reader::from_path(path).unwrap();
+
+    reader.deserialize()
+        .map(|row: Result<RegistryRow, _>| {
+            let row = row.expect(&format!("Invalid row in node ID registry {}", path.to_str().unwrap()));
+            let ip = match (row.ip, address_type) {
+                (IpAddr::V4(ip), IpType::V4) => u128::from(ipv4_to_numeric(ip)),
+                (IpAddr::V6(ip), IpType::V6) => ipv6_to_numeric(ip),
+                (ip, _) => {
+                    error!("Expected IP{:?} addresses only in node ID registry, but got {}", address_type, ip);
+                    exit(1);
+                }
+            };
+            (ip, row.node_id)
+        })
+        .collect()
+}
+
+/**
+ * Writes the given (node ID, IP) pairs back to the registry at the given path.
+ */
+#[allow(clippy::expect_fun_call)]
+pub fn store_registry(path: &Path, nodes: &[(u64, u128)], address_type: &IpType) {
+    info!("Writing {} nodes to node ID registry at {:?}...", nodes.len(), path);
+
+    if is_csv(path) {
+        let mut writer = csv::Writer::from_path(path)
+            .expect(&format!("Could not create node ID registry at {}", path.to_str().unwrap()));
+        writer.serialize(("ip", "node_id")).unwrap();
+        for &(node_id, ip) in nodes {
+            writer.serialize((ip_from_numeric(ip, address_type), node_id)).unwrap();
+        }
+        writer.flush().unwrap();
+    } else {
+        let index: HashMap<u128, u64> = nodes.iter()
+            .map(|&(node_id, ip)| (ip, node_id))
+            .collect();
+        file_util::write_binary_to_file(&path.to_path_buf(), &index);
+    }
+}
+
+pub fn ip_from_numeric(ip: u128, address_type: &IpType) -> IpAddr {
+    if address_type == &IpType::V4 {
+        IpAddr::V4(Ipv4Addr::from(u32::try_from(ip).unwrap()))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(ip))
+    }
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "csv")
+}
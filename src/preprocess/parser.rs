@@ -12,9 +12,10 @@ use crate::IpType;
  * If an unexpected IP type is found, the program exits.
  */
 pub fn parse_data_into_memory(row: &String, memory: &mut GraphBucketManager, expected_ip_type: &IpType) {
-    let (raw_target_ip, raw_hop_count, raw_hop_ip) = extract_strings_from_row(row);
+    let (raw_target_ip, raw_hop_count, raw_hop_ip, raw_rtt) = extract_strings_from_row(row);
 
     let hop_count = hop_count_str_to_numeric(raw_hop_count);
+    let rtt = rtt_str_to_numeric(raw_rtt);
 
     let target_ip: IpAddr;
     let hop_ip: IpAddr;
@@ -43,9 +44,12 @@ pub fn parse_data_into_memory(row: &String, memory: &mut GraphBucketManager, exp
             }
 
             let _ = &memory.add_node_v4(NodeV4 {
+                source_ip: None,
                 target_ip: ipv4_to_numeric(target),
+                trace: 0,
                 hop_ip: ipv4_to_numeric(hop),
                 hop_count,
+                rtt,
             });
         },
         (IpAddr::V6(target), IpAddr::V6(hop)) => {
@@ -55,9 +59,12 @@ pub fn parse_data_into_memory(row: &String, memory: &mut GraphBucketManager, exp
             }
 
             let _ = &memory.add_node_v6(NodeV6 {
+                source_ip: None,
                 target_ip: ipv6_to_numeric(target),
+                trace: 0,
                 hop_ip: ipv6_to_numeric(hop),
                 hop_count,
+                rtt,
             });
         },
         _ => {
@@ -68,7 +75,8 @@ pub fn parse_data_into_memory(row: &String, memory: &mut GraphBucketManager, exp
     }
 }
 
-fn extract_strings_from_row(row: &String) -> (&str, &str, &str) {
+#[allow(clippy::needless_range_loop)]
+fn extract_strings_from_row(row: &String) -> (&str, &str, &str, &str) {
     // Why not &str.split? It is a lot slower than manually iterating it as done here.
     // That adds up quickly, so this "simple" manual space-skip parsing saves us a lot of time.
 
@@ -86,6 +94,7 @@ fn extract_strings_from_row(row: &String) -> (&str, &str, &str) {
     let mut hop_count_split_start = 0;
     let mut hop_count_split_end = 0;
     let mut hop_ip_split_end = 0;
+    let mut rtt_split_end = last_index + 1;
     for i in (target_ip_split + 1)..last_index {
         if ascii_row[i] == 0x20 {
             if spaces_to_skip == 0 {
@@ -93,8 +102,10 @@ fn extract_strings_from_row(row: &String) -> (&str, &str, &str) {
                     hop_count_split_start = i + 1;
                 } else if hop_count_split_end == 0 {
                     hop_count_split_end = i;
-                } else {
+                } else if hop_ip_split_end == 0 {
                     hop_ip_split_end = i;
+                } else {
+                    rtt_split_end = i;
                     break;
                 }
             } else {
@@ -109,12 +120,22 @@ fn extract_strings_from_row(row: &String) -> (&str, &str, &str) {
 
     let raw_hop_count = &row[hop_count_split_start..hop_count_split_end];
     let raw_hop_ip = &row[hop_count_split_end+1..hop_ip_split_end];
+    let raw_rtt = &row[hop_ip_split_end+1..rtt_split_end];
 
-    (raw_target_ip, raw_hop_count, raw_hop_ip)
+    (raw_target_ip, raw_hop_count, raw_hop_ip, raw_rtt)
 }
 
+#[allow(clippy::expect_fun_call)]
 fn hop_count_str_to_numeric(hop_count_str: &str) -> u8 {
-    u8::from_str(&hop_count_str).expect(&construct_error("hop count", hop_count_str))
+    u8::from_str(hop_count_str).expect(&construct_error("hop count", hop_count_str))
+}
+
+/**
+ * YARRP reports the RTT in microseconds. Unparseable values are treated as missing instead of
+ * failing the whole row, since the RTT is only used for the latency statistics.
+ */
+fn rtt_str_to_numeric(rtt_str: &str) -> Option<u32> {
+    u32::from_str(rtt_str).ok()
 }
 
 fn construct_error(data_label: &str, info: &str) -> String {
@@ -123,20 +144,27 @@ fn construct_error(data_label: &str, info: &str) -> String {
 
 pub fn ipv4_to_numeric(parsed_ip: Ipv4Addr) -> u32 {
     let mut shift = 4;
-    return parsed_ip.octets()
+    parsed_ip.octets()
         .iter()
         .fold(0, |ip, &e| {
             shift -= 1;
             ip | (u32::from(e) << (shift * 8))
-        });
+        })
 }
 
 pub fn ipv6_to_numeric(parsed_ip: Ipv6Addr) -> u128 {
     let mut shift = 16;
-    return parsed_ip.octets()
+    parsed_ip.octets()
         .iter()
         .fold(0, |ip, &e| {
             shift -= 1;
             ip | (u128::from(e) << (shift * 8))
-        });
-}
\ No newline at end of file
+        })
+}
+
+pub fn ip_to_numeric(parsed_ip: IpAddr) -> u128 {
+    match parsed_ip {
+        IpAddr::V4(ip) => u128::from(ipv4_to_numeric(ip)),
+        IpAddr::V6(ip) => ipv6_to_numeric(ip),
+    }
+}
@@ -1,41 +1,39 @@
+use std::collections::HashSet;
 use std::fs;
-use std::fs::{DirEntry, File};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::fs::DirEntry;
+use std::net::IpAddr;
 use std::os::unix::fs::MetadataExt;
-use csv::Writer;
-use hashbrown::HashMap;
-use log::{debug, info};
-use warts::{Address, Object};
-use crate::common::structs::parse_data::MaxNodeIds;
-use crate::{DatasetConfig, IpType, OutputPaths};
-use crate::preprocess::file_util;
+use std::path::PathBuf;
+use std::process::exit;
+
+use log::{debug, error, info};
+use pbr::ProgressBar;
+use warts::{Address, Object, Traceroute};
+
+use crate::buckets::bucket_manager::{BucketFormat, GraphBucketManager};
+use crate::common::structs::parse_data::{NodeV4, NodeV6};
+use crate::{DatasetConfig, IpType};
+use crate::preprocess::{file_util, intermediate_store, node_registry};
 use crate::preprocess::parser::{ipv4_to_numeric, ipv6_to_numeric};
 
 pub struct WartsDataPreprocessor {
     config: DatasetConfig,
-    output_paths: OutputPaths,
 }
 
 impl WartsDataPreprocessor {
-    pub fn new(config: &DatasetConfig, output_paths: &OutputPaths) -> WartsDataPreprocessor {
-        WartsDataPreprocessor {
-            config: config.clone(),
-            output_paths: output_paths.clone(),
-        }
+    pub fn new(config: &DatasetConfig) -> WartsDataPreprocessor {
+        WartsDataPreprocessor { config: config.clone() }
     }
 
     /**
-     * Assigns IDs to the IP nodes and generates an edge list out of the paths.
-     * The paths are then generated as an edge list CSV (from,to).
-     * Additionally, the IP-to-NodeID mapping is also stored to a CSV (IP,ID).
-     * (-> see also merger.rs)
+     * Joins the traceroutes into buckets and creates a node-to-IP mapping, in the same way
+     * as the YARRP preprocessing does (-> see also yarrp_data_preprocessor.rs).
+     * This step only generates binary files, no human-readable output is generated.
+     * The output of this step is required for the merger step.
+     * Files that were already processed completely in a previous run are skipped.
      *
      *  Requires: .warts.gz input files at dataset.warts.input_path
-     * Generates:
-     *     - edges.csv (edge list)
-     *     - mapping.csv (IP to ID mapping)
-     *     - max_node_ids.csv (maximum IDs assigned, both known and unknown)
-     * The edges and mapping are in no particular order.
+     * Generates: Intermediate binary files at dataset.warts.intermediate_path
      */
     pub fn preprocess_files(&self) {
         info!("Step: Preprocessing WARTS files.");
@@ -43,183 +41,177 @@ impl WartsDataPreprocessor {
 
         let input_path = &self.config.input_path;
 
-        let files = fs::read_dir(&input_path).unwrap();
+        let files = fs::read_dir(input_path).unwrap();
 
         let empty_file_size_bytes = 100;
-        let files_to_process: Vec<DirEntry> = files
+        let mut files_to_process: Vec<DirEntry> = files
             .map(|entry| entry.unwrap())
             .filter(|i| i.path().is_file())
             .filter(|i| i.path().to_str().unwrap().trim().ends_with(".warts.gz"))
             .filter(|i| i.metadata().unwrap().size() > empty_file_size_bytes)
             .collect();
 
-        let mapping_file_name = &self.output_paths.mapping;
-        let edges_file_name = &self.output_paths.edges;
-        let max_node_file_name = &self.output_paths.max_node_ids;
-
-        let index_writer = csv::Writer::from_path(mapping_file_name)
-            .expect(&format!(
-                "Could not create file for storing node mapping at {}", mapping_file_name.to_str().unwrap()
-            ));
-        let mut edge_writer = csv::Writer::from_path(edges_file_name)
-            .expect(&format!(
-                "Could not create file for storing edges at {}", edges_file_name.to_str().unwrap()
-            ));
-        let mut max_node_ids_writer = csv::Writer::from_path(max_node_file_name)
-            .expect(&format!(
-                "Could not create file for storing max node ids at {}", max_node_file_name.to_str().unwrap()
-            ));
-
-        let mut index: HashMap<u128, i64> = HashMap::new();
-        let mut counter: i64 = 0;
-        let mut missing_node_counter: i64 = -1;
-        let mut missing_node_memory: HashMap<i64, i64> = HashMap::new();
-
-        let mut file_processed_counter = 1;
-        edge_writer.serialize(("from", "to")).unwrap();
-        for file in &files_to_process {
-            info!("Processing {} / {} files", file_processed_counter, files_to_process.len());
-            self.process_single_file(
-                &file,
+        // read_dir does not guarantee any order, but the node IDs depend on the order of processing
+        files_to_process.sort_by_key(|entry| entry.file_name());
+
+        let file_count = files_to_process.len() as u64;
+        if file_count == 0 {
+            info!("Found no files to process. Proceeding with next step.");
+            return
+        }
+
+        info!("Found {} files.", file_count);
+
+        let mut progress_bar = ProgressBar::new(file_count);
+        progress_bar.set(0);
+
+        let intermediate_path = &self.config.intermediate_path;
+        let registry = node_registry::load_configured_registry(&self.config);
+        let (mut index, mut counter) = intermediate_store::load_node_index(intermediate_path, &registry);
+        for file in files_to_process {
+            let (path, path_is_new) = intermediate_store::create_intermediate_path(
+                intermediate_path,
+                file.file_name().to_str().unwrap()
+            );
+            if !path_is_new {
+                progress_bar.inc();
+                continue
+            }
+
+            let mut memory = GraphBucketManager::new(
+                path.clone(),
                 &mut index,
-                &mut counter,
-                &mut missing_node_counter,
-                &mut missing_node_memory,
-                &mut edge_writer
+                &registry,
+                counter,
+                BucketFormat::Warts,
             );
-            file_processed_counter += 1;
+            self.preprocess_single_file(file.path(), &mut memory);
+            counter = memory.id_counter();
+            memory.store_buckets_to_disk();
+
+            // The index is persisted after every file so that an interrupted run can be resumed
+            intermediate_store::store_node_index(intermediate_path, &index);
+            intermediate_store::mark_as_complete(&path);
+            progress_bar.inc();
         }
 
-        info!("Writing node mapping to disk...");
-        self.write_node_mapping_to_disk(index_writer, index);
-        Self::write_max_node_ids_to_disk(&mut max_node_ids_writer, counter, missing_node_counter);
+        info!("Processing of {} files completed.", file_count);
     }
 
-    /**
-     * Processes all the paths in one file and directly writes the edges to the output file.
-     * For missing hops, a negative ID is assigned. The ID is pinned to the starting point -
-     * for any edge A-B with a known A and an unknown B, the same negative ID is used for B.
-     */
-    fn process_single_file(
-        &self,
-        file: &DirEntry,
-        index: &mut HashMap<u128, i64>,
-        counter: &mut i64,
-        missing_node_counter: &mut i64,
-        missing_node_memory: &mut HashMap<i64, i64>,
-        edge_writer: &mut Writer<File>,
-    ) {
-        let objects = file_util::read_warts_from_gzip(file.path());
+    fn preprocess_single_file(&self, input_path: PathBuf, memory: &mut GraphBucketManager) {
+        let objects = file_util::read_warts_from_gzip(input_path);
+        let mut trace = 0;
         for object in objects {
             match object {
                 Object::Traceroute(t) => {
-                    let src_addr = uint_from_raw_address(t.src_addr.unwrap());
-
-                    let src_id = get_or_put(index, src_addr, counter);
-
-                    let mut previous_node = src_id;
-                    let mut previous_hop = 0;
-
-                    for hop in t.hops {
-                        let current_hop = hop.probe_ttl.unwrap();
-
-                        let hop_addr_object = hop.addr.unwrap();
-                        match hop_addr_object {
-                            Address::IPv4(_, _) => { /* we can proceed */ }
-                            Address::IPv6(_, _) => { /* we can proceed */ }
-                            Address::Reference(reference) => {
-                                debug!("Got REFERENCE for traceroute addr at TTL {}: {}", current_hop, reference);
-                                continue;
-                            }
-                            Address::Ethernet(e1, e2) => {
-                                debug!("Got ETHERNET for traceroute addr at TTL {}: {} {:?}", current_hop, e1, e2);
-                                continue;
-                            }
-                            Address::FireWire(f1, f2) => {
-                                debug!("Got FIREWIRE for traceroute addr at TTL {}: {} {:?}", current_hop, f1, f2);
-                                continue;
-                            }
-                        }
-                        let addr = uint_from_raw_address(hop_addr_object);
-                        let addr_id = get_or_put(index, addr, counter);
-
-                        if current_hop > previous_hop + 1 {
-                            let missing_hops = (current_hop - 1) - (previous_hop + 1);
-                            for _ in 0..missing_hops {
-                                if !missing_node_memory.contains_key(&previous_node) {
-                                    missing_node_memory.insert(previous_node, *missing_node_counter);
-                                    *missing_node_counter -= 1;
-                                }
-
-                                let new_node_id = *missing_node_memory.get(&previous_node).unwrap();
-
-                                edge_writer.serialize((previous_node, new_node_id)).unwrap();
-                                previous_node = i64::try_from(new_node_id).unwrap();
-                                previous_hop += 1;
-                            }
-                        }
-
-                        edge_writer.serialize((previous_node, addr_id)).unwrap();
-                        previous_node = i64::try_from(addr_id).unwrap();
-                        previous_hop = current_hop;
-                    }
-                }
+                    trace += 1;
+                    self.parse_traceroute_into_memory(t, trace, memory)
+                },
                 _ => debug!("Encountered non-traceroute entry: {:?}", object)
             }
         }
     }
 
     /**
-     * Writes the node mapping to a CSV file.
+     * Feeds the hops of one traceroute into the given graph bucket manager. The hops are keyed by
+     * the source and destination address and the number of the traceroute within its file, so
+     * the path starts at the source address and is never merged with another traceroute.
+     * Hops without an IP address (references, ethernet, firewire) are skipped, as are further
+     * replies to a TTL that already has a hop.
      */
-    fn write_node_mapping_to_disk(&self, mut index_writer: Writer<File>, index: HashMap<u128, i64>) {
-        index_writer.serialize(("ip", "node_id")).unwrap();
-        index.iter()
-            .map(|(&ip, &node_id)| {
-                let ip_addr = if self.config.address_type == IpType::V4 {
-                    IpAddr::V4(Ipv4Addr::from(u32::try_from(ip).unwrap()))
-                } else {
-                    IpAddr::V6(Ipv6Addr::from(ip))
-                };
-                (ip_addr, node_id)
-            })
-            .for_each(|row| index_writer.serialize(row).unwrap());
-
-        index_writer.flush().unwrap();
+    fn parse_traceroute_into_memory(&self, t: Traceroute, trace: u32, memory: &mut GraphBucketManager) {
+        let (src_addr, dst_addr) = match (t.src_addr, t.dst_addr) {
+            (Some(src), Some(dst)) if is_ip_address(&src) && is_ip_address(&dst) => (src, dst),
+            _ => {
+                debug!("Skipping traceroute without source or destination IP address");
+                return
+            }
+        };
+
+        let route = (IpAddr::from(src_addr), IpAddr::from(dst_addr), trace);
+
+        let mut seen_ttls = HashSet::new();
+        for hop in t.hops {
+            let current_hop = hop.probe_ttl.unwrap();
+            if !seen_ttls.insert(current_hop) {
+                continue;
+            }
+
+            let hop_addr_object = hop.addr.unwrap();
+            match hop_addr_object {
+                Address::IPv4(_, _) => { /* we can proceed */ }
+                Address::IPv6(_, _) => { /* we can proceed */ }
+                Address::Reference(reference) => {
+                    debug!("Got REFERENCE for traceroute addr at TTL {}: {}", current_hop, reference);
+                    continue;
+                }
+                Address::Ethernet(e1, e2) => {
+                    debug!("Got ETHERNET for traceroute addr at TTL {}: {} {:?}", current_hop, e1, e2);
+                    continue;
+                }
+                Address::FireWire(f1, f2) => {
+                    debug!("Got FIREWIRE for traceroute addr at TTL {}: {} {:?}", current_hop, f1, f2);
+                    continue;
+                }
+            }
+
+            let hop_ip = IpAddr::from(hop_addr_object);
+            self.add_hop(memory, route, hop_ip, current_hop, hop.rtt_usec);
+        }
     }
 
     /**
-     * Writes the max node IDs that were assigned to a separate file.
+     * Adds a hop of the traceroute identified by the route (source IP, target IP, trace number).
      */
-    fn write_max_node_ids_to_disk(max_node_ids_writer: &mut Writer<File>, counter: i64, missing_node_counter: i64) {
-        let max_node_ids = MaxNodeIds {
-            known: (counter - 1) as usize,
-            unknown: -(missing_node_counter + 1) as usize,
-        };
-        max_node_ids_writer.serialize(max_node_ids).unwrap();
-        max_node_ids_writer.flush().unwrap();
-    }
-}
+    fn add_hop(
+        &self,
+        memory: &mut GraphBucketManager,
+        route: (IpAddr, IpAddr, u32),
+        hop_ip: IpAddr,
+        hop_count: u8,
+        rtt: Option<u32>,
+    ) {
+        let (source_ip, target_ip, trace) = route;
+        match (source_ip, target_ip, hop_ip) {
+            (IpAddr::V4(source), IpAddr::V4(target), IpAddr::V4(hop)) => {
+                if self.config.address_type == IpType::V6 {
+                    error!("Expected IPV6 addresses only but got IPV4 addresses: target ip {} hop ip {}", target, hop);
+                    exit(1);
+                }
 
-fn get_or_put(index: &mut HashMap<u128, i64>, addr: u128, counter: &mut i64) -> i64 {
-    if index.contains_key(&addr) {
-        *index.get(&addr).unwrap()
-    } else {
-        let new_value = *counter;
-        (*counter) += 1;
-        index.insert(addr, new_value);
-        new_value
-    }
-}
+                memory.add_node_v4(NodeV4 {
+                    source_ip: Some(ipv4_to_numeric(source)),
+                    target_ip: ipv4_to_numeric(target),
+                    trace,
+                    hop_ip: ipv4_to_numeric(hop),
+                    hop_count,
+                    rtt,
+                });
+            },
+            (IpAddr::V6(source), IpAddr::V6(target), IpAddr::V6(hop)) => {
+                if self.config.address_type == IpType::V4 {
+                    error!("Expected IPV4 addresses only but got IPV6 addresses: target ip {} hop ip {}", target, hop);
+                    exit(1);
+                }
 
-fn uint_from_raw_address(address: Address) -> u128 {
-    let ip = IpAddr::from(address);
-    match ip {
-        IpAddr::V4(target) => {
-            u128::from(ipv4_to_numeric(target))
-        }
-        IpAddr::V6(target) => {
-            ipv6_to_numeric(target)
+                memory.add_node_v6(NodeV6 {
+                    source_ip: Some(ipv6_to_numeric(source)),
+                    target_ip: ipv6_to_numeric(target),
+                    trace,
+                    hop_ip: ipv6_to_numeric(hop),
+                    hop_count,
+                    rtt,
+                });
+            },
+            _ => {
+                error!("IP type mismatch: Encountered a route with IPs of 2 different/unknown types: \
+                        source ip {} target ip {} hop ip {}", source_ip, target_ip, hop_ip);
+                exit(1);
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+fn is_ip_address(address: &Address) -> bool {
+    matches!(address, Address::IPv4(_, _) | Address::IPv6(_, _))
+}
@@ -1,15 +1,13 @@
-use std::collections::HashMap;
 use std::fs;
 use std::fs::DirEntry;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 use log::{debug, info, trace};
 use pbr::ProgressBar;
-use crate::buckets::bucket_manager::GraphBucketManager;
-use crate::common::parameters;
+use crate::buckets::bucket_manager::{BucketFormat, GraphBucketManager};
 use crate::DatasetConfig;
 
-use crate::preprocess::{parser, file_util};
+use crate::preprocess::{parser, file_util, intermediate_store, node_registry};
 
 pub struct YarrpDataPreprocessor {
     config: DatasetConfig,
@@ -24,6 +22,7 @@ impl YarrpDataPreprocessor {
      * Joins the path into buckets and creates a node-to-IP mapping.
      * This step only generates binary files, no human-readable output is generated.
      * The output of this step is required for the merger step.
+     * Files that were already processed completely in a previous run are skipped.
      *
      *  Requires: Input files (either compressed as .yarrp.bz2 or uncompressed as .yarrp)
      *            at dataset.yarrp.input_path
@@ -40,7 +39,7 @@ impl YarrpDataPreprocessor {
         }
 
         let raw_files_list = fs::read_dir(&self.config.input_path).unwrap();
-        let files_to_process: Vec<DirEntry> = raw_files_list
+        let mut files_to_process: Vec<DirEntry> = raw_files_list
             .map(|entry| entry.unwrap())
             .filter(|i| i.path().is_file())
             .filter(|i| {
@@ -54,6 +53,9 @@ impl YarrpDataPreprocessor {
             })
             .collect();
 
+        // read_dir does not guarantee any order, but the node IDs depend on the order of processing
+        files_to_process.sort_by_key(|entry| entry.file_name());
+
         let file_count = files_to_process.len() as u64;
         if file_count == 0 {
             info!(
@@ -71,46 +73,39 @@ impl YarrpDataPreprocessor {
         let mut progress_bar = ProgressBar::new(file_count);
         progress_bar.set(0);
 
-        let mut index: HashMap<u128, u64> = HashMap::new();
-        let mut counter = 1; // 0 is reserved for the source IP
+        let intermediate_path = &self.config.intermediate_path;
+        let registry = node_registry::load_configured_registry(&self.config);
+        let (mut index, mut counter) = intermediate_store::load_node_index(intermediate_path, &registry);
         for file in files_to_process {
-            let (path, path_is_new) = self.create_intermediate_path(file.file_name().to_str().unwrap());
+            let (path, path_is_new) = intermediate_store::create_intermediate_path(
+                intermediate_path,
+                file.file_name().to_str().unwrap()
+            );
             if !path_is_new {
                 progress_bar.inc();
                 continue
             }
 
             let mut memory = GraphBucketManager::new(
-                path,
+                path.clone(),
                 &mut index,
-                counter
+                &registry,
+                counter,
+                BucketFormat::Yarrp,
             );
             self.preprocess_single_file(file.path(), &mut memory);
             counter = memory.id_counter();
             memory.store_buckets_to_disk();
+
+            // The index is persisted after every file so that an interrupted run can be resumed
+            intermediate_store::store_node_index(intermediate_path, &index);
+            intermediate_store::mark_as_complete(&path);
             progress_bar.inc();
         }
 
-        self.store_index_to_disk(index);
-
         info!("Processing of {} files completed.", file_count);
     }
 
-    /**
-     * Creates an intermediate path
-     * Returns (intermediate_path: PathBuf, was_newly_created: bool)
-     */
-    fn create_intermediate_path(&mut self, suffix: &str) -> (PathBuf, bool) {
-        let path: PathBuf = self.config.intermediate_path.join(
-            Path::new(suffix),
-        );
-        let path_is_new = !path.exists();
-        if path_is_new {
-            fs::create_dir_all(&path).unwrap();
-        }
-        (path, path_is_new)
-    }
-
     fn preprocess_single_file(&self, input_path: PathBuf, memory: &mut GraphBucketManager) {
         trace!("Reading in input file...");
         let raw_rows = self.read_lines(&input_path);
@@ -139,11 +134,4 @@ impl YarrpDataPreprocessor {
             .map(str::to_string)
             .collect()
     }
-
-    fn store_index_to_disk(&self, index: HashMap<u128, u64>) {
-        let node_index_path = self.config.intermediate_path.join(
-            Path::new(parameters::NODE_INDEX_FILENAME)
-        );
-        file_util::write_binary_to_file(&node_index_path, &index);
-    }
 }
\ No newline at end of file